@@ -0,0 +1,89 @@
+//! Throughput of the core loop: `cargo bench -p nes-core`.
+//!
+//! The workload is a small NROM program that keeps a page of RAM busy with
+//! indexed loads and stores, arithmetic and a subroutine call, so changes
+//! to the opcode table, the bus or per-instruction bookkeeping all show up.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use nes_core::nes::Nes;
+
+const INSTRUCTIONS: u64 = 10_000;
+
+#[rustfmt::skip]
+const PROGRAM: &[u8] = &[
+    0xA2, 0x00,       // C000: LDX #$00
+    0xBD, 0x00, 0x02, // C002: LDA $0200,X
+    0x18,             // C005: CLC
+    0x69, 0x03,       // C006: ADC #$03
+    0x9D, 0x00, 0x02, // C008: STA $0200,X
+    0x20, 0x20, 0xC0, // C00B: JSR $C020
+    0xE8,             // C00E: INX
+    0xD0, 0xF1,       // C00F: BNE $C002
+    0x4C, 0x00, 0xC0, // C011: JMP $C000
+];
+
+#[rustfmt::skip]
+const SUBROUTINE: &[u8] = &[
+    0x48,       // C020: PHA
+    0xA5, 0x10, // C021: LDA $10
+    0x49, 0xFF, // C023: EOR #$FF
+    0x85, 0x10, // C025: STA $10
+    0x68,       // C027: PLA
+    0x60,       // C028: RTS
+];
+
+fn rom() -> Vec<u8> {
+    let mut prg = vec![0xEA; 0x4000];
+    prg[..PROGRAM.len()].copy_from_slice(PROGRAM);
+    prg[0x20..0x20 + SUBROUTINE.len()].copy_from_slice(SUBROUTINE);
+    // reset vector
+    prg[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0xC0]);
+
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend(prg);
+    rom
+}
+
+fn nes() -> Nes {
+    let mut nes = Nes::builder().build();
+    nes.load_rom(&rom()).expect("benchmark ROM loads");
+    nes
+}
+
+fn instructions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cpu");
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    // the program loops forever, so one console serves every iteration
+    let mut nes = nes();
+    group.bench_function("instructions", |b| {
+        b.iter(|| {
+            for _ in 0..INSTRUCTIONS {
+                nes.cpu.clock().unwrap();
+                while nes.cpu.cycles > 0 {
+                    nes.cpu.clock().unwrap();
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+fn frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("nes");
+    group.throughput(Throughput::Elements(1));
+
+    let mut plain = nes();
+    group.bench_function("frame", |b| b.iter(|| plain.run_frame().unwrap()));
+
+    // the per-instruction bookkeeping the debugger tools add
+    let mut instrumented = nes();
+    instrumented.set_history_capacity(10_000);
+    instrumented.profiler.set_enabled(true);
+    instrumented.opcode_stats.set_enabled(true);
+    group.bench_function("frame_instrumented", |b| b.iter(|| instrumented.run_frame().unwrap()));
+
+    group.finish();
+}
+
+criterion_group!(benches, instructions, frames);
+criterion_main!(benches);
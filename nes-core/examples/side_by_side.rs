@@ -0,0 +1,55 @@
+//! Runs two consoles side by side in one process, the fast and accurate
+//! builds of the same game, and reports the first frame their memory
+//! differs on, the way an A/B comparison would.
+//!
+//! `cargo run --example side_by_side -- game.nes` compares a ROM; without
+//! one it uses a small built-in program.
+
+use std::env;
+use std::fs;
+
+use nes_core::nes::{Accuracy, Nes};
+
+// LDX #$00; loop: TXA; STA $0200,X; INX; JMP loop
+const PROGRAM: [u8; 10] = [0xA2, 0x00, 0x8A, 0x9D, 0x00, 0x02, 0xE8, 0x4C, 0x02, 0x06];
+
+const FRAMES: u64 = 600;
+
+fn build(accuracy: Accuracy, rom: Option<&[u8]>) -> Nes {
+    let mut nes = Nes::builder().accuracy(accuracy).build();
+    match rom {
+        Some(rom) => nes.load_rom(rom).unwrap(),
+        None => nes.load(&PROGRAM).unwrap(),
+    }
+    nes
+}
+
+fn memory(nes: &Nes) -> Vec<u8> {
+    let mut memory = vec![0; 0x10000];
+    nes.cpu.bus.peek_range(0, &mut memory);
+    memory
+}
+
+fn main() {
+    let rom = env::args().nth(1).map(|path| fs::read(path).unwrap());
+    let mut consoles = [build(Accuracy::Fast, rom.as_deref()), build(Accuracy::Accurate, rom.as_deref())];
+
+    for frame in 1..=FRAMES {
+        for nes in consoles.iter_mut() {
+            nes.run_frame().unwrap();
+        }
+
+        let [a, b] = &consoles;
+        let (a, b) = (memory(a), memory(b));
+        if let Some(addr) = a.iter().zip(&b).position(|(a, b)| a != b) {
+            println!("frame {}: ${:04X} is ${:02X} fast, ${:02X} accurate", frame, addr, a[addr], b[addr]);
+            return;
+        }
+        if consoles.iter().any(|nes| nes.cpu.complete) {
+            break;
+        }
+    }
+
+    let frames = consoles[0].frame_count();
+    println!("memory identical for {} frames", frames);
+}
@@ -0,0 +1,41 @@
+//! Runs the emulator on a worker thread and hands finished frames back to
+//! the main thread over a bounded channel, the way a threaded frontend would.
+
+use std::sync::mpsc;
+use std::thread;
+
+use nes_core::nes::Nes;
+
+// LDX #$00; loop: TXA; STA $0200,X; INX; JMP loop
+const PROGRAM: [u8; 10] = [0xA2, 0x00, 0x8A, 0x9D, 0x00, 0x02, 0xE8, 0x4C, 0x02, 0x06];
+
+const FRAMES: usize = 10;
+
+fn main() {
+    let mut nes = Nes::builder().build();
+    nes.load(&PROGRAM).unwrap();
+
+    // a capacity of 2 lets the worker run at most one frame ahead
+    let (frames_tx, frames_rx) = mpsc::sync_channel::<Vec<u8>>(2);
+
+    let worker = thread::spawn(move || {
+        for _ in 0..FRAMES {
+            nes.run_frame().unwrap();
+
+            let video = (0x0200..0x0600).map(|addr| nes.cpu.peek(addr)).collect();
+            if frames_tx.send(video).is_err() {
+                break;
+            }
+        }
+
+        nes
+    });
+
+    for (i, frame) in frames_rx.iter().enumerate() {
+        let checksum: u32 = frame.iter().map(|&b| b as u32).sum();
+        println!("frame {}: checksum {}", i, checksum);
+    }
+
+    let nes = worker.join().unwrap();
+    println!("worker finished after {} frames", nes.frame_count());
+}
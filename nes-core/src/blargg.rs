@@ -0,0 +1,87 @@
+//! Runs blargg-style test ROMs, which report through PRG RAM.
+//!
+//! `$6000` holds the status: `$80` while the test runs, `$81` when it
+//! wants the reset button pressed, and the result code once done, 0 for a
+//! pass. `$6001-$6003` hold the signature `DE B0 61` once the status is
+//! valid, and a NUL-terminated message starts at `$6004`.
+
+use alloc::string::String;
+
+use crate::cpu::CPU;
+use crate::error::EmuError;
+use crate::nes::Nes;
+
+const STATUS: u16 = 0x6000;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const MESSAGE: u16 = 0x6004;
+// the message area runs to the end of PRG RAM
+const MESSAGE_LEN: usize = 0x2000 - 4;
+
+const RUNNING: u8 = 0x80;
+const NEEDS_RESET: u8 = 0x81;
+// tests ask for the reset to come at least 100ms after the request
+const RESET_DELAY_FRAMES: u64 = 6;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Verdict {
+    Passed,
+    /// The test's result code, which it explains in the message.
+    Failed(u8),
+    /// No result within the frame limit, or the program stopped first.
+    TimedOut,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Report {
+    pub verdict: Verdict,
+    pub message: String,
+    pub frames: u64,
+}
+
+/// The status byte, once the test has written the signature.
+pub fn status(cpu: &CPU) -> Option<u8> {
+    let signature = [1, 2, 3].map(|i| cpu.peek(STATUS + i));
+    (signature == SIGNATURE).then(|| cpu.peek(STATUS))
+}
+
+/// The text the test has written so far.
+pub fn message(cpu: &CPU) -> String {
+    (0..MESSAGE_LEN as u16)
+        .map(|i| cpu.peek(MESSAGE + i))
+        .take_while(|&byte| byte != 0)
+        .map(char::from)
+        .collect()
+}
+
+/// Runs the loaded test ROM for up to `max_frames` frames, pressing reset
+/// whenever it asks.
+pub fn run(nes: &mut Nes, max_frames: u64) -> Result<Report, EmuError> {
+    let mut reset_requested = None;
+    let mut frames = 0;
+
+    while frames < max_frames {
+        nes.run_frame()?;
+        frames += 1;
+        if nes.cpu.complete || nes.debugger.is_stopped() {
+            break;
+        }
+
+        let verdict = match status(&nes.cpu) {
+            Some(RUNNING) | None => continue,
+            Some(NEEDS_RESET) => {
+                let requested = *reset_requested.get_or_insert(frames);
+                if frames - requested >= RESET_DELAY_FRAMES {
+                    reset_requested = None;
+                    nes.reset();
+                }
+                continue;
+            },
+            Some(0) => Verdict::Passed,
+            Some(code) => Verdict::Failed(code),
+        };
+
+        return Ok(Report { verdict, message: message(&nes.cpu), frames });
+    }
+
+    Ok(Report { verdict: Verdict::TimedOut, message: message(&nes.cpu), frames })
+}
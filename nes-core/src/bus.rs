@@ -0,0 +1,228 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+
+use crate::controller::Joypad;
+use crate::debug_port::DebugPort;
+use crate::debugger::Watchpoints;
+use crate::events::{EventKind, EventLog};
+use crate::history::Journal;
+use crate::irq::IrqLine;
+use crate::mapper::Mapper;
+use crate::vs::VsSystem;
+
+// the 8KB windows bank switches are logged for
+const PRG_WINDOWS: [u16; 4] = [0x8000, 0xA000, 0xC000, 0xE000];
+
+// the value floating on the data bus when reading $4016/$4017, left over
+// from the address's high byte
+const OPEN_BUS: u8 = 0x40;
+
+/// The CPU address space: a flat 64KB of RAM with the two controller
+/// ports at `$4016`/`$4017`.
+///
+/// Once a cartridge is inserted it takes over `$4020-$FFFF` and internal
+/// RAM is mirrored every 2KB up to `$1FFF`, as on the console.
+pub struct Bus {
+    pub ram: [u8; 64 * 1024],
+    pub controllers: [Joypad; 2],
+    pub cartridge: Option<Box<dyn Mapper>>,
+    pub watchpoints: Watchpoints,
+    /// The coin slots and DIP switches, when a VS System game is in.
+    pub vs: Option<VsSystem>,
+    /// The CPU's IRQ input; [`Nes`](crate::nes::Nes) takes the interrupt
+    /// while it's asserted.
+    pub irq: IrqLine,
+    /// A console port for homebrew to print through, when enabled.
+    pub debug_port: Option<DebugPort>,
+    /// DMA, interrupts and bank switches, when logging is on.
+    pub events: EventLog,
+    // whether the program read a controller port since this was cleared
+    pub(crate) polled: bool,
+    // bytes pinned by a memory tool, read back whatever is written
+    pub(crate) frozen: BTreeMap<u16, u8>,
+    // what the running instruction changed, while execution history records
+    pub(crate) journal: Option<Journal>,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus {
+    pub fn new() -> Bus {
+        Bus {
+            ram: [0; 64 * 1024],
+            controllers: [Joypad::new(); 2],
+            cartridge: None,
+            watchpoints: Watchpoints::default(),
+            vs: None,
+            irq: IrqLine::new(),
+            debug_port: None,
+            events: EventLog::new(),
+            polled: false,
+            frozen: BTreeMap::new(),
+            journal: None,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        if !self.watchpoints.is_empty() {
+            self.watchpoints.check(addr, data, true);
+        }
+
+        if let Some(port) = self.debug_port.as_mut().filter(|port| port.addr() == addr) {
+            port.write(data);
+            return;
+        }
+
+        if self.journal.is_some() {
+            let old = self.peek(addr);
+            if let Some(journal) = self.journal.as_mut() {
+                journal.record(addr, old, self.cartridge.as_deref());
+            }
+        }
+
+        match addr {
+            // both controllers share the strobe line
+            0x4016 => {
+                for controller in self.controllers.iter_mut() {
+                    controller.write(data);
+                }
+            },
+            0x4014 if self.events.is_enabled() => {
+                self.events.push(EventKind::OamDma { page: data });
+                let index = self.ram_index(addr);
+                self.ram[index] = data;
+            },
+            _ => match (self.cartridge.as_mut(), addr) {
+                (Some(cartridge), 0x4020..=0xFFFF) if self.events.is_enabled() => {
+                    let before = PRG_WINDOWS.map(|window| cartridge.prg_bank(window));
+                    cartridge.write(addr, data);
+                    for (window, before) in PRG_WINDOWS.into_iter().zip(before) {
+                        let bank = cartridge.prg_bank(window);
+                        if bank != before {
+                            self.events.push(EventKind::BankSwitch { addr: window, bank });
+                        }
+                    }
+                },
+                (Some(cartridge), 0x4020..=0xFFFF) => cartridge.write(addr, data),
+                _ => {
+                    let index = self.ram_index(addr);
+                    self.ram[index] = data;
+                },
+            },
+        }
+    }
+
+    pub fn read(&mut self, addr: u16) -> u8 {
+        if let 0x4016 | 0x4017 = addr {
+            self.polled = true;
+        }
+
+        let value = match (self.frozen.get(&addr), addr) {
+            (Some(&value), _) => value,
+            (None, 0x4016) => self.controllers[0].read() | self.port_bits(0),
+            (None, 0x4017) => self.controllers[1].read() | self.port_bits(1),
+            (None, _) => match (self.cartridge.as_mut(), addr) {
+                (Some(cartridge), 0x4020..=0xFFFF) => cartridge.read(addr),
+                _ => self.ram[self.ram_index(addr)],
+            },
+        };
+
+        if !self.watchpoints.is_empty() {
+            self.watchpoints.check(addr, value, false);
+        }
+
+        value
+    }
+
+    /// Reads without side effects (no controller shifting), for debuggers.
+    pub fn peek(&self, addr: u16) -> u8 {
+        if let Some(&value) = self.frozen.get(&addr) {
+            return value;
+        }
+
+        match addr {
+            0x4016 => self.controllers[0].peek() | self.port_bits(0),
+            0x4017 => self.controllers[1].peek() | self.port_bits(1),
+            _ => match (&self.cartridge, addr) {
+                (Some(cartridge), 0x4020..=0xFFFF) => cartridge.peek(addr),
+                _ => self.ram[self.ram_index(addr)],
+            },
+        }
+    }
+
+    /// Copies memory starting at `start` into `out` without side effects,
+    /// wrapping at `$FFFF`; for hex viewers.
+    pub fn peek_range(&self, start: u16, out: &mut [u8]) {
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = self.peek(start.wrapping_add(i as u16));
+        }
+    }
+
+    /// Writes a byte for memory editors, without tripping watchpoints.
+    /// The controller ports are left alone, and in cartridge space the PRG
+    /// RAM or ROM behind `addr` changes rather than any board register.
+    pub fn poke(&mut self, addr: u16, data: u8) {
+        match (self.cartridge.as_mut(), addr) {
+            (_, 0x4016 | 0x4017) => {},
+            (Some(cartridge), 0x4020..=0xFFFF) => cartridge.poke(addr, data),
+            _ => {
+                let index = self.ram_index(addr);
+                self.ram[index] = data;
+            },
+        }
+    }
+
+    /// Pins `addr` to `value`: reads return it whatever the program
+    /// writes. Replaces an earlier freeze of the same address.
+    pub fn freeze(&mut self, addr: u16, value: u8) {
+        self.frozen.insert(addr, value);
+    }
+
+    /// Returns false if `addr` wasn't frozen.
+    pub fn unfreeze(&mut self, addr: u16) -> bool {
+        self.frozen.remove(&addr).is_some()
+    }
+
+    pub fn clear_freezes(&mut self) {
+        self.frozen.clear();
+    }
+
+    /// Frozen addresses and their values, lowest address first.
+    pub fn frozen(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+        self.frozen.iter().map(|(&addr, &value)| (addr, value))
+    }
+
+    /// The cartridge's PRG bank at `addr`, 0 without a cartridge.
+    pub fn prg_bank(&self, addr: u16) -> u16 {
+        self.cartridge.as_ref().map_or(0, |cartridge| cartridge.prg_bank(addr))
+    }
+
+    /// Where in the cartridge's PRG ROM `addr` reads from, if anywhere.
+    pub fn prg_offset(&self, addr: u16) -> Option<usize> {
+        self.cartridge.as_ref().and_then(|cartridge| cartridge.prg_offset(addr))
+    }
+
+    /// Where in the cartridge's CHR ROM the PPU reads pattern table
+    /// address `addr` from, if it has CHR ROM.
+    pub fn chr_offset(&self, addr: u16) -> Option<usize> {
+        self.cartridge.as_ref().and_then(|cartridge| cartridge.chr_offset(addr))
+    }
+
+    // what the bits a controller doesn't drive read as: the cabinet's on a
+    // VS System, otherwise open bus, still holding the $40 high byte of
+    // the address that was just fetched
+    fn port_bits(&self, port: usize) -> u8 {
+        self.vs.map_or(OPEN_BUS, |vs| vs.port_bits(port))
+    }
+
+    fn ram_index(&self, addr: u16) -> usize {
+        match addr {
+            0x0800..=0x1FFF if self.cartridge.is_some() => (addr & 0x07FF) as usize,
+            _ => addr as usize,
+        }
+    }
+}
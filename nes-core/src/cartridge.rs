@@ -0,0 +1,302 @@
+use alloc::format;
+use alloc::vec::Vec;
+
+use crate::constants::Region;
+use crate::error::EmuError;
+
+pub const HEADER_MAGIC: &[u8; 4] = b"NES\x1A";
+
+const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+const PRG_BANK_SIZE: usize = 16 * 1024;
+const CHR_BANK_SIZE: usize = 8 * 1024;
+const INST_ROM_SIZE: usize = 8 * 1024;
+const PROM_SIZE: usize = 32;
+
+/// How the cartridge wires the PPU nametables.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    FourScreen,
+}
+
+/// The hardware a ROM was dumped for.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Console {
+    Nes,
+    /// A VS UniSystem arcade board.
+    VsSystem,
+    /// A PlayChoice-10 cabinet. The game runs as on a console; the
+    /// cabinet's own data is kept in [`Cartridge::playchoice`].
+    PlayChoice10,
+}
+
+impl Console {
+    fn from_header(header: &[u8]) -> Console {
+        match header[7] & 0x03 {
+            // in NES 2.0, 3 points at an extended type in byte 13
+            1 => Console::VsSystem,
+            2 => Console::PlayChoice10,
+            _ => Console::Nes,
+        }
+    }
+}
+
+/// What a PlayChoice-10 dump carries after CHR ROM for the cabinet's
+/// menu hardware, which isn't emulated.
+#[derive(Clone, Debug)]
+pub struct PlayChoiceData {
+    /// The Z80 instruction ROM with the game's menu text.
+    pub inst_rom: Vec<u8>,
+    /// The key PROM, when the dump has it.
+    pub prom: Option<Vec<u8>>,
+}
+
+/// Where PRG ROM and CHR ROM start in an iNES file.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct RomLayout {
+    pub prg: usize,
+    pub chr: usize,
+}
+
+/// A ROM image parsed from an iNES or NES 2.0 file.
+#[derive(Clone, Debug)]
+pub struct Cartridge {
+    pub mapper: u16,
+    pub submapper: u8,
+    pub nes2: bool,
+    pub region: Region,
+    pub console: Console,
+    pub mirroring: Mirroring,
+    /// Battery-backed PRG RAM.
+    pub battery: bool,
+    pub prg_ram_size: usize,
+    pub trainer: Option<Vec<u8>>,
+    pub prg_rom: Vec<u8>,
+    pub chr_rom: Vec<u8>,
+    pub playchoice: Option<PlayChoiceData>,
+}
+
+impl Cartridge {
+    /// Returns true if `data` starts with the iNES magic.
+    pub fn is_ines(data: &[u8]) -> bool {
+        data.starts_with(HEADER_MAGIC)
+    }
+
+    pub fn from_ines(data: &[u8]) -> Result<Cartridge, EmuError> {
+        if data.len() < HEADER_SIZE || !Cartridge::is_ines(data) {
+            return Err(EmuError::InvalidRom("missing iNES header".into()));
+        }
+
+        let header = &data[..HEADER_SIZE];
+        let nes2 = (header[7] & 0x0C) == 0x08;
+
+        let mut mapper = ((header[6] >> 4) | (header[7] & 0xF0)) as u16;
+        let mut submapper = 0;
+        let prg_rom_size;
+        let chr_rom_size;
+        let prg_ram_size;
+
+        if nes2 {
+            mapper |= ((header[8] & 0x0F) as u16) << 8;
+            submapper = header[8] >> 4;
+            prg_rom_size = nes2_rom_size(header[4], header[9] & 0x0F, PRG_BANK_SIZE, "PRG ROM")?;
+            chr_rom_size = nes2_rom_size(header[5], header[9] >> 4, CHR_BANK_SIZE, "CHR ROM")?;
+            prg_ram_size = nes2_ram_size(header[10] & 0x0F) + nes2_ram_size(header[10] >> 4);
+        } else {
+            prg_rom_size = header[4] as usize * PRG_BANK_SIZE;
+            chr_rom_size = header[5] as usize * CHR_BANK_SIZE;
+            // 0 means 8KB for compatibility with old dumps
+            prg_ram_size = header[8].max(1) as usize * 8 * 1024;
+        }
+
+        let mirroring = if (header[6] & 0x08) != 0 {
+            Mirroring::FourScreen
+        } else if (header[6] & 0x01) != 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        };
+
+        let mut offset = HEADER_SIZE;
+
+        let trainer = if (header[6] & 0x04) != 0 {
+            let trainer = slice(data, offset, TRAINER_SIZE, "trainer")?.to_vec();
+            offset += TRAINER_SIZE;
+            Some(trainer)
+        } else {
+            None
+        };
+
+        let prg_rom = slice(data, offset, prg_rom_size, "PRG ROM")?.to_vec();
+        offset += prg_rom_size;
+        let chr_rom = slice(data, offset, chr_rom_size, "CHR ROM")?.to_vec();
+        offset += chr_rom_size;
+
+        let console = Console::from_header(header);
+        // many dumps leave the PROM off, or the whole cabinet part
+        let playchoice = match console {
+            Console::PlayChoice10 => data.get(offset..offset + INST_ROM_SIZE).map(|inst_rom| {
+                let prom = data.get(offset + INST_ROM_SIZE..offset + INST_ROM_SIZE + PROM_SIZE);
+                PlayChoiceData { inst_rom: inst_rom.to_vec(), prom: prom.map(<[u8]>::to_vec) }
+            }),
+            _ => None,
+        };
+
+        if prg_rom.is_empty() {
+            return Err(EmuError::InvalidRom("no PRG ROM".into()));
+        }
+
+        Ok(Cartridge {
+            mapper,
+            submapper,
+            nes2,
+            region: Region::from_header(header),
+            console,
+            mirroring,
+            battery: (header[6] & 0x02) != 0,
+            prg_ram_size,
+            trainer,
+            prg_rom,
+            chr_rom,
+            playchoice,
+        })
+    }
+
+    /// Where its ROMs start in its iNES file, past the header and any
+    /// trainer.
+    pub fn layout(&self) -> RomLayout {
+        let prg = HEADER_SIZE + self.trainer.as_ref().map_or(0, |_| TRAINER_SIZE);
+        RomLayout { prg, chr: prg + self.prg_rom.len() }
+    }
+
+    /// CRC-32 of the PRG and CHR data, which identifies a game whatever
+    /// its header says.
+    pub fn crc32(&self) -> u32 {
+        let crc = crc32_update(0xFFFF_FFFF, &self.prg_rom);
+        !crc32_update(crc, &self.chr_rom)
+    }
+}
+
+/// The standard (zlib) CRC-32.
+pub fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(0xFFFF_FFFF, data)
+}
+
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    crc
+}
+
+fn slice<'a>(data: &'a [u8], offset: usize, len: usize, what: &str) -> Result<&'a [u8], EmuError> {
+    offset.checked_add(len).and_then(|end| data.get(offset..end)).ok_or_else(|| {
+        EmuError::InvalidRom(format!("{} truncated: needs {} bytes at offset {}", what, len, offset))
+    })
+}
+
+// NES 2.0 sizes are either a 12-bit bank count or, with an MSB nibble of
+// 0xF, an exponent-multiplier pair; the exponent goes up to 63, so a
+// malformed header can ask for more than fits in a usize
+fn nes2_rom_size(lsb: u8, msb: u8, bank_size: usize, what: &str) -> Result<usize, EmuError> {
+    let size = if msb == 0x0F {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0x03) as usize * 2 + 1;
+        1usize.checked_shl(exponent).and_then(|size| size.checked_mul(multiplier))
+    } else {
+        (((msb as usize) << 8) | lsb as usize).checked_mul(bank_size)
+    };
+    size.ok_or_else(|| EmuError::InvalidRom(format!("{} size in the NES 2.0 header is too large", what)))
+}
+
+fn nes2_ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    // an NES 2.0 header with PRG ROM size bytes `lsb` and `msb`, followed
+    // by `prg` bytes of data
+    fn nes2(lsb: u8, msb: u8, prg: usize) -> Vec<u8> {
+        let mut rom = vec![b'N', b'E', b'S', 0x1A, lsb, 0, 0, 0x08, 0, msb, 0, 0, 0, 0, 0, 0];
+        rom.resize(HEADER_SIZE + prg, 0xEA);
+        rom
+    }
+
+    #[test]
+    fn inserting_switches_to_the_cartridges_region() {
+        use crate::nes::Nes;
+
+        // iNES 1.0 with the PAL bit set in byte 9
+        let mut pal = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0, 0, 0, 0x01, 0, 0, 0, 0, 0, 0];
+        pal.resize(HEADER_SIZE + 16 * 1024, 0xEA);
+
+        let mut nes = Nes::builder().build();
+        nes.load_rom(&pal).unwrap();
+        assert_eq!(nes.region(), Region::Pal);
+        // a raw program has no header, so it's back to the builder's
+        nes.load(&[0xEA]).unwrap();
+        assert_eq!(nes.region(), Region::Ntsc);
+
+        let mut pinned = Nes::builder().region(Region::Dendy).pin_region(true).build();
+        pinned.load_rom(&pal).unwrap();
+        assert_eq!(pinned.region(), Region::Dendy);
+    }
+
+    #[test]
+    fn inserting_drops_the_last_programs_freezes() {
+        use crate::nes::Nes;
+
+        let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        rom.resize(HEADER_SIZE + 16 * 1024, 0xEA);
+
+        let mut nes = Nes::builder().build();
+        nes.load_rom(&rom).unwrap();
+        nes.cpu.bus.freeze(0x0010, 9);
+        nes.power_cycle();
+        assert_eq!(nes.cpu.bus.frozen().count(), 1);
+
+        nes.load_rom(&rom).unwrap();
+        assert_eq!(nes.cpu.bus.frozen().count(), 0);
+    }
+
+    #[test]
+    fn reads_exponent_sizes() {
+        // 2^3 * 3 = 24 bytes
+        let cartridge = Cartridge::from_ines(&nes2(3 << 2 | 1, 0x0F, 24)).unwrap();
+        assert!(cartridge.nes2);
+        assert_eq!(cartridge.prg_rom.len(), 24);
+    }
+
+    #[test]
+    fn rejects_sizes_that_overflow() {
+        // 2^63 * 7 bytes
+        let error = Cartridge::from_ines(&nes2(0xFF, 0x0F, 16)).unwrap_err();
+        assert!(matches!(error, EmuError::InvalidRom(_)));
+
+        // 2^40 bytes fits in a usize but not in the file
+        let error = Cartridge::from_ines(&nes2(40 << 2, 0x0F, 16)).unwrap_err();
+        assert!(matches!(error, EmuError::InvalidRom(_)));
+    }
+
+    #[test]
+    fn rejects_truncated_images() {
+        let error = Cartridge::from_ines(&nes2(1, 0, 100)).unwrap_err();
+        assert!(matches!(error, EmuError::InvalidRom(_)));
+        assert!(Cartridge::from_ines(b"NES").is_err());
+    }
+}
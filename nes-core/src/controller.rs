@@ -0,0 +1,107 @@
+use crate::error::EmuError;
+use crate::state::{StateReader, StateWriter};
+
+/// Buttons on a standard controller, in the order they are shifted out.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub enum Button {
+    A,
+    B,
+    Select,
+    Start,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Button {
+    pub const ALL: [Button; 8] = [
+        Button::A,
+        Button::B,
+        Button::Select,
+        Button::Start,
+        Button::Up,
+        Button::Down,
+        Button::Left,
+        Button::Right,
+    ];
+
+    pub fn mask(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
+/// A standard controller behind `$4016`/`$4017`: a latch loaded while
+/// strobe is high and shifted out one bit per read.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Joypad {
+    buttons: u8,
+    shift: u8,
+    strobe: bool,
+}
+
+impl Joypad {
+    pub fn new() -> Joypad {
+        Joypad::default()
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        if pressed {
+            self.buttons |= button.mask();
+        } else {
+            self.buttons &= !button.mask();
+        }
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.buttons & button.mask() != 0
+    }
+
+    /// All buttons as a bitmask, `A` in bit 0.
+    pub fn buttons(&self) -> u8 {
+        self.buttons
+    }
+
+    pub fn set_buttons(&mut self, buttons: u8) {
+        self.buttons = buttons;
+    }
+
+    pub fn write(&mut self, data: u8) {
+        self.strobe = data & 0x01 != 0;
+        if self.strobe {
+            self.shift = self.buttons;
+        }
+    }
+
+    pub fn read(&mut self) -> u8 {
+        if self.strobe {
+            return self.buttons & 0x01;
+        }
+
+        let bit = self.shift & 0x01;
+        // after eight reads an official controller returns 1s
+        self.shift = (self.shift >> 1) | 0x80;
+        bit
+    }
+
+    pub fn peek(&self) -> u8 {
+        if self.strobe {
+            self.buttons & 0x01
+        } else {
+            self.shift & 0x01
+        }
+    }
+
+    pub(crate) fn save_state(&self, state: &mut StateWriter) {
+        state.u8(self.buttons);
+        state.u8(self.shift);
+        state.bool(self.strobe);
+    }
+
+    pub(crate) fn load_state(&mut self, state: &mut StateReader) -> Result<(), EmuError> {
+        self.buttons = state.u8()?;
+        self.shift = state.u8()?;
+        self.strobe = state.bool()?;
+        Ok(())
+    }
+}
@@ -1,29 +1,85 @@
+use alloc::boxed::Box;
+use core::fmt;
+
 use crate::bus::Bus;
+use crate::debugger::{CallFrame, CallStack, FrameKind};
+#[cfg(feature = "match-dispatch")]
+use crate::dispatch;
+use crate::error::EmuError;
+use crate::events::EventKind;
+use crate::hooks::{Hooks, Instruction, Interrupt};
+use crate::trace::{self, TraceEntry, TraceFilter, TraceSink};
 use crate::constants::{
     AddressingMode,
+    OpCode,
     Status,
     OPCODES,
-    OpCode
+    Region,
+    JAM_OPCODES,
 };
 
 
+/// The CPU registers and cycle counters at one moment, copied out so
+/// debuggers, tests and loggers can keep them around.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct CpuState {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub stack_pointer: u8,
+    pub program_counter: u16,
+    /// The flags as pushed by `PHP`; see [`Status::from_byte`].
+    pub status: u8,
+    pub cycles: u64,
+    pub total_cycles: u64,
+}
+
+impl CpuState {
+    pub fn flags(&self) -> Status {
+        Status::from_byte(self.status)
+    }
+}
+
+impl fmt::Display for CpuState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PC:{:04X} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+            self.program_counter, self.a, self.x, self.y, self.status, self.stack_pointer, self.total_cycles
+        )
+    }
+}
+
+/// A 6502 core (the NES 2A03 without decimal mode) wired to a [`Bus`].
 pub struct CPU {
     pub bus: Bus,
+    pub region: Region,
     pub status: Status,
     pub a: u8,
     pub x: u8,
     pub y: u8,
     pub stack_pointer: u8,
     pub program_counter: u16,
+    /// Set once a `BRK` has been executed.
     pub complete: bool,
 
+    /// Cycles left before the current instruction completes.
     pub cycles: u64,
+    /// Cycles run since power-on.
+    pub total_cycles: u64,
+
+    pub hooks: Hooks,
+    pub call_stack: CallStack,
+
+    trace_sink: Option<Box<dyn TraceSink + Send>>,
+    trace_filter: TraceFilter,
 }
 
 impl CPU {
     pub fn new(bus: Bus) -> CPU {
         CPU {
-            bus: bus,
+            bus,
+            region: Region::Ntsc,
             status: Status::from_byte(0b100100),
             a: 0x00,
             x: 0x00,
@@ -31,157 +87,296 @@ impl CPU {
             stack_pointer: 0xFD,
             program_counter: 0x0000,
             cycles: 0,
+            total_cycles: 0,
             complete: false,
+            hooks: Hooks::new(),
+            call_stack: CallStack::default(),
+            trace_sink: None,
+            trace_filter: TraceFilter::default(),
+        }
+    }
+
+    pub fn read(&mut self, addr: u16) -> u8 {
+        let data = self.bus.read(addr);
+
+        if self.hooks.watches_reads() {
+            self.hooks.memory_read(addr, data);
         }
+
+        data
     }
 
-    pub fn read(&self, addr: u16) -> u8 {
-        return self.bus.read(addr, false);
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            stack_pointer: self.stack_pointer,
+            program_counter: self.program_counter,
+            status: self.status.to_byte(),
+            cycles: self.cycles,
+            total_cycles: self.total_cycles,
+        }
+    }
+
+    /// Reads without side effects or hooks, for debuggers and frontends.
+    pub fn peek(&self, addr: u16) -> u8 {
+        self.bus.peek(addr)
     }
 
     pub fn write(&mut self, addr: u16, data: u8) {
-        self.bus.write(addr, data);
+        if self.hooks.watches_write_changes() {
+            // what's there may differ from what was written, e.g. for ROM
+            let old = self.bus.peek(addr);
+            self.bus.write(addr, data);
+            self.hooks.write_change(addr, old, self.bus.peek(addr));
+        } else {
+            self.bus.write(addr, data);
+        }
+
+        if self.hooks.watches_writes() {
+            self.hooks.memory_write(addr, data);
+        }
+    }
+
+    /// Installs a sink that receives every executed instruction.
+    pub fn set_trace_sink(&mut self, sink: Box<dyn TraceSink + Send>) {
+        self.trace_sink = Some(sink);
+    }
+
+    pub fn clear_trace_sink(&mut self) {
+        self.trace_sink = None;
+    }
+
+    /// Limits which instructions the trace sink receives.
+    pub fn set_trace_filter(&mut self, filter: TraceFilter) {
+        self.trace_filter = filter;
+    }
+
+    /// Describes the instruction at the PC and the registers before it
+    /// runs, without side effects. `None` if the opcode is unknown.
+    pub fn trace_entry(&self) -> Option<TraceEntry> {
+        let instruction = self.decode()?;
+        let opcode = instruction.opcode;
+        let bytes = [opcode.opcode, instruction.operand[0], instruction.operand[1]];
+
+        Some(TraceEntry {
+            pc: self.program_counter,
+            bytes,
+            len: opcode.bytes,
+            text: trace::disassemble(opcode, &bytes),
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            status: self.status.to_byte(),
+            stack_pointer: self.stack_pointer,
+        })
     }
 
-    pub fn print_instruction(&mut self, opcode: &OpCode) {
-        print!("${:04X}\t", self.program_counter);
+    /// Decodes the instruction at the PC, working out the address it will
+    /// use from the registers and memory as they are now, without side
+    /// effects. `None` if the opcode is unknown.
+    pub fn decode(&self) -> Option<Instruction> {
+        let pc = self.program_counter;
+        let opcode = OPCODES.get(&self.peek(pc))?;
 
-        for i in 0..opcode.bytes {
-            print!("{:02X} ", self.read(self.program_counter + i as u16));
+        let mut operand = [0; 2];
+        for (i, byte) in operand.iter_mut().enumerate().take(opcode.bytes as usize - 1) {
+            *byte = self.peek(pc.wrapping_add(1 + i as u16));
         }
 
-        print!("{}\t", "   ".repeat(3 - opcode.bytes as usize));
+        let address = self.peek_address(opcode.addressing_mode, pc.wrapping_add(1), operand);
+        Some(Instruction { pc, opcode, operand, address })
+    }
 
-        print!("{} ", opcode.name);
+    // the address an operand at `at` resolves to, read with peeks
+    fn peek_address(&self, addressing_mode: AddressingMode, at: u16, operand: [u8; 2]) -> Option<u16> {
+        let absolute = self.hilo_to_u16(operand[1], operand[0]);
+        let pointer = |pointer: u8| {
+            let low = self.peek(pointer as u16);
+            let high = self.peek(pointer.wrapping_add(1) as u16);
+            self.hilo_to_u16(high, low)
+        };
 
-        match opcode.addressing_mode {
-            AddressingMode::Immediate => {
-                print!("#${:02X}", self.read(self.program_counter + 1));
-            },
-            AddressingMode::ZeroPage => {
-                print!("${:02X}", self.read(self.program_counter + 1));
-            },
-            AddressingMode::ZeroPageX => {
-                print!("${:02X},X", self.read(self.program_counter + 1));
-            },
-            AddressingMode::ZeroPageY => {
-                print!("${:02X},Y", self.read(self.program_counter + 1));
-            },
-            AddressingMode::Absolute => {
-                print!("${:02X}{:02X}", self.read(self.program_counter + 2), self.read(self.program_counter + 1));
-            }
-            AddressingMode::AbsoluteX => {
-                print!("${:02X}{:02X},X", self.read(self.program_counter + 2), self.read(self.program_counter + 1));
-            }
-            AddressingMode::AbsoluteY => {
-                print!("${:02X}{:02X},Y", self.read(self.program_counter + 2), self.read(self.program_counter + 1));
-            }
+        match addressing_mode {
+            AddressingMode::Implicit | AddressingMode::Accumulator | AddressingMode::Immediate => None,
+            AddressingMode::ZeroPage => Some(operand[0] as u16),
+            AddressingMode::ZeroPageX => Some(operand[0].wrapping_add(self.x) as u16),
+            AddressingMode::ZeroPageY => Some(operand[0].wrapping_add(self.y) as u16),
+            AddressingMode::Relative => Some(at.wrapping_add(1).wrapping_add(operand[0] as i8 as u16)),
+            AddressingMode::Absolute => Some(absolute),
+            AddressingMode::AbsoluteX => Some(absolute.wrapping_add(self.x as u16)),
+            AddressingMode::AbsoluteY => Some(absolute.wrapping_add(self.y as u16)),
             AddressingMode::Indirect => {
-                print!("(${:02X}{:02X})", self.read(self.program_counter + 2), self.read(self.program_counter + 1));
-            }
-            AddressingMode::IndirectX => {
-                print!("(${:02X},X)", self.read(self.program_counter + 1));
-            }
-            AddressingMode::IndirectY => {
-                print!("(${:02X}),Y", self.read(self.program_counter + 1));
-            }
-            AddressingMode::Relative => {
-                print!("*{:+}", self.read(self.program_counter + 1) as i8);
-            }
-            AddressingMode::Accumulator => {
-                print!("A");
-            }
-            AddressingMode::Implicit => {
-                print!("");
+                let low = self.peek(absolute);
+                let high = self.peek(absolute.wrapping_add(1));
+                Some(self.hilo_to_u16(high, low))
+            },
+            AddressingMode::IndirectX => Some(pointer(operand[0].wrapping_add(self.x))),
+            AddressingMode::IndirectY => Some(pointer(operand[0]).wrapping_add(self.y as u16)),
+        }
+    }
+
+    fn trace_instruction(&mut self, opcode: &OpCode) {
+        let pc = self.program_counter;
+        if !self.trace_filter.allows(pc, opcode, || self.bus.prg_bank(pc)) {
+            return;
+        }
+
+        if let Some(entry) = self.trace_entry() {
+            if let Some(sink) = self.trace_sink.as_mut() {
+                sink.trace(&entry);
             }
         }
-        
     }
 
-    pub fn clock(&mut self) {
+    /// Advances the CPU by one cycle, fetching and executing a new
+    /// instruction whenever the previous one has used up its cycles.
+    pub fn clock(&mut self) -> Result<(), EmuError> {
         if self.cycles == 0 {
             let opcode = self.read(self.program_counter);
             match OPCODES.get(&opcode) {
                 Some(op) => {
-                    // self.print_instruction(&op);
-                    self.program_counter += 1;
+                    if self.trace_sink.is_some() {
+                        self.trace_instruction(op);
+                    }
+
+                    if self.hooks.watches_instructions() {
+                        self.hooks.instruction(self.program_counter, op);
+                    }
+
+                    if self.hooks.watches_decoded() {
+                        if let Some(instruction) = self.decode() {
+                            self.hooks.decoded(&instruction);
+                        }
+                    }
+
+                    self.program_counter = self.program_counter.wrapping_add(1);
                     self.cycles = op.cycles as u64;
                     let pg_state = self.program_counter;
 
-                    let operation = op.operation;
-                    operation(self, op.addressing_mode);
+                    #[cfg(feature = "match-dispatch")]
+                    dispatch::execute(self, opcode, op.addressing_mode);
+                    #[cfg(not(feature = "match-dispatch"))]
+                    (op.operation)(self, op.addressing_mode);
 
                     if self.program_counter == pg_state {
-                        self.program_counter += (op.bytes as u16) - 1;
+                        self.program_counter = self.program_counter.wrapping_add(op.bytes as u16 - 1);
                     }
-
-                    // println!("");
+                },
+                None if JAM_OPCODES.contains(&opcode) => {
+                    return Err(EmuError::CpuJam { opcode, pc: self.program_counter });
                 },
                 None => {
-                    println!("FAILED AT OP: {:02X} AND PC: {:04X}", opcode, self.program_counter);
-                    panic!("Invalid opcode: 0x{:X}", opcode);
+                    return Err(EmuError::IllegalOpcode { opcode, pc: self.program_counter });
                 }
             }
         }
 
         self.cycles -= 1;
+        self.total_cycles += 1;
+
+        Ok(())
     }
 
-    pub fn load(&mut self, program: &Vec<u8>) {
-        for i in 0..(program.len() as u16) {
-            self.write(0x0600 + i, program[i as usize]);
+    /// Copies `program` to `$0600` and points the reset vector at it.
+    ///
+    /// Fails if the program would run into the interrupt vectors.
+    pub fn load(&mut self, program: &[u8]) -> Result<(), EmuError> {
+        let end = 0x0600 + program.len() as u32;
+        if end > 0xFFFA {
+            return Err(EmuError::BadAddress(end));
+        }
+
+        for (i, byte) in program.iter().enumerate() {
+            self.write(0x0600 + i as u16, *byte);
         }
         self.write(0xFFFC, 0x00);
         self.write(0xFFFD, 0x06);
+
+        Ok(())
     }
 
+    /// Presses the reset button: jumps through the reset vector at `$FFFC`
+    /// with interrupts masked. A, X and Y keep their values and SP drops
+    /// by 3, since the 6502 runs the pushes of an interrupt with its
+    /// writes suppressed.
     pub fn reset(&mut self) {
-        let low = self.read(0xFFFC);
-        let high = self.read(0xFFFD);
-
-        self.program_counter = self.hilo_to_u16(high, low);
+        self.stack_pointer = self.stack_pointer.wrapping_sub(3);
+        self.status.interrupt = true;
+        self.start_at_reset_vector();
+    }
 
+    /// A cold boot: A, X and Y cleared, SP at `$FD` and only I (and the
+    /// unused bit) set in P, then the reset vector.
+    pub fn power_on(&mut self) {
         self.a = 0;
         self.x = 0;
         self.y = 0;
         self.stack_pointer = 0xFD;
+        self.status = Status::from_byte(0x24);
+        self.start_at_reset_vector();
+    }
+
+    fn start_at_reset_vector(&mut self) {
+        let low = self.read(0xFFFC);
+        let high = self.read(0xFFFD);
+
+        self.program_counter = self.hilo_to_u16(high, low);
+        self.call_stack.clear();
+        self.complete = false;
 
         self.cycles = 8;
     }
 
+    /// Services a non-maskable interrupt through `$FFFA`.
     pub fn nmi(&mut self) {
+        let caller = self.program_counter;
+        let caller_sp = self.stack_pointer;
+
         self.stack_push((self.program_counter >> 8) as u8);
         self.stack_push(self.program_counter as u8);
 
+        // P goes on the stack as it was, so RTI unmasks interrupts again
         self.status.break_command = false;
         self.status.unused = true;
-        self.status.interrupt = true;
-
         self.stack_push(self.status.to_byte());
+        self.status.interrupt = true;
 
         let low = self.read(0xFFFA);
         let high = self.read(0xFFFB);
         self.program_counter = self.hilo_to_u16(high, low);
+        self.enter_frame(FrameKind::Interrupt(Interrupt::Nmi), caller, caller_sp);
 
         self.cycles = 8;
+
+        self.bus.events.push(EventKind::Nmi);
+        self.hooks.interrupt(Interrupt::Nmi);
     }
 
+    /// Services an interrupt request through `$FFFE`, unless interrupts are disabled.
     pub fn irq(&mut self) {
         if !self.status.interrupt {
+            let caller = self.program_counter;
+            let caller_sp = self.stack_pointer;
+
             self.stack_push((self.program_counter >> 8) as u8);
             self.stack_push(self.program_counter as u8);
 
             self.status.break_command = false;
             self.status.unused = true;
-            self.status.interrupt = true;
-
             self.stack_push(self.status.to_byte());
+            self.status.interrupt = true;
 
             let low = self.read(0xFFFE);
             let high = self.read(0xFFFF);
             self.program_counter = self.hilo_to_u16(high, low);
+            self.enter_frame(FrameKind::Interrupt(Interrupt::Irq), caller, caller_sp);
 
             self.cycles = 7;
+
+            self.bus.events.push(EventKind::Irq);
+            self.hooks.interrupt(Interrupt::Irq);
         }
     }
 
@@ -199,13 +394,13 @@ impl CPU {
             AddressingMode::Relative => (0, false),
             AddressingMode::Absolute => {
                 let low = self.read(self.program_counter);
-                let high = self.read(self.program_counter + 1);
+                let high = self.read(self.program_counter.wrapping_add(1));
 
                 (self.hilo_to_u16(high, low), false)
             },
             AddressingMode::AbsoluteX => {
                 let low = self.read(self.program_counter);
-                let high = self.read(self.program_counter + 1);
+                let high = self.read(self.program_counter.wrapping_add(1));
 
                 let address = self.hilo_to_u16(high, low).wrapping_add(self.x as u16);
 
@@ -213,7 +408,7 @@ impl CPU {
             },
             AddressingMode::AbsoluteY => {
                 let low = self.read(self.program_counter);
-                let high = self.read(self.program_counter + 1);
+                let high = self.read(self.program_counter.wrapping_add(1));
 
                 let address = self.hilo_to_u16(high, low).wrapping_add(self.y as u16);
 
@@ -221,12 +416,12 @@ impl CPU {
             },
             AddressingMode::Indirect => {
                 let pointer_low = self.read(self.program_counter);
-                let pointer_high = self.read(self.program_counter + 1);
+                let pointer_high = self.read(self.program_counter.wrapping_add(1));
 
                 let pointer = self.hilo_to_u16(pointer_high, pointer_low);
 
                 let low = self.read(pointer);
-                let high = self.read(pointer + 1);
+                let high = self.read(pointer.wrapping_add(1));
 
                 (self.hilo_to_u16(high, low), false)
             },
@@ -235,15 +430,16 @@ impl CPU {
                     .wrapping_add(self.x);
 
                 let low = self.read(pointer as u16);
-                let high = self.read((pointer + 1) as u16);
+                let high = self.read(pointer.wrapping_add(1) as u16);
 
                 (self.hilo_to_u16(high, low), false)
             },
             AddressingMode::IndirectY => {
-                let pointer = self.read(self.program_counter) as u16;
+                // the pointer wraps within the zero page
+                let pointer = self.read(self.program_counter);
 
-                let low = self.read(pointer);
-                let high = self.read(pointer + 1);
+                let low = self.read(pointer as u16);
+                let high = self.read(pointer.wrapping_add(1) as u16);
 
                 let address = self.hilo_to_u16(high, low).wrapping_add(self.y as u16);
 
@@ -256,9 +452,9 @@ impl CPU {
         if !((addressing_mode == AddressingMode::Implicit) || (addressing_mode == AddressingMode::Accumulator)) {
             let (address, page_boundary_cross) = self.get_address(addressing_mode);
 
-            return (self.read(address), page_boundary_cross);
+            (self.read(address), page_boundary_cross)
         } else {
-            return (self.a, false);
+            (self.a, false)
         }
     }
 
@@ -286,6 +482,7 @@ impl CPU {
 
     fn stack_pop(&mut self) -> u8 {
         self.stack_pointer = self.stack_pointer.wrapping_add(1);
+        self.call_stack.unwind(self.stack_pointer);
         // print!("READING: 0x{:02X} FROM SP: 0x{:02X} ", self.read(0x0100 + (self.stack_pointer as u16)), 0x0100 + (self.stack_pointer as u16));
         self.read(0x0100 + (self.stack_pointer as u16))
     }
@@ -296,74 +493,15 @@ impl CPU {
         self.stack_pointer = self.stack_pointer.wrapping_sub(1);
     }
 
-    pub fn disassemble(program: &Vec<u8>) {
-        println!("Address\t\tHexdump\t\tDissassembly");
-        println!("-------------------------------");
-        // let mut pc: u16 = 0x0600;
-        let mut i: u16 = 0;
-        
-        loop {
-            print!("${:04X}\t\t", 0x0600 + i);
-            
-            let opc = &OPCODES[&program[i as usize]];
-
-            for hex in 0..opc.bytes {
-                print!("{:02X} ", program[(i as usize) + (hex as usize)]);
-            }
-
-            print!("{}", "    ".repeat(3 - opc.bytes as usize));
-
-            print!("\t{} ", opc.name);
-
-            match opc.addressing_mode {
-                AddressingMode::Immediate => {
-                    print!("#${:02X}", program[(i as usize) + 1]);
-                },
-                AddressingMode::ZeroPage => {
-                    print!("${:02X}", program[(i as usize) + 1]);
-                },
-                AddressingMode::ZeroPageX => {
-                    print!("${:02X},X", program[(i as usize) + 1]);
-                },
-                AddressingMode::ZeroPageY => {
-                    print!("${:02X},Y", program[(i as usize) + 1]);
-                },
-                AddressingMode::Absolute => {
-                    print!("${:02X}{:02X}", program[(i as usize) + 2], program[(i as usize) + 1]);
-                }
-                AddressingMode::AbsoluteX => {
-                    print!("${:02X}{:02X},X", program[(i as usize) + 2], program[(i as usize) + 1]);
-                }
-                AddressingMode::AbsoluteY => {
-                    print!("${:02X}{:02X},Y", program[(i as usize) + 2], program[(i as usize) + 1]);
-                }
-                AddressingMode::Indirect => {
-                    print!("(${:02X}{:02X})", program[(i as usize) + 2], program[(i as usize) + 1]);
-                }
-                AddressingMode::IndirectX => {
-                    print!("(${:02X},X)", program[(i as usize) + 1]);
-                }
-                AddressingMode::IndirectY => {
-                    print!("(${:02X}),Y", program[(i as usize) + 1]);
-                }
-                AddressingMode::Relative => {
-                    print!("*{:+}", program[(i as usize) + 1] as i8);
-                }
-                AddressingMode::Accumulator => {
-                    print!("A");
-                }
-                AddressingMode::Implicit => {
-                    print!("");
-                }
-            }
-
-            i += opc.bytes as u16;
-            println!("");
-
-            if i as usize == program.len() {
-                break;
-            }
-        }
+    fn enter_frame(&mut self, kind: FrameKind, caller: u16, caller_sp: u8) {
+        let frame = CallFrame {
+            kind,
+            caller,
+            target: self.program_counter,
+            bank: self.bus.prg_bank(self.program_counter),
+            sp: self.stack_pointer,
+        };
+        self.call_stack.enter(caller_sp, frame);
     }
 
     fn hilo_to_u16(&self, high: u8, low: u8) -> u16 {
@@ -476,6 +614,7 @@ impl CPU {
     #[allow(non_snake_case)]
     pub fn TXS(&mut self, _addressing_mode: AddressingMode) { // Transfer X to Stack Pointer
         self.stack_pointer = self.x;
+        self.call_stack.unwind(self.stack_pointer);
     }
 
     #[allow(non_snake_case)]
@@ -617,14 +756,13 @@ impl CPU {
 
     #[allow(non_snake_case)]
     pub fn SBC(&mut self, addressing_mode: AddressingMode) { // Subtract with Carry
-        let (mut value, page_boundary_cross) = self.get_data(addressing_mode);
-
-        value = value.wrapping_neg();
+        let (value, page_boundary_cross) = self.get_data(addressing_mode);
 
-        
+        // A - M - !C is A + !M + C, so the carry out is the inverted borrow
+        let value = !value;
         let addition = (self.a as u16)
             .wrapping_add(value as u16)
-            .wrapping_sub(!self.status.carry as u16);
+            .wrapping_add(self.status.carry as u16);
         let result = addition as u8;
 
         self.status.carry = addition > 0xFF;
@@ -731,12 +869,15 @@ impl CPU {
     pub fn JSR(&mut self, addressing_mode: AddressingMode) { // Jump to Subroutine
         let address = self.get_address(addressing_mode).0;
 
-        self.program_counter += 1;
+        self.program_counter = self.program_counter.wrapping_add(1);
+        let caller = self.program_counter.wrapping_sub(2);
+        let caller_sp = self.stack_pointer;
 
         self.stack_push((self.program_counter >> 8) as u8);
         self.stack_push((self.program_counter & 0xFF) as u8);
 
         self.program_counter = address;
+        self.enter_frame(FrameKind::Subroutine, caller, caller_sp);
     }
 
     #[allow(non_snake_case)]
@@ -846,6 +987,8 @@ impl CPU {
     // STATUS & SYSTEM OPERATIONS
     #[allow(non_snake_case)]
     pub fn BRK(&mut self, _addressing_mode: AddressingMode) { // Force Interrupt
+        let caller = self.program_counter.wrapping_sub(1);
+        let caller_sp = self.stack_pointer;
         self.program_counter = self.program_counter.wrapping_add(1);
 
         self.stack_push((self.program_counter >> 8) as u8);
@@ -858,6 +1001,9 @@ impl CPU {
         let low = self.read(0xFFFE);
         let high = self.read(0xFFFF);
         self.program_counter = self.hilo_to_u16(high, low);
+        self.enter_frame(FrameKind::Interrupt(Interrupt::Brk), caller, caller_sp);
+
+        self.hooks.interrupt(Interrupt::Brk);
 
         self.complete = true;
     }
@@ -901,11 +1047,41 @@ impl CPU {
     pub fn SEI(&mut self, _addressing_mode: AddressingMode) { // Set Interrupt Disable
         self.status.interrupt = true;
     }
-    
-    
-    // ilLeGaL OPERATIONS
-    #[allow(non_snake_case)]
-    pub fn ILLEGAL(&mut self, _addressing_mode: AddressingMode) { // Illegal Instruction
-        todo!();
+}
+#[cfg(test)]
+mod tests {
+    use crate::nes::Nes;
+
+    fn run(program: &[u8], instructions: usize) -> Nes {
+        let mut nes = Nes::builder().build();
+        nes.load(program).unwrap();
+        for _ in 0..instructions {
+            nes.step_instruction().unwrap();
+        }
+        nes
+    }
+
+    #[test]
+    fn indirect_pointers_wrap_within_the_zero_page() {
+        // $FF/$00 point at $1234, which holds $2A
+        let setup = [0xA9, 0x34, 0x85, 0xFF, 0xA9, 0x12, 0x85, 0x00, 0xA9, 0x2A, 0x8D, 0x34, 0x12];
+
+        // LDX #$00; LDA ($FF,X)
+        let nes = run(&[&setup[..], &[0xA2, 0x00, 0xA1, 0xFF]].concat(), 8);
+        assert_eq!(nes.cpu.a, 0x2A);
+
+        // LDY #$00; LDA ($FF),Y
+        let nes = run(&[&setup[..], &[0xA0, 0x00, 0xB1, 0xFF]].concat(), 8);
+        assert_eq!(nes.cpu.a, 0x2A);
+    }
+
+    #[test]
+    fn program_counter_wraps_past_ffff() {
+        // JMP $FFFF, where a NOP sits in the IRQ vector's high byte
+        let mut nes = run(&[0x4C, 0xFF, 0xFF], 0);
+        nes.cpu.bus.poke(0xFFFF, 0xEA);
+        nes.step_instruction().unwrap();
+        nes.step_instruction().unwrap();
+        assert_eq!(nes.cpu.program_counter, 0x0000);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,58 @@
+//! A write-only console port for homebrew to print through, so programs
+//! can be debugged with printf inside the emulator.
+//!
+//! Every byte written to the port is taken as a character; a newline ends
+//! the line and carriage returns are dropped. The frontend collects the
+//! finished lines with [`DebugPort::take_lines`]. Nothing on a real
+//! console answers there, so the writes don't reach the cartridge.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem;
+
+/// Where Mesen puts its debug console, just past the APU and I/O registers.
+pub const DEFAULT_ADDR: u16 = 0x4020;
+
+// lines are cut here, for programs that never write a newline
+const MAX_LINE: usize = 256;
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct DebugPort {
+    addr: u16,
+    line: String,
+    lines: Vec<String>,
+}
+
+impl DebugPort {
+    pub fn new(addr: u16) -> DebugPort {
+        DebugPort { addr, line: String::new(), lines: Vec::new() }
+    }
+
+    pub fn addr(&self) -> u16 {
+        self.addr
+    }
+
+    pub(crate) fn write(&mut self, data: u8) {
+        match data {
+            b'\n' => self.lines.push(mem::take(&mut self.line)),
+            b'\r' => {},
+            // Latin-1, so any byte shows as something
+            _ => {
+                self.line.push(char::from(data));
+                if self.line.len() >= MAX_LINE {
+                    self.lines.push(mem::take(&mut self.line));
+                }
+            },
+        }
+    }
+
+    /// The lines finished since the last call, oldest first.
+    pub fn take_lines(&mut self) -> Vec<String> {
+        mem::take(&mut self.lines)
+    }
+
+    /// What has been written of the current line so far.
+    pub fn pending(&self) -> &str {
+        &self.line
+    }
+}
@@ -0,0 +1,592 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::RangeInclusive;
+
+use crate::constants::OPCODES;
+use crate::cpu::CPU;
+use crate::error::EmuError;
+use crate::expr::Expr;
+use crate::hooks::Interrupt;
+
+/// Why emulation stopped.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum BreakReason {
+    /// An execution breakpoint at this PC.
+    Breakpoint,
+    /// A single step finished.
+    Step,
+    /// The previous instruction touched a watched address.
+    Watchpoint(WatchHit),
+    /// At the first instruction of an NMI or IRQ handler, or before a BRK.
+    Interrupt(Interrupt),
+    /// Before an opcode the 6502 doesn't officially define.
+    UnofficialOpcode(u8),
+    /// At the first instruction once the frame reaches the vblank
+    /// scanline.
+    Vblank,
+    /// At the first instruction once the cartridge pulled /IRQ low.
+    MapperIrq,
+}
+
+impl fmt::Display for BreakReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakReason::Breakpoint => write!(f, "breakpoint"),
+            BreakReason::Step => write!(f, "step"),
+            BreakReason::Watchpoint(hit) => {
+                let access = if hit.write { "write" } else { "read" };
+                write!(f, "{} ${:02X} at ${:04X} from ${:04X}", access, hit.value, hit.addr, hit.pc)
+            },
+            BreakReason::Interrupt(interrupt) => write!(f, "{:?}", interrupt),
+            BreakReason::UnofficialOpcode(opcode) => write!(f, "unofficial opcode ${:02X}", opcode),
+            BreakReason::Vblank => write!(f, "vblank"),
+            BreakReason::MapperIrq => write!(f, "mapper IRQ"),
+        }
+    }
+}
+
+/// Which accesses a watchpoint triggers on.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    fn matches(self, write: bool) -> bool {
+        match self {
+            Access::Read => !write,
+            Access::Write => write,
+            Access::ReadWrite => true,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Watchpoint {
+    pub range: RangeInclusive<u16>,
+    pub access: Access,
+}
+
+/// A watched access: the instruction that made it, and what it read or
+/// wrote where.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct WatchHit {
+    pub pc: u16,
+    pub addr: u16,
+    pub value: u8,
+    pub write: bool,
+}
+
+/// CPU address space watchpoints. They live on the [`Bus`](crate::bus::Bus)
+/// so `read` and `write` can check them; side-effect free `peek`s never
+/// trigger them.
+#[derive(Default, Debug, Clone)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    hit: Option<WatchHit>,
+}
+
+impl Watchpoints {
+    pub fn add(&mut self, range: RangeInclusive<u16>, access: Access) {
+        self.list.push(Watchpoint { range, access });
+    }
+
+    /// Removes every watchpoint on exactly `range`; returns false if there
+    /// were none.
+    pub fn remove(&mut self, range: &RangeInclusive<u16>) -> bool {
+        let len = self.list.len();
+        self.list.retain(|watchpoint| watchpoint.range != *range);
+        self.list.len() != len
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+        self.hit = None;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Watchpoint> {
+        self.list.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Records the first watched access since the last `take_hit`.
+    pub(crate) fn check(&mut self, addr: u16, value: u8, write: bool) {
+        if self.hit.is_some() {
+            return;
+        }
+
+        let watched = self
+            .list
+            .iter()
+            .any(|watchpoint| watchpoint.range.contains(&addr) && watchpoint.access.matches(write));
+
+        if watched {
+            // the bus doesn't know the PC; the caller fills it in
+            self.hit = Some(WatchHit { pc: 0, addr, value, write });
+        }
+    }
+
+    pub(crate) fn take_hit(&mut self) -> Option<WatchHit> {
+        self.hit.take()
+    }
+}
+
+/// How a call stack frame was entered.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum FrameKind {
+    Subroutine,
+    Interrupt(Interrupt),
+}
+
+/// One entry of the call stack.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct CallFrame {
+    pub kind: FrameKind,
+    /// The JSR, or the instruction the interrupt arrived before.
+    pub caller: u16,
+    pub target: u16,
+    /// The mapper's PRG bank at `target` when it was entered.
+    pub bank: u16,
+    /// The stack pointer on entry, after the return address was pushed.
+    pub sp: u8,
+}
+
+// the return addresses of more frames than this can't fit on the stack
+const MAX_FRAMES: usize = 128;
+
+/// Calls and interrupts the CPU is nested in, innermost last.
+///
+/// Frames are dropped as soon as a pull or `TXS` moves the stack pointer
+/// above where they were entered, so games that pull return addresses off
+/// by hand or reset SP leave no stale frames behind, and pushing an
+/// address to `RTS` to it keeps the caller's frame.
+#[derive(Default, Debug, Clone)]
+pub struct CallStack {
+    frames: Vec<CallFrame>,
+    // frames entered so far, so observers can tell a call happened
+    entered: u64,
+}
+
+impl CallStack {
+    pub fn frames(&self) -> &[CallFrame] {
+        &self.frames
+    }
+
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Pushes a frame; `caller_sp` is the stack pointer before the return
+    /// address was pushed.
+    pub(crate) fn enter(&mut self, caller_sp: u8, frame: CallFrame) {
+        self.unwind(caller_sp);
+        if self.frames.len() == MAX_FRAMES {
+            self.frames.remove(0);
+        }
+        self.frames.push(frame);
+        self.entered = self.entered.wrapping_add(1);
+    }
+
+    pub(crate) fn entered(&self) -> u64 {
+        self.entered
+    }
+
+    /// Drops the frames whose return address sits below `sp`.
+    pub(crate) fn unwind(&mut self, sp: u8) {
+        self.frames.retain(|frame| frame.sp >= sp);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+/// Where and why emulation stopped. The instruction at `pc` has not run
+/// yet.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Break {
+    pub pc: u16,
+    pub reason: BreakReason,
+}
+
+/// An expression the debugger UI shows the value of.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct WatchExpr {
+    pub text: String,
+    pub expr: Expr,
+}
+
+/// Where a step over, step out or run to cursor stops.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum RunTarget {
+    /// Before the instruction at this address.
+    Address(u16),
+    /// Before `pc` once the stack is back up to `sp`, i.e. after the
+    /// subroutine called from there returned.
+    Return { pc: u16, sp: u8 },
+    /// After an RTS or RTI pops the stack above `sp`.
+    Exit { sp: u8 },
+}
+
+/// Breakpoints and the stopped/running state, checked by
+/// [`Nes`](crate::nes::Nes) at every instruction boundary.
+#[derive(Default, Debug, Clone)]
+pub struct Debugger {
+    // an optional condition that must also hold for the break to fire
+    breakpoints: BTreeMap<u16, Option<Expr>>,
+    stopped: Option<Break>,
+    // lets `resume` get past the breakpoint it is sitting on
+    skip: Option<u16>,
+    target: Option<RunTarget>,
+    watches: Vec<WatchExpr>,
+    break_on_interrupts: Vec<Interrupt>,
+    break_on_unofficial: bool,
+    break_on_vblank: bool,
+    break_on_mapper_irq: bool,
+    // an event the console raised since the last check
+    pending: Option<BreakReason>,
+    // the call stack's entry count at the last check, to spot interrupts,
+    // and the opcode of the instruction that ran since; unknown after the
+    // debugger was idle, as nothing was checked then
+    entered: Option<u64>,
+    previous: Option<u8>,
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger::default()
+    }
+
+    pub fn add_breakpoint(&mut self, pc: u16) {
+        self.breakpoints.insert(pc, None);
+    }
+
+    /// Breaks at `pc` only when `condition` evaluates to non-zero there.
+    /// Replaces any breakpoint already at `pc`.
+    pub fn add_conditional_breakpoint(&mut self, pc: u16, condition: Expr) {
+        self.breakpoints.insert(pc, Some(condition));
+    }
+
+    /// Returns false if there was no breakpoint at `pc`.
+    pub fn remove_breakpoint(&mut self, pc: u16) -> bool {
+        self.breakpoints.remove(&pc).is_some()
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.keys().copied()
+    }
+
+    /// The condition on the breakpoint at `pc`, if it has one.
+    pub fn condition(&self, pc: u16) -> Option<&Expr> {
+        self.breakpoints.get(&pc).and_then(Option::as_ref)
+    }
+
+    /// Breaks at the start of every NMI or IRQ handler, or before every
+    /// BRK, depending on `interrupt`.
+    pub fn set_break_on_interrupt(&mut self, interrupt: Interrupt, enabled: bool) {
+        self.break_on_interrupts.retain(|&other| other != interrupt);
+        if enabled {
+            self.break_on_interrupts.push(interrupt);
+        }
+    }
+
+    /// Breaks before any opcode outside the official instruction set, which
+    /// usually means the CPU went off into data.
+    pub fn set_break_on_unofficial(&mut self, enabled: bool) {
+        self.break_on_unofficial = enabled;
+    }
+
+    /// Breaks once each frame reaches vblank, before the next instruction.
+    pub fn set_break_on_vblank(&mut self, enabled: bool) {
+        self.break_on_vblank = enabled;
+        self.pending = self.pending.filter(|&reason| enabled || reason != BreakReason::Vblank);
+    }
+
+    /// Breaks whenever the cartridge asserts its IRQ, before the next
+    /// instruction; if the I flag is clear that is the handler's first.
+    pub fn set_break_on_mapper_irq(&mut self, enabled: bool) {
+        self.break_on_mapper_irq = enabled;
+        self.pending = self.pending.filter(|&reason| enabled || reason != BreakReason::MapperIrq);
+    }
+
+    pub(crate) fn vblank_started(&mut self) {
+        if self.break_on_vblank {
+            self.pending = Some(BreakReason::Vblank);
+        }
+    }
+
+    pub(crate) fn mapper_irq_asserted(&mut self) {
+        if self.break_on_mapper_irq {
+            self.pending = Some(BreakReason::MapperIrq);
+        }
+    }
+
+    /// Adds a watch expression, e.g. `[0x00F0] + X`; see [`Expr`].
+    pub fn add_watch(&mut self, text: &str) -> Result<(), EmuError> {
+        let expr = Expr::parse(text)?;
+        self.watches.push(WatchExpr { text: text.to_string(), expr });
+        Ok(())
+    }
+
+    /// Removes the watch at `index` in [`Debugger::watches`]; returns false
+    /// if there is none.
+    pub fn remove_watch(&mut self, index: usize) -> bool {
+        if index >= self.watches.len() {
+            return false;
+        }
+        self.watches.remove(index);
+        true
+    }
+
+    pub fn watches(&self) -> &[WatchExpr] {
+        &self.watches
+    }
+
+    /// Every watch expression with its current value, for showing when
+    /// emulation stops or after each frame.
+    pub fn evaluate_watches<'a>(&'a self, cpu: &'a CPU) -> impl Iterator<Item = (&'a str, i32)> + 'a {
+        self.watches.iter().map(move |watch| (watch.text.as_str(), watch.expr.eval(cpu)))
+    }
+
+    /// The break emulation is stopped at, if any.
+    pub fn stopped(&self) -> Option<Break> {
+        self.stopped
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.is_some()
+    }
+
+    /// Stops emulation before the instruction at `pc`, cancelling any run
+    /// to a target.
+    pub fn stop(&mut self, pc: u16, reason: BreakReason) {
+        self.stopped = Some(Break { pc, reason });
+        self.target = None;
+    }
+
+    /// Lets emulation continue; the breakpoint it is stopped at will not
+    /// fire again until another instruction has run.
+    pub fn resume(&mut self) {
+        if let Some(stopped) = self.stopped.take() {
+            self.skip = Some(stopped.pc);
+        }
+    }
+
+    /// Resumes and stops again with [`BreakReason::Step`] once `target`
+    /// is reached. Breakpoints still fire on the way and cancel it.
+    pub fn run_until(&mut self, target: RunTarget) {
+        self.resume();
+        self.target = Some(target);
+    }
+
+    pub fn run_target(&self) -> Option<RunTarget> {
+        self.target
+    }
+
+    pub fn cancel_run(&mut self) {
+        self.target = None;
+    }
+
+    /// Decides at an instruction boundary whether to stop before the
+    /// instruction at the CPU's PC.
+    pub(crate) fn check(&mut self, cpu: &CPU) -> bool {
+        let pc = cpu.program_counter;
+        // kept up to date while stopped too, so interrupts taken and
+        // returns made by single steps don't fire once resumed
+        let opcode = cpu.peek(pc);
+        let previous = self.previous.replace(opcode);
+        let entered = self.entered.replace(cpu.call_stack.entered());
+
+        if self.stopped.is_some() {
+            return true;
+        }
+
+        if self.skip.take() == Some(pc) {
+            return false;
+        }
+
+        if let Some(reason) = self.pending.take() {
+            self.stop(pc, reason);
+            return true;
+        }
+
+        // a new NMI or IRQ frame since the last check means its handler is
+        // about to start; BRK is caught before it runs, since it ends
+        // playground programs
+        let interrupt = match cpu.call_stack.frames().last() {
+            _ if opcode == 0x00 => Some(Interrupt::Brk),
+            Some(frame) if entered.is_some_and(|entered| entered != cpu.call_stack.entered()) => match frame.kind {
+                FrameKind::Interrupt(Interrupt::Brk) | FrameKind::Subroutine => None,
+                FrameKind::Interrupt(interrupt) => Some(interrupt),
+            },
+            _ => None,
+        };
+
+        if let Some(interrupt) = interrupt.filter(|interrupt| self.break_on_interrupts.contains(interrupt)) {
+            self.stop(pc, BreakReason::Interrupt(interrupt));
+            return true;
+        }
+
+        if self.break_on_unofficial && OPCODES.get(&opcode).is_none() {
+            self.stop(pc, BreakReason::UnofficialOpcode(opcode));
+            return true;
+        }
+
+        let reached = match self.target {
+            Some(RunTarget::Address(addr)) => pc == addr,
+            Some(RunTarget::Return { pc: ret, sp }) => pc == ret && cpu.stack_pointer >= sp,
+            // RTI or RTS
+            Some(RunTarget::Exit { sp }) => matches!(previous, Some(0x40 | 0x60)) && cpu.stack_pointer > sp,
+            None => false,
+        };
+
+        if reached {
+            self.stop(pc, BreakReason::Step);
+            return true;
+        }
+
+        let hit = match self.breakpoints.get(&pc) {
+            Some(Some(condition)) => condition.is_true(cpu),
+            Some(None) => true,
+            None => false,
+        };
+
+        if hit {
+            self.stop(pc, BreakReason::Breakpoint);
+            return true;
+        }
+
+        false
+    }
+
+    /// Called at the boundaries the run loop skips checking while idle, so
+    /// the first check once something is enabled starts from the CPU as
+    /// it is then rather than from the last check before.
+    pub(crate) fn unchecked(&mut self) {
+        self.entered = None;
+        self.previous = None;
+    }
+
+    // nothing to check, so the run loop can skip the call
+    pub(crate) fn is_idle(&self) -> bool {
+        self.breakpoints.is_empty()
+            && self.stopped.is_none()
+            && self.target.is_none()
+            && self.break_on_interrupts.is_empty()
+            && !self.break_on_unofficial
+            && !self.break_on_vblank
+            && !self.break_on_mapper_irq
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::irq::IrqSource;
+    use crate::nes::Nes;
+
+    // CLI, then spin at $0601; the IRQ handler at $0700 spins with I set
+    fn nes() -> Nes {
+        let mut nes = Nes::builder().build();
+        nes.load(&[0x58, 0x4C, 0x01, 0x06]).unwrap();
+        for (addr, byte) in [(0x0700, 0x4C), (0x0701, 0x00), (0x0702, 0x07), (0xFFFE, 0x00), (0xFFFF, 0x07)] {
+            nes.cpu.bus.write(addr, byte);
+        }
+        nes
+    }
+
+    fn run(nes: &mut Nes, cycles: usize) {
+        for _ in 0..cycles {
+            nes.clock().unwrap();
+        }
+    }
+
+    fn frame(target: u16, sp: u8) -> CallFrame {
+        CallFrame { kind: FrameKind::Subroutine, caller: 0x8000, target, bank: 0, sp }
+    }
+
+    fn targets(stack: &CallStack) -> Vec<u16> {
+        stack.frames().iter().map(|frame| frame.target).collect()
+    }
+
+    #[test]
+    fn call_stack_unwinds_as_the_stack_pointer_rises() {
+        let mut stack = CallStack::default();
+        stack.enter(0xFD, frame(0x9000, 0xFB));
+        stack.enter(0xFB, frame(0xA000, 0xF9));
+        stack.enter(0xF9, frame(0xB000, 0xF7));
+        assert_eq!((stack.depth(), stack.entered()), (3, 3));
+
+        stack.unwind(0xF9);
+        assert_eq!(targets(&stack), [0x9000, 0xA000]);
+
+        // a TXS back up to the top drops everything but the new frame
+        stack.enter(0xFF, frame(0xC000, 0xFD));
+        assert_eq!(targets(&stack), [0xC000]);
+        assert_eq!(stack.entered(), 4);
+    }
+
+    #[test]
+    fn call_stack_drops_the_oldest_frame_when_full() {
+        let mut stack = CallStack::default();
+        for i in 0..=MAX_FRAMES as u16 {
+            stack.enter(0, frame(i, 0));
+        }
+        assert_eq!(stack.depth(), MAX_FRAMES);
+        assert_eq!(stack.frames()[0].target, 1);
+    }
+
+    #[test]
+    fn tracks_jsr_and_rts() {
+        // JSR $0700; then spin; $0700: RTS
+        let mut nes = Nes::builder().build();
+        nes.load(&[0x20, 0x00, 0x07, 0x4C, 0x03, 0x06]).unwrap();
+        nes.cpu.bus.write(0x0700, 0x60);
+
+        nes.step_instruction().unwrap();
+        let frames = nes.cpu.call_stack.frames();
+        assert_eq!(frames.len(), 1);
+        assert_eq!((frames[0].kind, frames[0].caller, frames[0].target), (FrameKind::Subroutine, 0x0600, 0x0700));
+        assert_eq!(frames[0].sp, nes.cpu.stack_pointer);
+
+        nes.step_instruction().unwrap();
+        assert_eq!(nes.cpu.call_stack.depth(), 0);
+        assert_eq!(nes.cpu.program_counter, 0x0603);
+    }
+
+    #[test]
+    fn breaks_at_the_interrupt_handler() {
+        let mut nes = nes();
+        nes.debugger.set_break_on_interrupt(Interrupt::Irq, true);
+        run(&mut nes, 20);
+        nes.cpu.bus.irq.assert(IrqSource::External);
+        run(&mut nes, 50);
+
+        let stopped = nes.debugger.stopped().unwrap();
+        assert_eq!(stopped, Break { pc: 0x0700, reason: BreakReason::Interrupt(Interrupt::Irq) });
+    }
+
+    #[test]
+    fn enabling_mid_run_ignores_interrupts_taken_while_idle() {
+        let mut nes = nes();
+        run(&mut nes, 20);
+        nes.cpu.bus.irq.assert(IrqSource::External);
+        run(&mut nes, 50);
+        assert_eq!(nes.cpu.call_stack.frames().last().map(|frame| frame.target), Some(0x0700));
+
+        nes.debugger.set_break_on_interrupt(Interrupt::Irq, true);
+        run(&mut nes, 50);
+        assert_eq!(nes.debugger.stopped(), None);
+    }
+}
@@ -0,0 +1,76 @@
+//! A `match` over the opcode byte in place of the function pointers in
+//! [`OPCODES`](crate::constants::OPCODES), built with the
+//! `match-dispatch` feature.
+//!
+//! A call through a pointer can't be inlined, so every instruction pays
+//! for a call and the optimizer can't see across it; with a `match` the
+//! compiler is free to inline the hot instruction bodies into the loop.
+//! The arms must list the same opcodes as the table, which the CPU still
+//! uses for sizes, cycles and names.
+
+use crate::constants::AddressingMode;
+use crate::cpu::CPU;
+
+/// Runs the instruction for `opcode`, which must be in the opcode table.
+#[inline]
+pub(crate) fn execute(cpu: &mut CPU, opcode: u8, addressing_mode: AddressingMode) {
+    match opcode {
+        0x69 | 0x65 | 0x75 | 0x6D | 0x7D | 0x79 | 0x61 | 0x71 => cpu.ADC(addressing_mode),
+        0x29 | 0x25 | 0x35 | 0x2D | 0x3D | 0x39 | 0x21 | 0x31 => cpu.AND(addressing_mode),
+        0x0A | 0x06 | 0x16 | 0x0E | 0x1E => cpu.ASL(addressing_mode),
+        0x90 => cpu.BCC(addressing_mode),
+        0xB0 => cpu.BCS(addressing_mode),
+        0xF0 => cpu.BEQ(addressing_mode),
+        0x24 | 0x2C => cpu.BIT(addressing_mode),
+        0x30 => cpu.BMI(addressing_mode),
+        0xD0 => cpu.BNE(addressing_mode),
+        0x10 => cpu.BPL(addressing_mode),
+        0x00 => cpu.BRK(addressing_mode),
+        0x50 => cpu.BVC(addressing_mode),
+        0x70 => cpu.BVS(addressing_mode),
+        0x18 => cpu.CLC(addressing_mode),
+        0xD8 => cpu.CLD(addressing_mode),
+        0x58 => cpu.CLI(addressing_mode),
+        0xB8 => cpu.CLV(addressing_mode),
+        0xC9 | 0xC5 | 0xD5 | 0xCD | 0xDD | 0xD9 | 0xC1 | 0xD1 => cpu.CMP(addressing_mode),
+        0xE0 | 0xE4 | 0xEC => cpu.CPX(addressing_mode),
+        0xC0 | 0xC4 | 0xCC => cpu.CPY(addressing_mode),
+        0xC6 | 0xD6 | 0xCE | 0xDE => cpu.DEC(addressing_mode),
+        0xCA => cpu.DEX(addressing_mode),
+        0x88 => cpu.DEY(addressing_mode),
+        0x49 | 0x45 | 0x55 | 0x4D | 0x5D | 0x59 | 0x41 | 0x51 => cpu.EOR(addressing_mode),
+        0xE6 | 0xF6 | 0xEE | 0xFE => cpu.INC(addressing_mode),
+        0xE8 => cpu.INX(addressing_mode),
+        0xC8 => cpu.INY(addressing_mode),
+        0x4C | 0x6C => cpu.JMP(addressing_mode),
+        0x20 => cpu.JSR(addressing_mode),
+        0xA9 | 0xA5 | 0xB5 | 0xAD | 0xBD | 0xB9 | 0xA1 | 0xB1 => cpu.LDA(addressing_mode),
+        0xA2 | 0xA6 | 0xB6 | 0xAE | 0xBE => cpu.LDX(addressing_mode),
+        0xA0 | 0xA4 | 0xB4 | 0xAC | 0xBC => cpu.LDY(addressing_mode),
+        0x4A | 0x46 | 0x56 | 0x4E | 0x5E => cpu.LSR(addressing_mode),
+        0xEA => cpu.NOP(addressing_mode),
+        0x09 | 0x05 | 0x15 | 0x0D | 0x1D | 0x19 | 0x01 | 0x11 => cpu.ORA(addressing_mode),
+        0x48 => cpu.PHA(addressing_mode),
+        0x08 => cpu.PHP(addressing_mode),
+        0x68 => cpu.PLA(addressing_mode),
+        0x28 => cpu.PLP(addressing_mode),
+        0x2A | 0x26 | 0x36 | 0x2E | 0x3E => cpu.ROL(addressing_mode),
+        0x6A | 0x66 | 0x76 | 0x6E | 0x7E => cpu.ROR(addressing_mode),
+        0x40 => cpu.RTI(addressing_mode),
+        0x60 => cpu.RTS(addressing_mode),
+        0xE9 | 0xE5 | 0xF5 | 0xED | 0xFD | 0xF9 | 0xE1 | 0xF1 => cpu.SBC(addressing_mode),
+        0x38 => cpu.SEC(addressing_mode),
+        0xF8 => cpu.SED(addressing_mode),
+        0x78 => cpu.SEI(addressing_mode),
+        0x85 | 0x95 | 0x8D | 0x9D | 0x99 | 0x81 | 0x91 => cpu.STA(addressing_mode),
+        0x86 | 0x96 | 0x8E => cpu.STX(addressing_mode),
+        0x84 | 0x94 | 0x8C => cpu.STY(addressing_mode),
+        0xAA => cpu.TAX(addressing_mode),
+        0xA8 => cpu.TAY(addressing_mode),
+        0xBA => cpu.TSX(addressing_mode),
+        0x8A => cpu.TXA(addressing_mode),
+        0x9A => cpu.TXS(addressing_mode),
+        0x98 => cpu.TYA(addressing_mode),
+        _ => unreachable!("${:02X} isn't in the opcode table", opcode),
+    }
+}
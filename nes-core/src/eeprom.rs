@@ -0,0 +1,369 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::state::{BoardReader, BoardWriter};
+
+/// The serial EEPROM chips Bandai boards keep saves in.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Model {
+    /// 128 bytes. There's no device address: the first byte after a start
+    /// is the word address with R/W in bit 7, and every byte goes least
+    /// significant bit first.
+    X24C01,
+    /// 256 bytes on a standard I²C bus, most significant bit first.
+    C24C02,
+}
+
+impl Model {
+    pub fn size(&self) -> usize {
+        match self {
+            Model::X24C01 => 128,
+            Model::C24C02 => 256,
+        }
+    }
+
+    // bytes a write can fill before the address wraps
+    fn page(&self) -> u8 {
+        match self {
+            Model::X24C01 => 4,
+            Model::C24C02 => 8,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum Mode {
+    Idle,
+    Device,
+    Address,
+    Write,
+    Read,
+    // the chip wasn't addressed or the master stopped acking; waits for a stop
+    Ignore,
+}
+
+impl Mode {
+    const ALL: [Mode; 6] = [Mode::Idle, Mode::Device, Mode::Address, Mode::Write, Mode::Read, Mode::Ignore];
+
+    fn from_byte(byte: u8) -> Mode {
+        Mode::ALL.get(byte as usize).copied().unwrap_or(Mode::Idle)
+    }
+}
+
+/// A 24C0x EEPROM driven bit by bit through its clock (SCL) and data (SDA)
+/// pins.
+///
+/// A falling SDA while SCL is high starts a transfer and a rising one stops
+/// it. Bits are taken on the rising edge of SCL and the chip changes its
+/// output while SCL is low; every ninth clock is an acknowledge.
+pub struct Eeprom {
+    model: Model,
+    data: Vec<u8>,
+    scl: bool,
+    sda: bool,
+    mode: Mode,
+    // where `mode` goes once the acknowledge clock has passed
+    next: Mode,
+    bits: u8,
+    shift: u8,
+    address: u8,
+    output: bool,
+}
+
+impl Eeprom {
+    /// A chip that hasn't been written yet, all bits set.
+    pub fn new(model: Model) -> Eeprom {
+        Eeprom {
+            model,
+            data: vec![0xFF; model.size()],
+            scl: false,
+            sda: false,
+            mode: Mode::Idle,
+            next: Mode::Idle,
+            bits: 0,
+            shift: 0,
+            address: 0,
+            output: true,
+        }
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Replaces the contents, e.g. from a save file. A short `data` only
+    /// fills the start of the chip.
+    pub fn load(&mut self, data: &[u8]) {
+        let len = data.len().min(self.data.len());
+        self.data[..len].copy_from_slice(&data[..len]);
+    }
+
+    /// The level the chip drives SDA to; high when it's letting go.
+    pub fn output(&self) -> bool {
+        self.output
+    }
+
+    /// Drops any transfer in progress; the contents stay.
+    pub fn power_on(&mut self) {
+        self.scl = false;
+        self.sda = false;
+        self.mode = Mode::Idle;
+        self.bits = 0;
+        self.output = true;
+    }
+
+    /// Sets both pins as the board drives them.
+    pub fn set_lines(&mut self, scl: bool, sda: bool) {
+        if self.scl && scl && self.sda != sda {
+            if sda {
+                self.mode = Mode::Idle;
+            } else {
+                self.start();
+            }
+            self.output = true;
+        } else if !self.scl && scl {
+            self.rise(sda);
+        } else if self.scl && !scl {
+            self.fall();
+        }
+
+        self.scl = scl;
+        self.sda = sda;
+    }
+
+    /// Writes the contents and the transfer in progress, for the board's
+    /// save state.
+    pub fn save_state(&self, state: &mut BoardWriter) {
+        state.bytes(&self.data);
+        state.bool(self.scl);
+        state.bool(self.sda);
+        state.u8(self.mode as u8);
+        state.u8(self.next as u8);
+        state.u8(self.bits);
+        state.u8(self.shift);
+        state.u8(self.address);
+        state.bool(self.output);
+    }
+
+    pub fn load_state(&mut self, state: &mut BoardReader) {
+        state.bytes(&mut self.data);
+        self.scl = state.bool();
+        self.sda = state.bool();
+        self.mode = Mode::from_byte(state.u8());
+        self.next = Mode::from_byte(state.u8());
+        self.bits = state.u8();
+        self.shift = state.u8();
+        self.address = (state.u8() as usize % self.data.len()) as u8;
+        self.output = state.bool();
+    }
+
+    /// Moves only the clock pin, for boards that wire it apart from SDA.
+    pub fn set_scl(&mut self, scl: bool) {
+        self.set_lines(scl, self.sda);
+    }
+
+    /// Moves only the data pin, for boards that clock the chip elsewhere.
+    pub fn set_sda(&mut self, sda: bool) {
+        self.set_lines(self.scl, sda);
+    }
+
+    fn start(&mut self) {
+        self.mode = match self.model {
+            Model::X24C01 => Mode::Address,
+            Model::C24C02 => Mode::Device,
+        };
+        self.bits = 0;
+        self.shift = 0;
+    }
+
+    fn rise(&mut self, sda: bool) {
+        match self.mode {
+            Mode::Device | Mode::Address | Mode::Write if self.bits < 8 => {
+                self.shift = match self.model {
+                    Model::X24C01 => self.shift | (sda as u8) << self.bits,
+                    Model::C24C02 => self.shift << 1 | sda as u8,
+                };
+                self.bits += 1;
+                if self.bits == 8 {
+                    self.next = self.receive(self.shift);
+                }
+            },
+            // the acknowledge clock
+            Mode::Device | Mode::Address | Mode::Write => {
+                self.mode = self.next;
+                self.bits = 0;
+                self.shift = 0;
+            },
+            Mode::Read if self.bits < 8 => self.bits += 1,
+            // the master acks to ask for the next byte
+            Mode::Read => {
+                if sda {
+                    self.mode = Mode::Ignore;
+                } else {
+                    self.address = ((self.address as usize + 1) % self.data.len()) as u8;
+                    self.bits = 0;
+                }
+            },
+            Mode::Idle | Mode::Ignore => {},
+        }
+    }
+
+    fn fall(&mut self) {
+        self.output = match self.mode {
+            Mode::Device | Mode::Address | Mode::Write if self.bits == 8 => self.next == Mode::Ignore,
+            Mode::Read if self.bits < 8 => {
+                let bit = match self.model {
+                    Model::X24C01 => self.bits,
+                    Model::C24C02 => 7 - self.bits,
+                };
+                self.data[self.address as usize] >> bit & 1 != 0
+            },
+            _ => true,
+        };
+    }
+
+    // takes a whole byte and says what comes after its acknowledge
+    fn receive(&mut self, byte: u8) -> Mode {
+        match (self.mode, self.model) {
+            (Mode::Device, _) if byte & 0xF0 != 0xA0 => Mode::Ignore,
+            (Mode::Device, _) if byte & 0x01 != 0 => Mode::Read,
+            (Mode::Device, _) => Mode::Address,
+            (Mode::Address, Model::X24C01) => {
+                self.address = byte & 0x7F;
+                if byte & 0x80 != 0 { Mode::Read } else { Mode::Write }
+            },
+            (Mode::Address, Model::C24C02) => {
+                self.address = byte;
+                Mode::Write
+            },
+            _ => {
+                self.data[self.address as usize] = byte;
+                let page = self.model.page();
+                self.address = self.address & !(page - 1) | self.address.wrapping_add(1) & (page - 1);
+                Mode::Write
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SDA falls while SCL is high
+    fn start(chip: &mut Eeprom) {
+        chip.set_lines(false, chip.sda);
+        chip.set_lines(false, true);
+        chip.set_lines(true, true);
+        chip.set_lines(true, false);
+    }
+
+    // SDA rises while SCL is high
+    fn stop(chip: &mut Eeprom) {
+        chip.set_lines(false, chip.sda);
+        chip.set_lines(false, false);
+        chip.set_lines(true, false);
+        chip.set_lines(true, true);
+    }
+
+    // one clock with the master driving `sda`, leaving SCL high; returns
+    // the bus level while it's high
+    fn clock(chip: &mut Eeprom, sda: bool) -> bool {
+        chip.set_lines(false, chip.sda);
+        chip.set_lines(false, sda);
+        chip.set_lines(true, sda);
+        sda && chip.output()
+    }
+
+    fn bit_order(chip: &Eeprom) -> [u8; 8] {
+        match chip.model {
+            Model::X24C01 => [0, 1, 2, 3, 4, 5, 6, 7],
+            Model::C24C02 => [7, 6, 5, 4, 3, 2, 1, 0],
+        }
+    }
+
+    // sends a byte and returns whether the chip acknowledged it
+    fn send(chip: &mut Eeprom, byte: u8) -> bool {
+        for bit in bit_order(chip) {
+            clock(chip, byte >> bit & 1 != 0);
+        }
+        !clock(chip, true)
+    }
+
+    // reads a byte with SDA released, then acks to ask for another or not
+    fn receive(chip: &mut Eeprom, ack: bool) -> u8 {
+        let mut byte = 0;
+        for bit in bit_order(chip) {
+            byte |= (clock(chip, true) as u8) << bit;
+        }
+        clock(chip, !ack);
+        byte
+    }
+
+    #[test]
+    fn c24c02_writes_and_reads_back() {
+        let mut chip = Eeprom::new(Model::C24C02);
+        start(&mut chip);
+        assert!(send(&mut chip, 0xA0));
+        assert!(send(&mut chip, 0x10));
+        assert!(send(&mut chip, 0x42));
+        assert!(send(&mut chip, 0x43));
+        stop(&mut chip);
+        assert_eq!(&chip.data()[0x10..0x12], [0x42, 0x43]);
+
+        // a dummy write sets the address, then a repeated start reads
+        start(&mut chip);
+        assert!(send(&mut chip, 0xA0));
+        assert!(send(&mut chip, 0x10));
+        start(&mut chip);
+        assert!(send(&mut chip, 0xA1));
+        assert_eq!(receive(&mut chip, true), 0x42);
+        assert_eq!(receive(&mut chip, false), 0x43);
+        stop(&mut chip);
+    }
+
+    #[test]
+    fn c24c02_ignores_other_devices() {
+        let mut chip = Eeprom::new(Model::C24C02);
+        start(&mut chip);
+        assert!(!send(&mut chip, 0xB0));
+        assert!(!send(&mut chip, 0x10));
+        stop(&mut chip);
+        assert!(chip.data().iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn writes_wrap_within_a_page() {
+        let mut chip = Eeprom::new(Model::C24C02);
+        start(&mut chip);
+        send(&mut chip, 0xA0);
+        send(&mut chip, 0x07);
+        for byte in [1, 2, 3] {
+            send(&mut chip, byte);
+        }
+        stop(&mut chip);
+        assert_eq!(&chip.data()[..3], [2, 3, 0xFF]);
+        assert_eq!(chip.data()[0x07], 1);
+        assert_eq!(chip.data()[0x08], 0xFF);
+    }
+
+    #[test]
+    fn x24c01_takes_the_address_first_and_lsb_first() {
+        let mut chip = Eeprom::new(Model::X24C01);
+        start(&mut chip);
+        assert!(send(&mut chip, 0x05));
+        assert!(send(&mut chip, 0x81));
+        stop(&mut chip);
+        assert_eq!(chip.data()[0x05], 0x81);
+
+        // R/W in bit 7 of the address byte
+        start(&mut chip);
+        assert!(send(&mut chip, 0x80 | 0x05));
+        assert_eq!(receive(&mut chip, false), 0x81);
+        stop(&mut chip);
+    }
+}
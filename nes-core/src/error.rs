@@ -0,0 +1,51 @@
+use alloc::string::String;
+use core::fmt;
+
+/// Everything that can go wrong while loading or running the emulator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmuError {
+    /// The ROM image is malformed (bad magic, truncated data, ...).
+    InvalidRom(String),
+    /// The ROM uses a mapper that is not implemented.
+    UnsupportedMapper(u16),
+    /// An access or load fell outside of the mapped address space.
+    BadAddress(u32),
+    /// A `.pal` file was not 64 RGB triples; holds the length that was given.
+    InvalidPalette(usize),
+    /// An input movie could not be parsed.
+    InvalidMovie(String),
+    /// A debugger expression could not be parsed.
+    InvalidExpression(String),
+    /// A save state could not be restored.
+    StateLoad(String),
+    /// Battery-backed save data didn't fit the cartridge.
+    SaveData(String),
+    /// The CPU fetched one of the opcodes that halt a real 6502.
+    CpuJam { opcode: u8, pc: u16 },
+    /// The CPU fetched an opcode the core does not implement.
+    IllegalOpcode { opcode: u8, pc: u16 },
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmuError::InvalidRom(reason) => write!(f, "invalid ROM: {}", reason),
+            EmuError::UnsupportedMapper(mapper) => write!(f, "unsupported mapper: {}", mapper),
+            EmuError::BadAddress(addr) => write!(f, "bad address: ${:X}", addr),
+            EmuError::InvalidPalette(len) => write!(f, "palette needs 192 bytes, got {}", len),
+            EmuError::InvalidMovie(reason) => write!(f, "invalid movie: {}", reason),
+            EmuError::InvalidExpression(reason) => write!(f, "invalid expression: {}", reason),
+            EmuError::StateLoad(reason) => write!(f, "failed to load state: {}", reason),
+            EmuError::SaveData(reason) => write!(f, "bad save data: {}", reason),
+            EmuError::CpuJam { opcode, pc } => {
+                write!(f, "CPU jammed on opcode 0x{:02X} at ${:04X}", opcode, pc)
+            },
+            EmuError::IllegalOpcode { opcode, pc } => {
+                write!(f, "illegal opcode 0x{:02X} at ${:04X}", opcode, pc)
+            },
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EmuError {}
@@ -0,0 +1,117 @@
+//! A log of the DMA, interrupt and bank-switch events a frame went
+//! through, for seeing when a game does what it does without stepping.
+//!
+//! The log is a ring buffer that keeps the newest events once full, and is
+//! off until [`EventLog::set_capacity`] gives it room.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use core::fmt::Write;
+
+/// When an event happened: the frame and scanline it fell in, and the
+/// CPU cycle count at the start of the instruction that caused it.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct Timestamp {
+    pub frame: u64,
+    pub scanline: u16,
+    pub cycle: u64,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum EventKind {
+    /// A write to `$4014`, copying this page to sprite memory.
+    OamDma { page: u8 },
+    Nmi,
+    /// An IRQ the CPU took; a masked one isn't logged.
+    Irq,
+    /// The cartridge mapped `bank` into the 8KB window at `addr`.
+    BankSwitch { addr: u16, bank: u16 },
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Event {
+    pub time: Timestamp,
+    pub kind: EventKind,
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct EventLog {
+    events: VecDeque<Event>,
+    capacity: usize,
+    // stamped on events as they are pushed
+    time: Timestamp,
+}
+
+impl EventLog {
+    pub fn new() -> EventLog {
+        EventLog::default()
+    }
+
+    /// Keeps the last `capacity` events; 0 turns logging off. Drops what
+    /// was logged so far.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.events = VecDeque::with_capacity(capacity);
+        self.capacity = capacity;
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The events logged, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Event> {
+        self.events.iter()
+    }
+
+    /// The events as a JSON array of objects, oldest first, e.g.
+    /// `{"frame":3,"scanline":12,"cycle":90210,"event":"bank_switch","addr":32768,"bank":5}`.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("[");
+        for (i, event) in self.events.iter().enumerate() {
+            let Timestamp { frame, scanline, cycle } = event.time;
+            let separator = if i == 0 { "" } else { "," };
+            let _ = write!(out, "{}\n{{\"frame\":{},\"scanline\":{},\"cycle\":{},", separator, frame, scanline, cycle);
+            let _ = match event.kind {
+                EventKind::OamDma { page } => write!(out, "\"event\":\"oam_dma\",\"page\":{}}}", page),
+                EventKind::Nmi => write!(out, "\"event\":\"nmi\"}}"),
+                EventKind::Irq => write!(out, "\"event\":\"irq\"}}"),
+                EventKind::BankSwitch { addr, bank } => {
+                    write!(out, "\"event\":\"bank_switch\",\"addr\":{},\"bank\":{}}}", addr, bank)
+                },
+            };
+        }
+        out.push_str("\n]\n");
+        out
+    }
+
+    pub(crate) fn set_time(&mut self, time: Timestamp) {
+        self.time = time;
+    }
+
+    pub(crate) fn push(&mut self, kind: EventKind) {
+        if self.capacity == 0 {
+            return;
+        }
+        // the oldest slot is reused, so a full log doesn't allocate
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(Event { time: self.time, kind });
+    }
+}
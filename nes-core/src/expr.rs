@@ -0,0 +1,436 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::cpu::CPU;
+use crate::error::EmuError;
+
+/// A CPU register or flag an expression can name.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Register {
+    A,
+    X,
+    Y,
+    Sp,
+    Pc,
+    /// The status register as a byte.
+    P,
+    Carry,
+    Zero,
+    Interrupt,
+    Decimal,
+    Overflow,
+    Negative,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Add,
+    Sub,
+}
+
+/// A debugger expression over registers, flags and memory, e.g.
+/// `A == 0x20 && [0x00F0] > 5`.
+///
+/// Numbers are decimal, or hex with a `0x` or `$` prefix. `[addr]` reads a
+/// byte without side effects. Flags (`C Z I D V N`) are 0 or 1, and so are
+/// comparisons; anything non-zero is true. Brackets, unary operators and
+/// chains of binary operators nest at most 64 deep.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Expr {
+    Number(i32),
+    Register(Register),
+    Memory(Box<Expr>),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+// binary operators from the loosest binding to the tightest
+const LEVELS: [&[(&str, BinaryOp)]; 8] = [
+    &[("||", BinaryOp::Or)],
+    &[("&&", BinaryOp::And)],
+    &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+    &[("<=", BinaryOp::Le), (">=", BinaryOp::Ge), ("<", BinaryOp::Lt), (">", BinaryOp::Gt)],
+    &[("|", BinaryOp::BitOr)],
+    &[("^", BinaryOp::BitXor)],
+    &[("&", BinaryOp::BitAnd)],
+    &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+];
+
+// how deep brackets, unary operators and chains of binary operators may
+// nest, so a condition like `((((...` can't overflow the stack when it's
+// parsed or evaluated
+const MAX_DEPTH: usize = 64;
+
+// longest first so `<=` is not read as `<`
+const SYMBOLS: [&str; 18] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "|", "^", "&", "+", "-", "!", "[", "]", "(", ")",
+];
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+enum Token {
+    Number(i32),
+    Name(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "'{}'", value),
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Symbol(symbol) => write!(f, "'{}'", symbol),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Expr, EmuError> {
+        let mut parser = Parser { tokens: tokenize(text)?, pos: 0, depth: 0 };
+        let expr = parser.binary(0)?;
+
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(token) => Err(invalid(format!("unexpected {}", token))),
+        }
+    }
+
+    pub fn eval(&self, cpu: &CPU) -> i32 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Register(register) => register.value(cpu),
+            Expr::Memory(addr) => cpu.peek(addr.eval(cpu) as u16) as i32,
+            Expr::Not(expr) => (expr.eval(cpu) == 0) as i32,
+            Expr::Negate(expr) => expr.eval(cpu).wrapping_neg(),
+            Expr::Binary(op, left, right) => {
+                let left = left.eval(cpu);
+                // short-circuit so `[..]` reads on the right are skipped
+                match op {
+                    BinaryOp::Or if left != 0 => return 1,
+                    BinaryOp::And if left == 0 => return 0,
+                    _ => {/* evaluate both sides */}
+                }
+                let right = right.eval(cpu);
+
+                match op {
+                    BinaryOp::Or | BinaryOp::And => (right != 0) as i32,
+                    BinaryOp::Eq => (left == right) as i32,
+                    BinaryOp::Ne => (left != right) as i32,
+                    BinaryOp::Lt => (left < right) as i32,
+                    BinaryOp::Le => (left <= right) as i32,
+                    BinaryOp::Gt => (left > right) as i32,
+                    BinaryOp::Ge => (left >= right) as i32,
+                    BinaryOp::BitOr => left | right,
+                    BinaryOp::BitXor => left ^ right,
+                    BinaryOp::BitAnd => left & right,
+                    BinaryOp::Add => left.wrapping_add(right),
+                    BinaryOp::Sub => left.wrapping_sub(right),
+                }
+            },
+        }
+    }
+
+    pub fn is_true(&self, cpu: &CPU) -> bool {
+        self.eval(cpu) != 0
+    }
+}
+
+impl Register {
+    fn from_name(name: &str) -> Option<Register> {
+        let register = match name.to_ascii_uppercase().as_str() {
+            "A" => Register::A,
+            "X" => Register::X,
+            "Y" => Register::Y,
+            "SP" => Register::Sp,
+            "PC" => Register::Pc,
+            "P" => Register::P,
+            "C" => Register::Carry,
+            "Z" => Register::Zero,
+            "I" => Register::Interrupt,
+            "D" => Register::Decimal,
+            "V" => Register::Overflow,
+            "N" => Register::Negative,
+            _ => return None,
+        };
+
+        Some(register)
+    }
+
+    fn value(self, cpu: &CPU) -> i32 {
+        match self {
+            Register::A => cpu.a as i32,
+            Register::X => cpu.x as i32,
+            Register::Y => cpu.y as i32,
+            Register::Sp => cpu.stack_pointer as i32,
+            Register::Pc => cpu.program_counter as i32,
+            Register::P => cpu.status.to_byte() as i32,
+            Register::Carry => cpu.status.carry as i32,
+            Register::Zero => cpu.status.zero as i32,
+            Register::Interrupt => cpu.status.interrupt as i32,
+            Register::Decimal => cpu.status.decimal as i32,
+            Register::Overflow => cpu.status.overflow as i32,
+            Register::Negative => cpu.status.negative as i32,
+        }
+    }
+}
+
+fn invalid(reason: String) -> EmuError {
+    EmuError::InvalidExpression(reason)
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, EmuError> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_alphanumeric() || c == '$' || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '$' || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(word(&rest[..len])?);
+            len
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or_else(|| invalid(format!("unexpected '{}'", c)))?;
+            tokens.push(Token::Symbol(symbol));
+            symbol.len()
+        };
+
+        rest = rest[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+fn word(word: &str) -> Result<Token, EmuError> {
+    let number = if let Some(hex) = word.strip_prefix("0x").or_else(|| word.strip_prefix('$')) {
+        i32::from_str_radix(hex, 16)
+    } else if word.starts_with(|c: char| c.is_ascii_digit()) {
+        word.parse()
+    } else {
+        return Ok(Token::Name(word.to_string()));
+    };
+
+    number.map(Token::Number).map_err(|_| invalid(format!("bad number '{}'", word)))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // how many levels the expression being parsed sits below the root
+    depth: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), EmuError> {
+        match self.next() {
+            Some(Token::Symbol(found)) if found == symbol => Ok(()),
+            Some(token) => Err(invalid(format!("expected '{}', found {}", symbol, token))),
+            None => Err(invalid(format!("expected '{}'", symbol))),
+        }
+    }
+
+    // goes a level further down, failing past MAX_DEPTH
+    fn deeper(&mut self) -> Result<(), EmuError> {
+        if self.depth == MAX_DEPTH {
+            return Err(invalid(format!("nested deeper than {} levels", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn nested(&mut self, parse: impl FnOnce(&mut Parser) -> Result<Expr, EmuError>) -> Result<Expr, EmuError> {
+        let depth = self.depth;
+        self.deeper()?;
+        let expr = parse(self)?;
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, EmuError> {
+        let Some(operators) = LEVELS.get(level) else {
+            return self.unary();
+        };
+
+        let depth = self.depth;
+        let mut left = self.binary(level + 1)?;
+
+        loop {
+            let op = match self.tokens.get(self.pos) {
+                Some(Token::Symbol(symbol)) => operators.iter().find(|(text, _)| text == symbol),
+                _ => None,
+            };
+            let Some(&(_, op)) = op else {
+                self.depth = depth;
+                return Ok(left);
+            };
+
+            // each operator in a chain puts the ones before it a level down
+            self.pos += 1;
+            self.deeper()?;
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, EmuError> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Name(name)) => Register::from_name(&name)
+                .map(Expr::Register)
+                .ok_or_else(|| invalid(format!("unknown register '{}'", name))),
+            Some(Token::Symbol("!")) => Ok(Expr::Not(Box::new(self.nested(Parser::unary)?))),
+            Some(Token::Symbol("-")) => Ok(Expr::Negate(Box::new(self.nested(Parser::unary)?))),
+            Some(Token::Symbol("[")) => {
+                let addr = self.nested(|parser| parser.binary(0))?;
+                self.expect("]")?;
+                Ok(Expr::Memory(Box::new(addr)))
+            },
+            Some(Token::Symbol("(")) => {
+                let expr = self.nested(|parser| parser.binary(0))?;
+                self.expect(")")?;
+                Ok(expr)
+            },
+            Some(token) => Err(invalid(format!("unexpected {}", token))),
+            None => Err(invalid("unexpected end of expression".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::*;
+    use crate::bus::Bus;
+
+    fn eval(text: &str) -> i32 {
+        let mut cpu = CPU::new(Bus::new());
+        cpu.a = 0x20;
+        cpu.x = 3;
+        cpu.program_counter = 0xC000;
+        cpu.status.carry = true;
+        cpu.bus.ram[0x00F0] = 7;
+        cpu.bus.ram[0x0007] = 0x42;
+        Expr::parse(text).unwrap().eval(&cpu)
+    }
+
+    fn error(text: &str) -> String {
+        match Expr::parse(text) {
+            Err(EmuError::InvalidExpression(reason)) => reason,
+            result => panic!("{:?} parsed as {:?}", text, result),
+        }
+    }
+
+    fn number(value: i32) -> Box<Expr> {
+        Box::new(Expr::Number(value))
+    }
+
+    #[test]
+    fn binds_tighter_operators_first() {
+        let expected = Expr::Binary(BinaryOp::Eq, Box::new(Expr::Binary(BinaryOp::Add, number(1), number(2))), number(3));
+        assert_eq!(Expr::parse("1 + 2 == 3").unwrap(), expected);
+
+        assert_eq!(eval("1 | 2 & 3"), 3);
+        assert_eq!(eval("6 ^ 3 & 1"), 7);
+        assert_eq!(eval("1 + 1 < 3"), 1);
+        assert_eq!(eval("0 && 1 || 1"), 1);
+        assert_eq!(eval("1 || 0 && 0"), 1);
+    }
+
+    #[test]
+    fn chains_left_to_right() {
+        assert_eq!(eval("5 - 2 - 1"), 2);
+        assert_eq!(eval("1 - 2 + 3"), 2);
+        assert_eq!(eval("3 > 2 > 0"), 1);
+    }
+
+    #[test]
+    fn applies_unary_operators() {
+        assert_eq!(eval("-1 + 2"), 1);
+        assert_eq!(eval("--4"), 4);
+        assert_eq!(eval("!0"), 1);
+        assert_eq!(eval("!!5"), 1);
+        assert_eq!(eval("-(1 + 2)"), -3);
+        assert_eq!(eval("!(A == 0x20)"), 0);
+    }
+
+    #[test]
+    fn groups_with_parentheses() {
+        assert_eq!(eval("(1 | 2) & 2"), 2);
+        assert_eq!(eval("5 - (2 - 1)"), 4);
+        assert_eq!(eval("((((1))))"), 1);
+    }
+
+    #[test]
+    fn reads_registers_flags_and_numbers() {
+        assert_eq!(eval("A"), 0x20);
+        assert_eq!(eval("x + 1"), 4);
+        assert_eq!(eval("PC == $C000"), 1);
+        assert_eq!(eval("C + Z"), 1);
+        assert_eq!(eval("0x10 + 10"), 26);
+    }
+
+    #[test]
+    fn reads_memory() {
+        assert_eq!(eval("[0x00F0]"), 7);
+        assert_eq!(eval("[$F0] > 5"), 1);
+        assert_eq!(eval("[[0xF0]]"), 0x42);
+        assert_eq!(eval("[0xEF + 1] == 7"), 1);
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(error(""), "unexpected end of expression");
+        assert_eq!(error("1 +"), "unexpected end of expression");
+        assert_eq!(error("(1"), "expected ')'");
+        assert_eq!(error("[1)"), "expected ']', found ')'");
+        assert_eq!(error("1 2"), "unexpected '2'");
+        assert_eq!(error("foo"), "unknown register 'foo'");
+        assert_eq!(error("0xZZ"), "bad number '0xZZ'");
+        assert_eq!(error("A @ 1"), "unexpected '@'");
+    }
+
+    #[test]
+    fn caps_nesting() {
+        let deep = |open: &str, close: &str, levels: usize| {
+            let mut text = open.repeat(levels);
+            text.push('1');
+            text.push_str(&close.repeat(levels));
+            text
+        };
+        assert_eq!(eval(&deep("(", ")", MAX_DEPTH)), 1);
+
+        let expected = format!("nested deeper than {} levels", MAX_DEPTH);
+        assert_eq!(error(&deep("(", ")", 100_000)), expected);
+        assert_eq!(error(&deep("[", "]", MAX_DEPTH + 1)), expected);
+        assert_eq!(error(&deep("-", "", 100_000)), expected);
+        assert_eq!(error(&deep("!", "", MAX_DEPTH + 1)), expected);
+
+        let mut chain = "1 + ".repeat(100_000);
+        chain.push('1');
+        assert_eq!(error(&chain), expected);
+    }
+}
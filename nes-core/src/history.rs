@@ -0,0 +1,286 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::constants::Status;
+use crate::cpu::CPU;
+use crate::mapper::{Mapper, STATE_SIZE};
+
+// the most bytes one instruction writes: BRK pushes three
+const MAX_WRITES: usize = 3;
+
+/// What an instruction changed, collected by the bus as it runs.
+#[derive(Default)]
+pub(crate) struct Journal {
+    // old values of the bytes written to memory, PRG RAM included
+    writes: Vec<(u16, u8)>,
+    // the board's state before the first write to one of its registers;
+    // writing the old byte back would be another register write, so the
+    // whole board is put back instead
+    board: Vec<u8>,
+    board_saved: bool,
+}
+
+impl Journal {
+    fn new() -> Journal {
+        Journal { writes: Vec::with_capacity(MAX_WRITES), board: Vec::new(), board_saved: false }
+    }
+
+    fn clear(&mut self) {
+        self.writes.clear();
+        self.board_saved = false;
+    }
+
+    /// Notes a write of `addr`, which held `old`, before it happens.
+    pub(crate) fn record(&mut self, addr: u16, old: u8, cartridge: Option<&dyn Mapper>) {
+        match (cartridge, addr) {
+            (Some(cartridge), 0x4020..=0xFFFF) if !cartridge.is_ram(addr) => {
+                if !self.board_saved {
+                    self.save_board(cartridge);
+                    self.board_saved = true;
+                }
+            },
+            _ => self.writes.push((addr, old)),
+        }
+    }
+
+    fn save_board(&mut self, cartridge: &dyn Mapper) {
+        // a board always saves the same number of bytes, so only a journal
+        // that has never held its state needs room for the largest
+        if self.board.is_empty() {
+            self.board.resize(STATE_SIZE, 0);
+            let len = cartridge.save_state(&mut self.board);
+            self.board.truncate(len);
+            self.board.shrink_to_fit();
+        } else {
+            cartridge.save_state(&mut self.board);
+        }
+    }
+}
+
+/// The registers before an instruction or interrupt entry ran, and what it
+/// wrote.
+struct Entry {
+    a: u8,
+    x: u8,
+    y: u8,
+    stack_pointer: u8,
+    program_counter: u16,
+    status: u8,
+    complete: bool,
+    journal: Journal,
+}
+
+/// The most recently executed instructions, newest last, kept so the
+/// debugger can undo them one at a time. Taking an interrupt counts as
+/// one, so stepping back over it pops what it pushed.
+///
+/// The CPU registers, memory and any board registers an instruction wrote
+/// go back; frame timing, board timers and the call stack carry on from
+/// where they were.
+#[derive(Default)]
+pub struct History {
+    entries: VecDeque<Entry>,
+    capacity: usize,
+}
+
+impl History {
+    /// A history of up to `capacity` instructions; 0 records nothing.
+    pub fn new(capacity: usize) -> History {
+        History { entries: VecDeque::new(), capacity }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Runs `run` on an instruction boundary, e.g. `CPU::clock` or
+    /// `CPU::irq`, journaling what it changes.
+    pub(crate) fn record<T>(&mut self, cpu: &mut CPU, run: impl FnOnce(&mut CPU) -> T) -> T {
+        // once full, the oldest entry's journal is reused so recording
+        // doesn't allocate
+        let mut journal = if self.entries.len() == self.capacity {
+            self.entries.pop_front().map(|entry| entry.journal).unwrap_or_default()
+        } else {
+            Journal::new()
+        };
+        journal.clear();
+
+        let mut entry = Entry {
+            a: cpu.a,
+            x: cpu.x,
+            y: cpu.y,
+            stack_pointer: cpu.stack_pointer,
+            program_counter: cpu.program_counter,
+            status: cpu.status.to_byte(),
+            complete: cpu.complete,
+            journal: Journal::default(),
+        };
+
+        cpu.bus.journal = Some(journal);
+        let result = run(cpu);
+        entry.journal = cpu.bus.journal.take().unwrap_or_default();
+        self.entries.push_back(entry);
+
+        result
+    }
+
+    /// Undoes the newest instruction; returns false if there is none.
+    pub(crate) fn undo(&mut self, cpu: &mut CPU) -> bool {
+        let Some(entry) = self.entries.pop_back() else {
+            return false;
+        };
+
+        // the board goes back first: its state may hold PRG RAM bytes the
+        // instruction wrote before touching a register
+        if let Some(cartridge) = cpu.bus.cartridge.as_mut().filter(|_| entry.journal.board_saved) {
+            cartridge.load_state(&entry.journal.board);
+        }
+        for &(addr, value) in entry.journal.writes.iter().rev() {
+            cpu.bus.poke(addr, value);
+        }
+
+        cpu.a = entry.a;
+        cpu.x = entry.x;
+        cpu.y = entry.y;
+        cpu.stack_pointer = entry.stack_pointer;
+        cpu.program_counter = entry.program_counter;
+        cpu.status = Status::from_byte(entry.status);
+        cpu.complete = entry.complete;
+        cpu.cycles = 0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use crate::irq::IrqSource;
+    use crate::nes::Nes;
+
+    // a Namco 108 (mapper 206) cartridge with eight 8KB PRG banks, bank n
+    // filled with 7 - n, running `program` from $E000 with the IRQ handler
+    // at $E100
+    fn nes(program: &[u8]) -> Nes {
+        let mut prg: Vec<u8> = (0..8u8).flat_map(|bank| vec![7 - bank; 8 * 1024]).collect();
+        let last = prg.len() - 0x2000;
+        prg[last..last + program.len()].copy_from_slice(program);
+        prg[last + 0x100] = 0x40; // E100: RTI
+        let end = prg.len();
+        prg[end - 4..].copy_from_slice(&[0x00, 0xE0, 0x00, 0xE1]);
+
+        let mut rom = vec![b'N', b'E', b'S', 0x1A, 4, 0, 0xE0, 0xC0, 0, 0, 0, 0, 0, 0, 0, 0];
+        rom.extend(prg);
+        let mut nes = Nes::builder().build();
+        nes.load_rom(&rom).unwrap();
+        nes.set_history_capacity(100);
+        nes
+    }
+
+    fn stack(nes: &Nes) -> Vec<u8> {
+        nes.cpu.bus.ram[0x0100..0x0200].to_vec()
+    }
+
+    #[test]
+    fn undoes_registers_and_ram() {
+        // LDA #$2A; STA $10; INC $10
+        let mut nes = nes(&[0xA9, 0x2A, 0x85, 0x10, 0xE6, 0x10]);
+        for _ in 0..3 {
+            nes.step_instruction().unwrap();
+        }
+        assert_eq!(nes.cpu.bus.peek(0x10), 0x2B);
+
+        assert!(nes.step_back());
+        assert_eq!(nes.cpu.bus.peek(0x10), 0x2A);
+        assert_eq!(nes.cpu.program_counter, 0xE004);
+        assert!(nes.step_back());
+        assert!(nes.step_back());
+        assert_eq!(nes.cpu.bus.peek(0x10), 0x00);
+        assert_eq!(nes.cpu.program_counter, 0xE000);
+        assert!(!nes.step_back());
+    }
+
+    #[test]
+    fn undoes_bank_switches_without_writing_the_board() {
+        // LDA #$06; STA $8000; LDA #$05; STA $8001
+        let mut nes = nes(&[0xA9, 0x06, 0x8D, 0x00, 0x80, 0xA9, 0x05, 0x8D, 0x01, 0x80]);
+        for _ in 0..4 {
+            nes.step_instruction().unwrap();
+        }
+        assert_eq!(nes.cpu.bus.peek(0x8000), 7 - 5);
+
+        // writing back the old ROM byte, 7, would have selected bank 7
+        assert!(nes.step_back());
+        assert_eq!(nes.cpu.bus.peek(0x8000), 7);
+    }
+
+    #[test]
+    fn undoes_prg_ram_writes_without_saving_the_board() {
+        // an NROM cartridge; LDA #$2A; STA $6000
+        let mut prg = vec![0; 16 * 1024];
+        prg[..5].copy_from_slice(&[0xA9, 0x2A, 0x8D, 0x00, 0x60]);
+        prg[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0xC0]);
+        let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        rom.extend(prg);
+        let mut nes = Nes::builder().build();
+        nes.load_rom(&rom).unwrap();
+        nes.set_history_capacity(100);
+
+        nes.step_instruction().unwrap();
+        nes.step_instruction().unwrap();
+        assert_eq!(nes.cpu.bus.peek(0x6000), 0x2A);
+        let journal = &nes.history().entries.back().unwrap().journal;
+        assert_eq!(journal.writes, [(0x6000, 0x00)]);
+        assert!(!journal.board_saved && journal.board.capacity() == 0);
+
+        assert!(nes.step_back());
+        assert_eq!(nes.cpu.bus.peek(0x6000), 0x00);
+    }
+
+    #[test]
+    fn undoes_a_subroutine_call() {
+        // JSR $E010
+        let mut nes = nes(&[0x20, 0x10, 0xE0]);
+        let before = stack(&nes);
+        let sp = nes.cpu.stack_pointer;
+        nes.step_instruction().unwrap();
+        assert_eq!(nes.cpu.stack_pointer, sp.wrapping_sub(2));
+
+        assert!(nes.step_back());
+        assert_eq!(nes.cpu.program_counter, 0xE000);
+        assert_eq!(nes.cpu.stack_pointer, sp);
+        assert_eq!(stack(&nes), before);
+    }
+
+    #[test]
+    fn undoes_interrupt_entry() {
+        // CLI; then spin
+        let mut nes = nes(&[0x58, 0x4C, 0x01, 0xE0]);
+        nes.step_instruction().unwrap();
+        let before = stack(&nes);
+        let sp = nes.cpu.stack_pointer;
+
+        nes.cpu.bus.irq.assert(IrqSource::External);
+        nes.step_instruction().unwrap();
+        assert_eq!(nes.cpu.program_counter, 0xE100);
+        assert_ne!(stack(&nes), before);
+
+        assert!(nes.step_back());
+        assert_eq!(nes.cpu.program_counter, 0xE001);
+        assert_eq!(nes.cpu.stack_pointer, sp);
+        assert_eq!(stack(&nes), before);
+    }
+}
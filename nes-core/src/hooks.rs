@@ -0,0 +1,287 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::bus::Bus;
+use crate::constants::OpCode;
+
+/// Interrupt sequences the CPU can enter.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Interrupt {
+    Nmi,
+    Irq,
+    Brk,
+}
+
+/// An instruction about to execute, decoded against the registers and
+/// memory as they are before it runs.
+#[derive(Copy, Clone)]
+pub struct Instruction {
+    pub pc: u16,
+    pub opcode: &'static OpCode,
+    /// The bytes after the opcode; only the first `opcode.bytes - 1` are
+    /// meaningful.
+    pub operand: [u8; 2],
+    /// The address the instruction reads, writes or jumps to, with
+    /// indexing applied; a branch's is its target whether or not it's
+    /// taken. `None` for implied, accumulator and immediate operands.
+    pub address: Option<u16>,
+}
+
+impl Instruction {
+    /// The operand bytes that are part of the instruction.
+    pub fn operand_bytes(&self) -> &[u8] {
+        &self.operand[..self.opcode.bytes as usize - 1]
+    }
+}
+
+/// When a change subscription compares the values it watches.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ChangeCheck {
+    /// After each frame, against the values after the one before. Costs
+    /// nothing during the frame and sees changes made any way, including
+    /// by loading a state, but reports each address at most once a frame
+    /// and misses changes undone within one.
+    Frame,
+    /// On every CPU write that changes a value, as it happens.
+    Write,
+}
+
+/// Handle returned when registering a hook, used to remove it again.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
+pub struct HookId(u32);
+
+type InstructionHook = Box<dyn FnMut(u16, &OpCode) + Send>;
+type DecodedHook = Box<dyn FnMut(&Instruction) + Send>;
+type MemoryHook = Box<dyn FnMut(u16, u8) + Send>;
+type InterruptHook = Box<dyn FnMut(Interrupt) + Send>;
+type ScanlineHook = Box<dyn FnMut(u16) + Send>;
+type FrameHook = Box<dyn FnMut(u64) + Send>;
+type ChangeHook = Box<dyn FnMut(u16, u8, u8) + Send>;
+
+struct Change {
+    id: HookId,
+    range: RangeInclusive<u16>,
+    check: ChangeCheck,
+    // the values at the last frame boundary, empty until the first
+    last: Vec<u8>,
+    hook: ChangeHook,
+}
+
+/// Callbacks that observe emulation without changing it. Registering no
+/// hooks of a kind costs nothing beyond an emptiness check.
+#[derive(Default)]
+pub struct Hooks {
+    next_id: u32,
+    instruction: Vec<(HookId, InstructionHook)>,
+    decoded: Vec<(HookId, DecodedHook)>,
+    read: Vec<(HookId, RangeInclusive<u16>, MemoryHook)>,
+    write: Vec<(HookId, RangeInclusive<u16>, MemoryHook)>,
+    interrupt: Vec<(HookId, InterruptHook)>,
+    scanline: Vec<(HookId, ScanlineHook)>,
+    frame: Vec<(HookId, FrameHook)>,
+    change: Vec<Change>,
+}
+
+impl Hooks {
+    pub fn new() -> Hooks {
+        Hooks::default()
+    }
+
+    fn next_id(&mut self) -> HookId {
+        self.next_id += 1;
+        HookId(self.next_id)
+    }
+
+    /// Called with the PC and opcode before each instruction executes.
+    pub fn on_instruction(&mut self, hook: impl FnMut(u16, &OpCode) + Send + 'static) -> HookId {
+        let id = self.next_id();
+        self.instruction.push((id, Box::new(hook)));
+        id
+    }
+
+    /// Called before each instruction executes with it fully decoded, for
+    /// tools that need its operand or the address it touches. Decoding
+    /// costs a few side-effect-free reads per instruction, so prefer
+    /// [`Hooks::on_instruction`] when the opcode is enough.
+    pub fn on_decoded(&mut self, hook: impl FnMut(&Instruction) + Send + 'static) -> HookId {
+        let id = self.next_id();
+        self.decoded.push((id, Box::new(hook)));
+        id
+    }
+
+    /// Called with the address and value of every CPU read inside `range`.
+    pub fn on_read(&mut self, range: RangeInclusive<u16>, hook: impl FnMut(u16, u8) + Send + 'static) -> HookId {
+        let id = self.next_id();
+        self.read.push((id, range, Box::new(hook)));
+        id
+    }
+
+    /// Called with the address and value of every CPU write inside `range`.
+    pub fn on_write(&mut self, range: RangeInclusive<u16>, hook: impl FnMut(u16, u8) + Send + 'static) -> HookId {
+        let id = self.next_id();
+        self.write.push((id, range, Box::new(hook)));
+        id
+    }
+
+    /// Called whenever the CPU enters an interrupt sequence.
+    pub fn on_interrupt(&mut self, hook: impl FnMut(Interrupt) + Send + 'static) -> HookId {
+        let id = self.next_id();
+        self.interrupt.push((id, Box::new(hook)));
+        id
+    }
+
+    /// Called with the scanline number at the start of each scanline.
+    pub fn on_scanline(&mut self, hook: impl FnMut(u16) + Send + 'static) -> HookId {
+        let id = self.next_id();
+        self.scanline.push((id, Box::new(hook)));
+        id
+    }
+
+    /// Called with the frame number after each frame completes.
+    pub fn on_frame(&mut self, hook: impl FnMut(u64) + Send + 'static) -> HookId {
+        let id = self.next_id();
+        self.frame.push((id, Box::new(hook)));
+        id
+    }
+
+    /// Called with an address, its old value and its new one whenever a
+    /// value inside `range` changes, as `check` says; the primitive for
+    /// HUDs, auto-splitters and achievements. With [`ChangeCheck::Frame`]
+    /// the first frame only records the values.
+    pub fn on_change(
+        &mut self,
+        range: RangeInclusive<u16>,
+        check: ChangeCheck,
+        hook: impl FnMut(u16, u8, u8) + Send + 'static,
+    ) -> HookId {
+        let id = self.next_id();
+        self.change.push(Change { id, range, check, last: Vec::new(), hook: Box::new(hook) });
+        id
+    }
+
+    /// Unregisters a hook, returning whether it was found.
+    pub fn remove(&mut self, id: HookId) -> bool {
+        let before = self.len();
+
+        self.instruction.retain(|(hook_id, _)| *hook_id != id);
+        self.decoded.retain(|(hook_id, _)| *hook_id != id);
+        self.read.retain(|(hook_id, _, _)| *hook_id != id);
+        self.write.retain(|(hook_id, _, _)| *hook_id != id);
+        self.interrupt.retain(|(hook_id, _)| *hook_id != id);
+        self.scanline.retain(|(hook_id, _)| *hook_id != id);
+        self.frame.retain(|(hook_id, _)| *hook_id != id);
+        self.change.retain(|change| change.id != id);
+
+        self.len() != before
+    }
+
+    pub fn len(&self) -> usize {
+        self.instruction.len() + self.decoded.len() + self.read.len() + self.write.len()
+            + self.interrupt.len() + self.scanline.len() + self.frame.len() + self.change.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn instruction(&mut self, pc: u16, opcode: &OpCode) {
+        for (_, hook) in self.instruction.iter_mut() {
+            hook(pc, opcode);
+        }
+    }
+
+    pub(crate) fn decoded(&mut self, instruction: &Instruction) {
+        for (_, hook) in self.decoded.iter_mut() {
+            hook(instruction);
+        }
+    }
+
+    pub(crate) fn memory_read(&mut self, addr: u16, data: u8) {
+        for (_, range, hook) in self.read.iter_mut() {
+            if range.contains(&addr) {
+                hook(addr, data);
+            }
+        }
+    }
+
+    pub(crate) fn memory_write(&mut self, addr: u16, data: u8) {
+        for (_, range, hook) in self.write.iter_mut() {
+            if range.contains(&addr) {
+                hook(addr, data);
+            }
+        }
+    }
+
+    pub(crate) fn interrupt(&mut self, interrupt: Interrupt) {
+        for (_, hook) in self.interrupt.iter_mut() {
+            hook(interrupt);
+        }
+    }
+
+    pub(crate) fn scanline(&mut self, scanline: u16) {
+        for (_, hook) in self.scanline.iter_mut() {
+            hook(scanline);
+        }
+    }
+
+    pub(crate) fn frame(&mut self, frame: u64) {
+        for (_, hook) in self.frame.iter_mut() {
+            hook(frame);
+        }
+    }
+
+    pub(crate) fn write_change(&mut self, addr: u16, old: u8, new: u8) {
+        if old == new {
+            return;
+        }
+
+        for change in self.change.iter_mut() {
+            if change.check == ChangeCheck::Write && change.range.contains(&addr) {
+                (change.hook)(addr, old, new);
+            }
+        }
+    }
+
+    // compares frame subscriptions against memory as it is now
+    pub(crate) fn frame_changes(&mut self, bus: &Bus) {
+        for change in self.change.iter_mut().filter(|change| change.check == ChangeCheck::Frame) {
+            let first = change.last.is_empty();
+            if first {
+                change.last.resize(change.range.clone().count(), 0);
+            }
+
+            for (addr, last) in change.range.clone().zip(change.last.iter_mut()) {
+                let value = bus.peek(addr);
+                if value != *last && !first {
+                    (change.hook)(addr, *last, value);
+                }
+                *last = value;
+            }
+        }
+    }
+
+    pub(crate) fn watches_write_changes(&self) -> bool {
+        self.change.iter().any(|change| change.check == ChangeCheck::Write)
+    }
+
+    pub(crate) fn watches_frame_changes(&self) -> bool {
+        self.change.iter().any(|change| change.check == ChangeCheck::Frame)
+    }
+
+    pub(crate) fn watches_instructions(&self) -> bool {
+        !self.instruction.is_empty()
+    }
+
+    pub(crate) fn watches_decoded(&self) -> bool {
+        !self.decoded.is_empty()
+    }
+
+    pub(crate) fn watches_reads(&self) -> bool {
+        !self.read.is_empty()
+    }
+
+    pub(crate) fn watches_writes(&self) -> bool {
+        !self.write.is_empty()
+    }
+}
@@ -0,0 +1,86 @@
+use crate::error::EmuError;
+use crate::state::{StateReader, StateWriter};
+
+/// Things that can pull the CPU's /IRQ line low.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum IrqSource {
+    /// The APU frame counter's interrupt flag.
+    FrameCounter,
+    /// The APU's DMC reaching the end of a sample.
+    Dmc,
+    /// The cartridge board, e.g. a scanline or cycle counter.
+    Mapper,
+    /// Anything on the expansion port, or a frontend or test.
+    External,
+}
+
+impl IrqSource {
+    pub const ALL: [IrqSource; 4] = [IrqSource::FrameCounter, IrqSource::Dmc, IrqSource::Mapper, IrqSource::External];
+
+    fn mask(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// The CPU's /IRQ input, wired-OR between every source.
+///
+/// The line is level-triggered: it stays asserted while any source holds
+/// it, and the CPU takes an interrupt at each instruction boundary where
+/// it's asserted and the I flag is clear. Each source lets go on its own
+/// acknowledge, so one being serviced doesn't hide another that asserted
+/// at the same time.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct IrqLine {
+    sources: u8,
+}
+
+impl IrqLine {
+    pub fn new() -> IrqLine {
+        IrqLine::default()
+    }
+
+    pub fn assert(&mut self, source: IrqSource) {
+        self.sources |= source.mask();
+    }
+
+    /// Lets go of the line for `source` only.
+    pub fn acknowledge(&mut self, source: IrqSource) {
+        self.sources &= !source.mask();
+    }
+
+    /// Asserts or acknowledges, for sources that report a level.
+    pub fn set(&mut self, source: IrqSource, asserted: bool) {
+        if asserted {
+            self.assert(source);
+        } else {
+            self.acknowledge(source);
+        }
+    }
+
+    /// Whether any source is holding the line.
+    pub fn is_asserted(&self) -> bool {
+        self.sources != 0
+    }
+
+    pub fn is_held_by(&self, source: IrqSource) -> bool {
+        self.sources & source.mask() != 0
+    }
+
+    /// The sources holding the line, for debuggers.
+    pub fn sources(&self) -> impl Iterator<Item = IrqSource> + '_ {
+        IrqSource::ALL.into_iter().filter(|&source| self.is_held_by(source))
+    }
+
+    pub fn clear(&mut self) {
+        self.sources = 0;
+    }
+
+    pub(crate) fn save_state(&self, state: &mut StateWriter) {
+        state.u8(self.sources);
+    }
+
+    pub(crate) fn load_state(&mut self, state: &mut StateReader) -> Result<(), EmuError> {
+        self.sources = state.u8()?;
+        Ok(())
+    }
+}
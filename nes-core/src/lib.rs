@@ -0,0 +1,57 @@
+//! Emulation core for the NES / 6502.
+//!
+//! The core does no I/O of its own: frontends own the window, audio device
+//! and input, and drive the emulator through the types exported here.
+//!
+//! ```
+//! use nes_core::bus::Bus;
+//! use nes_core::cpu::CPU;
+//!
+//! # fn main() -> Result<(), nes_core::error::EmuError> {
+//! let mut cpu = CPU::new(Bus::new());
+//! cpu.load(&[0xA9, 0x42, 0x00])?; // LDA #$42; BRK
+//! cpu.power_on();
+//!
+//! while !cpu.complete {
+//!     cpu.clock()?;
+//! }
+//!
+//! assert_eq!(cpu.a, 0x42);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With `default-features = false` the crate is `no_std` and only needs
+//! `alloc`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod constants;
+pub mod cpu;
+#[cfg(feature = "match-dispatch")]
+mod dispatch;
+pub mod bus;
+pub mod cartridge;
+pub mod mapper;
+pub mod controller;
+pub mod error;
+pub mod trace;
+pub mod palette;
+pub mod nes;
+pub mod hooks;
+pub mod state;
+pub mod debugger;
+pub mod expr;
+pub mod history;
+pub mod profiler;
+pub mod movie;
+pub mod nestest;
+pub mod lockstep;
+pub mod blargg;
+pub mod vs;
+pub mod eeprom;
+pub mod irq;
+pub mod debug_port;
+pub mod events;
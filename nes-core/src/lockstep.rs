@@ -0,0 +1,253 @@
+//! Runs a ROM in lockstep with a reference trace from another emulator and
+//! stops at the first instruction where the two disagree, for tracking
+//! down accuracy bugs.
+//!
+//! Each line of the reference is one instruction, logged before it runs,
+//! starting with its address in hex. The registers are read from `A:`,
+//! `X:`, `Y:`, `P:` and `SP:` (or `S:`) fields and the CPU cycle count
+//! from `CYC:` or `Cycle:`, so nestest-style logs and Mesen's traces both
+//! work; `P` may be hex or flag letters, upper case for set, as in
+//! `nvUbdIzc`. Fields a line lacks aren't compared.
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::error::EmuError;
+use crate::nes::Nes;
+
+/// Reference lines shown before a divergence.
+pub const CONTEXT: usize = 8;
+
+// B and the unused bit only exist on the stack, and emulators log them
+// differently
+const FLAGS: u8 = 0xCF;
+
+/// The first instruction where the emulator and the reference disagree.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Divergence {
+    /// 1-based line in the reference.
+    pub line: usize,
+    /// What differs, e.g. `A is $05, expected $04`.
+    pub reason: String,
+    pub expected: String,
+    /// The emulator's instruction, in the nestest format with `CYC:`.
+    pub actual: String,
+    /// The reference lines leading up to it, oldest first.
+    pub context: Vec<String>,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Outcome {
+    /// Every line of the reference matched.
+    Passed { lines: usize },
+    Diverged(Divergence),
+    /// Emulation failed before the instruction on `line`.
+    Failed { line: usize, error: EmuError },
+}
+
+/// What a reference line says the CPU looks like before its instruction.
+#[derive(Default)]
+struct Expected {
+    pc: Option<u16>,
+    a: Option<u8>,
+    x: Option<u8>,
+    y: Option<u8>,
+    status: Option<u8>,
+    stack_pointer: Option<u8>,
+    cycles: Option<u64>,
+}
+
+impl Expected {
+    fn parse(line: &str) -> Expected {
+        Expected {
+            pc: line.get(..4).and_then(|pc| u16::from_str_radix(pc, 16).ok()),
+            a: field(line, "A:").and_then(hex),
+            x: field(line, "X:").and_then(hex),
+            y: field(line, "Y:").and_then(hex),
+            status: field(line, "P:").and_then(|text| hex(text).or_else(|| flags(text))),
+            stack_pointer: field(line, "SP:").or_else(|| field(line, "S:")).and_then(hex),
+            cycles: field(line, "CYC:").or_else(|| field(line, "Cycle:")).and_then(|text| text.parse().ok()),
+        }
+    }
+}
+
+/// Powers on into `rom` and runs it against `reference`, one instruction
+/// per line, for as many lines as it has.
+///
+/// Cycle counts are compared as the cycles each instruction took, since
+/// emulators start counting at different points of the reset sequence.
+pub fn run(rom: &[u8], reference: &str) -> Result<Outcome, EmuError> {
+    let mut nes = Nes::builder().build();
+    nes.load_rom(rom)?;
+
+    // the reset sequence is still in flight after loading
+    while nes.cpu.cycles > 0 {
+        nes.clock()?;
+    }
+
+    let mut context = VecDeque::with_capacity(CONTEXT);
+    let mut previous: Option<(u64, u64)> = None;
+    let mut lines = 0;
+
+    for (i, text) in reference.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let line = i + 1;
+        lines += 1;
+
+        let expected = Expected::parse(text);
+        let cycles = nes.cpu.total_cycles;
+        if let Some(reason) = compare(&nes, &expected, previous, cycles) {
+            let actual = match nes.cpu.trace_entry() {
+                Some(entry) => format!("{} CYC:{}", entry, cycles),
+                None => format!("{} CYC:{}", nes.cpu.state(), cycles),
+            };
+            return Ok(Outcome::Diverged(Divergence {
+                line,
+                reason,
+                expected: text.to_string(),
+                actual,
+                context: context.into_iter().collect(),
+            }));
+        }
+
+        if context.len() == CONTEXT {
+            context.pop_front();
+        }
+        context.push_back(text.to_string());
+        previous = expected.cycles.map(|expected| (expected, cycles));
+
+        if let Err(error) = step(&mut nes) {
+            return Ok(Outcome::Failed { line, error });
+        }
+    }
+
+    Ok(Outcome::Passed { lines })
+}
+
+// the first difference between the CPU and a reference line; `previous`
+// holds the reference's and the emulator's cycle counts a line earlier
+fn compare(nes: &Nes, expected: &Expected, previous: Option<(u64, u64)>, cycles: u64) -> Option<String> {
+    let cpu = &nes.cpu;
+    if let Some(pc) = expected.pc.filter(|&pc| pc != cpu.program_counter) {
+        return Some(format!("PC is ${:04X}, expected ${:04X}", cpu.program_counter, pc));
+    }
+
+    let registers = [
+        ("A", cpu.a, expected.a),
+        ("X", cpu.x, expected.x),
+        ("Y", cpu.y, expected.y),
+        ("SP", cpu.stack_pointer, expected.stack_pointer),
+    ];
+    for (name, actual, expected) in registers {
+        if let Some(expected) = expected.filter(|&expected| expected != actual) {
+            return Some(format!("{} is ${:02X}, expected ${:02X}", name, actual, expected));
+        }
+    }
+
+    let status = cpu.status.to_byte();
+    if let Some(expected) = expected.status.filter(|&expected| expected & FLAGS != status & FLAGS) {
+        return Some(format!("P is {}, expected {}", letters(status), letters(expected)));
+    }
+
+    match (previous, expected.cycles) {
+        (Some((expected_before, before)), Some(expected_now)) => {
+            let took = cycles - before;
+            let expected_took = expected_now.wrapping_sub(expected_before);
+            (took != expected_took)
+                .then(|| format!("the last instruction took {} cycles, expected {}", took, expected_took))
+        },
+        _ => None,
+    }
+}
+
+// runs the instruction at the PC, then the interrupt it lets in, so the
+// next line is what the CPU runs next either way
+fn step(nes: &mut Nes) -> Result<(), EmuError> {
+    run_instruction(nes)?;
+    if nes.cpu.bus.irq.is_asserted() && !nes.cpu.status.interrupt {
+        run_instruction(nes)?;
+    }
+    Ok(())
+}
+
+fn run_instruction(nes: &mut Nes) -> Result<(), EmuError> {
+    nes.clock()?;
+    while nes.cpu.cycles > 0 {
+        nes.clock()?;
+    }
+    Ok(())
+}
+
+// the text after ` NAME` in a line, up to the next space
+fn field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let start = line.match_indices(name).find(|&(i, _)| i > 0 && line.as_bytes()[i - 1] == b' ')?.0;
+    let rest = line[start + name.len()..].trim_start();
+    rest.split_whitespace().next()
+}
+
+fn hex(text: &str) -> Option<u8> {
+    match text.len() {
+        2 => u8::from_str_radix(text, 16).ok(),
+        _ => None,
+    }
+}
+
+// `NV-BDIZC` as letters, upper case for a set flag
+fn flags(text: &str) -> Option<u8> {
+    if text.chars().count() != 8 {
+        return None;
+    }
+    Some(text.chars().fold(0, |byte, flag| byte << 1 | flag.is_ascii_uppercase() as u8))
+}
+
+fn letters(status: u8) -> String {
+    "NVUBDIZC"
+        .chars()
+        .enumerate()
+        .map(|(i, flag)| if status & (0x80 >> i) != 0 { flag } else { flag.to_ascii_lowercase() })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nestest_lines() {
+        let line = "C72A  B0 04     BCS $C730                       A:00 X:00 Y:00 P:27 SP:FB PPU:  0, 51 CYC:17";
+        let expected = Expected::parse(line);
+        assert_eq!(expected.pc, Some(0xC72A));
+        assert_eq!((expected.a, expected.x, expected.y), (Some(0), Some(0), Some(0)));
+        assert_eq!((expected.status, expected.stack_pointer), (Some(0x27), Some(0xFB)));
+        assert_eq!(expected.cycles, Some(17));
+    }
+
+    #[test]
+    fn parses_mesen_lines() {
+        let line = "8000   $78     SEI                A:01 X:02 Y:03 S:FD P:nvUbdIzc Cycle:7";
+        let expected = Expected::parse(line);
+        assert_eq!(expected.pc, Some(0x8000));
+        assert_eq!((expected.a, expected.x, expected.y), (Some(1), Some(2), Some(3)));
+        assert_eq!((expected.status, expected.stack_pointer), (Some(0x24), Some(0xFD)));
+        assert_eq!(expected.cycles, Some(7));
+    }
+
+    #[test]
+    fn skips_missing_and_malformed_fields() {
+        let expected = Expected::parse("80G0 A:123 X:4 P:nvub");
+        assert_eq!((expected.pc, expected.a, expected.x, expected.status), (None, None, None, None));
+        assert_eq!((expected.stack_pointer, expected.cycles), (None, None));
+
+        // `SP:` isn't read as a `P:` field
+        assert_eq!(field("C000 SP:FD", "P:"), None);
+    }
+
+    #[test]
+    fn flag_letters_round_trip() {
+        assert_eq!(flags("NVUBDIZC"), Some(0xFF));
+        assert_eq!(flags("nvUbdIzc"), Some(0x24));
+        assert_eq!(flags("nvUbdIz"), None);
+        assert_eq!(letters(0xA5), "NvUbdIzC");
+    }
+}
@@ -0,0 +1,1075 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::cartridge::Cartridge;
+use crate::eeprom::{Eeprom, Model};
+use crate::error::EmuError;
+use crate::state::{BoardReader, BoardWriter};
+
+/// The most bytes any board's [`Mapper::save_state`] writes.
+pub const STATE_SIZE: usize = 16 * 1024;
+
+/// The CPU side of a cartridge board: everything from `$4020` up.
+pub trait Mapper: Send {
+    fn peek(&self, addr: u16) -> u8;
+
+    fn write(&mut self, addr: u16, data: u8);
+
+    /// Like `peek`, for boards whose reads have side effects.
+    fn read(&mut self, addr: u16) -> u8 {
+        self.peek(addr)
+    }
+
+    /// Changes the byte `peek` returns at `addr`, for memory editors: the
+    /// PRG RAM or PRG ROM behind it in the current banks. Never a register
+    /// write; anything else is ignored.
+    fn poke(&mut self, _addr: u16, _data: u8) {}
+
+    /// Whether `addr` is PRG RAM, where a write just stores the byte and
+    /// `poke` can put the old one back; false for registers.
+    fn is_ram(&self, _addr: u16) -> bool {
+        false
+    }
+
+    /// The PRG bank mapped at `addr`, for debuggers; 0 on boards without
+    /// bank switching.
+    fn prg_bank(&self, _addr: u16) -> u16 {
+        0
+    }
+
+    /// Where in PRG ROM the CPU reads `addr` from, for debuggers; `None`
+    /// for RAM, registers and anything else that isn't PRG ROM.
+    fn prg_offset(&self, _addr: u16) -> Option<usize> {
+        None
+    }
+
+    /// Where in CHR ROM the PPU reads pattern table address `addr` from,
+    /// for debuggers; `None` on boards with CHR RAM.
+    fn chr_offset(&self, _addr: u16) -> Option<usize> {
+        None
+    }
+
+    /// Puts the board's registers back as they are at power-on, for a
+    /// power cycle. Battery-backed memory keeps its contents.
+    fn power_on(&mut self) {}
+
+    /// Called once per CPU cycle, for boards with timers.
+    fn clock(&mut self) {}
+
+    /// Whether the board is holding the IRQ line low.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// The cartridge's own DIP switches, on boards that have them. Set
+    /// from [`Nes::dip_switches`](crate::nes::Nes::dip_switches) when the
+    /// cartridge goes in.
+    fn set_dip_switches(&mut self, _dip_switches: u8) {}
+
+    /// The time of day in seconds since the Unix epoch, for boards with a
+    /// real-time clock. Set from the console's
+    /// [`ClockSource`](crate::nes::ClockSource) when the cartridge goes in
+    /// and after every frame.
+    fn set_time(&mut self, _unix_seconds: u64) {}
+
+    /// The battery-backed RAM or EEPROM to keep in a save file; `None`
+    /// when the board forgets everything at power-off.
+    fn save_data(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Restores what [`Mapper::save_data`] returned in an earlier session.
+    /// Extra bytes are ignored and a short save only fills the start.
+    fn load_save_data(&mut self, _data: &[u8]) {}
+
+    /// Writes the registers, counters and RAM that make up the board's
+    /// state into `out`, which holds [`STATE_SIZE`] bytes, and returns how
+    /// many it used. The same board always writes the same number.
+    fn save_state(&self, _out: &mut [u8]) -> usize {
+        0
+    }
+
+    /// Puts the board back as [`Mapper::save_state`] wrote it.
+    fn load_state(&mut self, _data: &[u8]) {}
+}
+
+/// Creates the board a cartridge asks for.
+pub fn create(cartridge: Cartridge) -> Result<Box<dyn Mapper>, EmuError> {
+    match cartridge.mapper {
+        0 => Ok(Box::new(Nrom::new(cartridge))),
+        16 | 157 | 159 => Ok(Box::new(Bandai::new(cartridge))),
+        76 | 88 | 154 | 206 => Ok(Box::new(Namco108::new(cartridge))),
+        105 => Ok(Box::new(Nwc::new(cartridge))),
+        mapper => Err(EmuError::UnsupportedMapper(mapper)),
+    }
+}
+
+/// The usual name of an iNES mapper number, supported or not, for
+/// describing dumps; `None` for the less common ones.
+pub fn name(mapper: u16) -> Option<&'static str> {
+    let name = match mapper {
+        0 => "NROM",
+        1 => "MMC1",
+        2 => "UxROM",
+        3 => "CNROM",
+        4 => "MMC3",
+        5 => "MMC5",
+        7 => "AxROM",
+        9 => "MMC2",
+        10 => "MMC4",
+        11 => "Color Dreams",
+        13 => "CPROM",
+        16 => "Bandai FCG",
+        19 => "Namco 163",
+        21 | 23 | 25 => "VRC4",
+        22 => "VRC2",
+        24 | 26 => "VRC6",
+        34 => "BNROM / NINA-001",
+        66 => "GxROM",
+        69 => "Sunsoft FME-7",
+        71 => "Camerica BF909x",
+        76 => "NAMCOT-3446",
+        85 => "VRC7",
+        88 => "NAMCOT-3443",
+        105 => "NES-EVENT",
+        154 => "NAMCOT-3453",
+        157 => "Bandai Datach",
+        159 => "Bandai LZ93D50 with 24C01",
+        206 => "Namco 108 / DxROM",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Mapper 0: 16KB or 32KB of fixed PRG ROM and optional PRG RAM.
+pub struct Nrom {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    // there's no PPU to read CHR ROM yet, only its size is kept
+    chr_size: usize,
+    battery: bool,
+}
+
+impl Nrom {
+    pub fn new(cartridge: Cartridge) -> Nrom {
+        let mut prg_ram = vec![0; 8 * 1024];
+        if let Some(trainer) = &cartridge.trainer {
+            prg_ram[0x1000..0x1000 + trainer.len()].copy_from_slice(trainer);
+        }
+
+        Nrom {
+            prg_rom: cartridge.prg_rom,
+            prg_ram,
+            chr_size: cartridge.chr_rom.len(),
+            battery: cartridge.battery,
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram[(addr - 0x6000) as usize],
+            // a 16KB image is mirrored into both halves
+            0x8000..=0xFFFF => self.prg_rom[(addr - 0x8000) as usize % self.prg_rom.len()],
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        if let 0x6000..=0x7FFF = addr {
+            self.prg_ram[(addr - 0x6000) as usize] = data;
+        }
+    }
+
+    fn poke(&mut self, addr: u16, data: u8) {
+        match (addr, self.prg_offset(addr)) {
+            (0x6000..=0x7FFF, _) => self.prg_ram[(addr - 0x6000) as usize] = data,
+            (_, Some(offset)) => self.prg_rom[offset] = data,
+            _ => {},
+        }
+    }
+
+    fn is_ram(&self, addr: u16) -> bool {
+        (0x6000..=0x7FFF).contains(&addr)
+    }
+
+    fn prg_offset(&self, addr: u16) -> Option<usize> {
+        (addr >= 0x8000).then(|| (addr - 0x8000) as usize % self.prg_rom.len())
+    }
+
+    fn chr_offset(&self, addr: u16) -> Option<usize> {
+        (self.chr_size > 0).then(|| (addr as usize & 0x1FFF) % self.chr_size)
+    }
+
+    fn save_data(&self) -> Option<&[u8]> {
+        self.battery.then_some(&self.prg_ram[..])
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let len = data.len().min(self.prg_ram.len());
+        self.prg_ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    fn save_state(&self, out: &mut [u8]) -> usize {
+        let mut state = BoardWriter::new(out);
+        state.bytes(&self.prg_ram);
+        state.len()
+    }
+
+    fn load_state(&mut self, data: &[u8]) {
+        BoardReader::new(data).bytes(&mut self.prg_ram);
+    }
+}
+
+const PRG_BANK: usize = 16 * 1024;
+const PRG_BANK_8K: usize = 8 * 1024;
+const CHR_BANK_1K: usize = 1024;
+const NWC_CHIP: usize = 128 * 1024;
+// the timer always runs at least 2^29 cycles, about 5 minutes
+const NWC_TIMER_BASE: u32 = 0x2000_0000;
+
+/// Mapper 105: the Nintendo World Championships 1990 cartridge, an MMC1
+/// with two 128KB PRG chips and a competition timer.
+///
+/// PRG starts locked to the first 32KB until the game toggles the timer's
+/// I bit (bit 4 of the `$A000` register) on and off. After that bit 3
+/// selects the chip: the first in 32KB banks from bits 1-2, or the second
+/// banked like a normal MMC1 through `$E000`. While I is clear the timer
+/// counts CPU cycles and raises an IRQ after `2^29 + dip * 2^25` of them.
+/// Setting I resets and acknowledges it. The low 4 DIP switches pick the
+/// time limit: 0 is about 5:00, and each step adds about 18.7s.
+pub struct Nwc {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    shift: u8,
+    shift_count: u8,
+    control: u8,
+    chr_bank: u8,
+    prg_bank: u8,
+    // PRG stays on the first 32KB until I has gone high then low
+    unlocked: bool,
+    seen_high: bool,
+    counter: u32,
+    irq: bool,
+    dip_switches: u8,
+}
+
+impl Nwc {
+    pub fn new(cartridge: Cartridge) -> Nwc {
+        let mut nwc = Nwc {
+            prg_rom: cartridge.prg_rom,
+            prg_ram: vec![0; 8 * 1024],
+            shift: 0,
+            shift_count: 0,
+            control: 0,
+            chr_bank: 0,
+            prg_bank: 0,
+            unlocked: false,
+            seen_high: false,
+            counter: 0,
+            irq: false,
+            dip_switches: 0,
+        };
+        nwc.power_on();
+        nwc
+    }
+
+    fn timer_held(&self) -> bool {
+        self.chr_bank & 0x10 != 0
+    }
+
+    // the 16KB bank, counted over both chips, mapped at `addr`
+    fn bank(&self, addr: u16) -> usize {
+        let upper = addr >= 0xC000;
+        if !self.unlocked {
+            return upper as usize;
+        }
+
+        let banks = NWC_CHIP / PRG_BANK;
+        if self.chr_bank & 0x08 == 0 {
+            let bank32 = ((self.chr_bank >> 1) & 0x03) as usize;
+            return bank32 * 2 + upper as usize;
+        }
+
+        let bank = (self.prg_bank & 0x07) as usize;
+        let bank = match ((self.control >> 2) & 0x03, upper) {
+            // 32KB mode ignores the low bit
+            (0 | 1, _) => (bank & !1) + upper as usize,
+            (2, false) => 0,
+            (2, true) => bank,
+            (_, false) => bank,
+            (_, true) => banks - 1,
+        };
+        banks + bank
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x8000..=0x9FFF => self.control = value,
+            0xA000..=0xBFFF => {
+                self.chr_bank = value;
+                if self.timer_held() {
+                    self.seen_high = true;
+                    self.counter = 0;
+                    self.irq = false;
+                } else if self.seen_high {
+                    self.unlocked = true;
+                }
+            },
+            // CHR is 8KB of RAM, so the second CHR register does nothing
+            0xC000..=0xDFFF => {},
+            _ => self.prg_bank = value,
+        }
+    }
+}
+
+impl Mapper for Nwc {
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram[(addr - 0x6000) as usize],
+            0x8000..=0xFFFF => {
+                let offset = self.bank(addr) * PRG_BANK + (addr as usize & (PRG_BANK - 1));
+                self.prg_rom[offset % self.prg_rom.len()]
+            },
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram[(addr - 0x6000) as usize] = data,
+            0x8000..=0xFFFF => {
+                // bit 7 resets the serial port and goes back to fixing the last bank
+                if data & 0x80 != 0 {
+                    self.shift = 0;
+                    self.shift_count = 0;
+                    self.control |= 0x0C;
+                    return;
+                }
+
+                self.shift |= (data & 0x01) << self.shift_count;
+                self.shift_count += 1;
+                if self.shift_count == 5 {
+                    let value = self.shift;
+                    self.shift = 0;
+                    self.shift_count = 0;
+                    self.write_register(addr, value);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn poke(&mut self, addr: u16, data: u8) {
+        match (addr, self.prg_offset(addr)) {
+            (0x6000..=0x7FFF, _) => self.prg_ram[(addr - 0x6000) as usize] = data,
+            (_, Some(offset)) => self.prg_rom[offset] = data,
+            _ => {},
+        }
+    }
+
+    fn is_ram(&self, addr: u16) -> bool {
+        (0x6000..=0x7FFF).contains(&addr)
+    }
+
+    fn prg_bank(&self, addr: u16) -> u16 {
+        self.bank(addr) as u16
+    }
+
+    fn prg_offset(&self, addr: u16) -> Option<usize> {
+        let offset = self.bank(addr) * PRG_BANK + (addr as usize & (PRG_BANK - 1));
+        (addr >= 0x8000).then(|| offset % self.prg_rom.len())
+    }
+
+    fn power_on(&mut self) {
+        self.shift = 0;
+        self.shift_count = 0;
+        self.control = 0x0C;
+        // the timer is held in reset at power-on
+        self.chr_bank = 0x10;
+        self.prg_bank = 0;
+        self.unlocked = false;
+        self.seen_high = false;
+        self.counter = 0;
+        self.irq = false;
+    }
+
+    fn clock(&mut self) {
+        if self.timer_held() {
+            return;
+        }
+
+        self.counter = self.counter.wrapping_add(1);
+        let target = NWC_TIMER_BASE | ((self.dip_switches & 0x0F) as u32) << 25;
+        if self.counter == target {
+            self.irq = true;
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq
+    }
+
+    fn set_dip_switches(&mut self, dip_switches: u8) {
+        self.dip_switches = dip_switches;
+    }
+
+    // the DIP switches are the cabinet's setting, not state
+    fn save_state(&self, out: &mut [u8]) -> usize {
+        let mut state = BoardWriter::new(out);
+        state.bytes(&self.prg_ram);
+        state.u8(self.shift);
+        state.u8(self.shift_count);
+        state.u8(self.control);
+        state.u8(self.chr_bank);
+        state.u8(self.prg_bank);
+        state.bool(self.unlocked);
+        state.bool(self.seen_high);
+        state.u32(self.counter);
+        state.bool(self.irq);
+        state.len()
+    }
+
+    fn load_state(&mut self, data: &[u8]) {
+        let mut state = BoardReader::new(data);
+        state.bytes(&mut self.prg_ram);
+        self.shift = state.u8();
+        // kept in range so a corrupt state can't overflow the shift
+        self.shift_count = state.u8() % 5;
+        self.control = state.u8();
+        self.chr_bank = state.u8();
+        self.prg_bank = state.u8();
+        self.unlocked = state.bool();
+        self.seen_high = state.bool();
+        self.counter = state.u32();
+        self.irq = state.bool();
+    }
+}
+
+/// Mappers 16, 157 and 159: Bandai's FCG-1/FCG-2 and LZ93D50 boards.
+///
+/// Register `x` is written at `$6000 + x` on the FCG chips and `$8000 + x`
+/// on the LZ93D50; plain mapper 16 answers at both. Register 8 picks the
+/// 16KB bank at `$8000` with the last one fixed at `$C000`, A enables the
+/// IRQ and B/C set its 16-bit counter, which counts down every CPU cycle
+/// and fires on reaching 0. On the LZ93D50 B/C only load a latch that
+/// writing A copies into the counter.
+///
+/// Register D drives the serial EEPROM's clock (bit 5) and data (bit 6)
+/// pins and reads of `$6000-$7FFF` return its data in bit 4: a 24C02 on
+/// mapper 16 (submapper 4 has none), a 24C01 on mapper 159, and on the
+/// Datach (157) both, with the cartridge's 24C01 clocked from bit 3 of the
+/// CHR registers.
+pub struct Bandai {
+    prg_rom: Vec<u8>,
+    // whether the registers answer at $6000 and $8000
+    fcg: bool,
+    lz93d50: bool,
+    prg_bank: u8,
+    chr_banks: [u8; 8],
+    chr_size: usize,
+    irq_enabled: bool,
+    counter: u16,
+    latch: u16,
+    irq: bool,
+    eeprom: Option<Eeprom>,
+    // the Datach game cartridge's own chip
+    external: Option<Eeprom>,
+    // the board's copy of every chip's contents, refreshed for `save_data`
+    save: Vec<u8>,
+}
+
+impl Bandai {
+    pub fn new(cartridge: Cartridge) -> Bandai {
+        let (fcg, lz93d50) = match (cartridge.mapper, cartridge.submapper) {
+            (16, 4) => (true, false),
+            (16, 5) | (157 | 159, _) => (false, true),
+            _ => (true, true),
+        };
+        let (eeprom, external) = match (cartridge.mapper, cartridge.submapper) {
+            (16, 4) => (None, None),
+            (157, _) => (Some(Model::C24C02), Some(Model::X24C01)),
+            (159, _) => (Some(Model::X24C01), None),
+            _ => (Some(Model::C24C02), None),
+        };
+        let eeprom = eeprom.map(Eeprom::new);
+        let external = external.map(Eeprom::new);
+
+        let mut bandai = Bandai {
+            prg_rom: cartridge.prg_rom,
+            fcg,
+            lz93d50,
+            prg_bank: 0,
+            chr_banks: [0; 8],
+            chr_size: cartridge.chr_rom.len(),
+            irq_enabled: false,
+            counter: 0,
+            latch: 0,
+            irq: false,
+            eeprom,
+            external,
+            save: Vec::new(),
+        };
+        bandai.sync_save();
+        bandai
+    }
+
+    // the bank fixed at $C000; NES 2.0 allows PRG ROM under 16KB, which
+    // is mirrored as one bank
+    fn last_bank(&self) -> usize {
+        (self.prg_rom.len() / PRG_BANK).max(1) - 1
+    }
+
+    fn chips(&mut self) -> impl Iterator<Item = &mut Eeprom> {
+        self.eeprom.iter_mut().chain(self.external.iter_mut())
+    }
+
+    fn sync_save(&mut self) {
+        self.save.clear();
+        for chip in self.eeprom.iter().chain(self.external.iter()) {
+            self.save.extend_from_slice(chip.data());
+        }
+    }
+
+    fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0x00..=0x07 => {
+                // CHR has nowhere to go without a PPU, so the banks are only kept
+                self.chr_banks[register as usize] = value;
+                if let Some(external) = self.external.as_mut() {
+                    external.set_scl(value & 0x08 != 0);
+                    self.sync_save();
+                }
+            },
+            0x08 => self.prg_bank = value & 0x0F,
+            // mirroring; nothing to mirror without a PPU
+            0x09 => {},
+            0x0A => {
+                self.irq_enabled = value & 0x01 != 0;
+                self.irq = false;
+                if self.lz93d50 {
+                    self.counter = self.latch;
+                }
+            },
+            0x0B | 0x0C => {
+                let shift = (register - 0x0B) * 8;
+                self.latch = self.latch & !(0xFF << shift) | (value as u16) << shift;
+                if self.fcg {
+                    self.counter = self.latch;
+                }
+            },
+            0x0D => {
+                // bit 7 turns the board's side of SDA around for reads,
+                // which the wired-AND of the two outputs covers
+                let (scl, sda) = (value & 0x20 != 0, value & 0x40 != 0);
+                if let Some(eeprom) = self.eeprom.as_mut() {
+                    eeprom.set_lines(scl, sda);
+                }
+                // the Datach cartridge's chip shares SDA but is clocked
+                // from the CHR registers
+                if let Some(external) = self.external.as_mut() {
+                    external.set_sda(sda);
+                }
+                self.sync_save();
+            },
+            // E and F only exist on the Datach for its barcode reader
+            _ => {},
+        }
+    }
+}
+
+impl Mapper for Bandai {
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            0x6000..=0x7FFF => {
+                let output = self.eeprom.iter().chain(self.external.iter()).all(Eeprom::output);
+                (self.eeprom.is_some() && output) as u8 * 0x10
+            },
+            0x8000..=0xFFFF => {
+                let bank = match addr {
+                    0x8000..=0xBFFF => self.prg_bank as usize,
+                    _ => self.last_bank(),
+                };
+                let offset = bank * PRG_BANK + (addr as usize & (PRG_BANK - 1));
+                self.prg_rom[offset % self.prg_rom.len()]
+            },
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if self.fcg => self.write_register(addr & 0x0F, data),
+            0x8000..=0xFFFF if self.lz93d50 => self.write_register(addr & 0x0F, data),
+            _ => {},
+        }
+    }
+
+    // $6000-$7FFF is the EEPROM's data line, not memory
+    fn poke(&mut self, addr: u16, data: u8) {
+        if let Some(offset) = self.prg_offset(addr) {
+            self.prg_rom[offset] = data;
+        }
+    }
+
+    fn prg_bank(&self, addr: u16) -> u16 {
+        match addr {
+            0x8000..=0xBFFF => self.prg_bank as u16,
+            _ => self.last_bank() as u16,
+        }
+    }
+
+    fn prg_offset(&self, addr: u16) -> Option<usize> {
+        let offset = self.prg_bank(addr) as usize * PRG_BANK + (addr as usize & (PRG_BANK - 1));
+        (addr >= 0x8000).then(|| offset % self.prg_rom.len())
+    }
+
+    // one 1KB bank per register; the Datach has CHR RAM instead
+    fn chr_offset(&self, addr: u16) -> Option<usize> {
+        let bank = self.chr_banks[(addr as usize >> 10) & 0x07] as usize;
+        let offset = bank * CHR_BANK_1K + (addr as usize & (CHR_BANK_1K - 1));
+        (self.chr_size > 0).then(|| offset % self.chr_size)
+    }
+
+    fn power_on(&mut self) {
+        self.prg_bank = 0;
+        self.chr_banks = [0; 8];
+        self.irq_enabled = false;
+        self.counter = 0;
+        self.latch = 0;
+        self.irq = false;
+        for chip in self.chips() {
+            chip.power_on();
+        }
+    }
+
+    fn clock(&mut self) {
+        if !self.irq_enabled {
+            return;
+        }
+
+        if self.counter == 0 {
+            self.irq = true;
+        }
+        self.counter = self.counter.wrapping_sub(1);
+    }
+
+    fn irq(&self) -> bool {
+        self.irq
+    }
+
+    fn save_data(&self) -> Option<&[u8]> {
+        (!self.save.is_empty()).then_some(&self.save[..])
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let mut rest = data;
+        for chip in self.chips() {
+            let len = chip.data().len().min(rest.len());
+            chip.load(&rest[..len]);
+            rest = &rest[len..];
+        }
+        self.sync_save();
+    }
+
+    fn save_state(&self, out: &mut [u8]) -> usize {
+        let mut state = BoardWriter::new(out);
+        state.u8(self.prg_bank);
+        state.bytes(&self.chr_banks);
+        state.bool(self.irq_enabled);
+        state.u16(self.counter);
+        state.u16(self.latch);
+        state.bool(self.irq);
+        for chip in self.eeprom.iter().chain(self.external.iter()) {
+            chip.save_state(&mut state);
+        }
+        state.len()
+    }
+
+    fn load_state(&mut self, data: &[u8]) {
+        let mut state = BoardReader::new(data);
+        self.prg_bank = state.u8();
+        state.bytes(&mut self.chr_banks);
+        self.irq_enabled = state.bool();
+        self.counter = state.u16();
+        self.latch = state.u16();
+        self.irq = state.bool();
+        for chip in self.chips() {
+            chip.load_state(&mut state);
+        }
+        self.sync_save();
+    }
+}
+
+/// Mappers 206, 76, 88 and 154: the Namco 108 and the NAMCOT boards built
+/// around it, an MMC3 without the IRQ counter, PRG mode or PRG RAM.
+///
+/// An even address in `$8000-$9FFF` selects register 0-7 and an odd one
+/// writes it. Registers 6 and 7 pick the 8KB banks at `$8000` and `$A000`,
+/// with the last two fixed at `$C000`. Registers 0-5 bank CHR: 0 and 1 in
+/// 2KB at `$0000`, 2-5 in 1KB at `$1000`. Mapper 76 instead uses 2-5 for
+/// four 2KB banks, 88 and 154 take the upper 64KB of CHR for `$1000`, and
+/// 154 also switches one-screen mirroring, which doesn't matter without a
+/// PPU.
+pub struct Namco108 {
+    prg_rom: Vec<u8>,
+    chr_size: usize,
+    // which NAMCOT board, by mapper number
+    board: u16,
+    select: u8,
+    registers: [u8; 8],
+}
+
+impl Namco108 {
+    pub fn new(cartridge: Cartridge) -> Namco108 {
+        let mut namco = Namco108 {
+            prg_rom: cartridge.prg_rom,
+            chr_size: cartridge.chr_rom.len(),
+            board: cartridge.mapper,
+            select: 0,
+            registers: [0; 8],
+        };
+        namco.power_on();
+        namco
+    }
+
+    // the 8KB bank mapped at `addr`
+    fn bank(&self, addr: u16) -> usize {
+        let banks = (self.prg_rom.len() / PRG_BANK_8K).max(1);
+        match addr {
+            0x8000..=0x9FFF => (self.registers[6] & 0x0F) as usize,
+            0xA000..=0xBFFF => (self.registers[7] & 0x0F) as usize,
+            0xC000..=0xDFFF => banks.saturating_sub(2),
+            _ => banks - 1,
+        }
+    }
+
+    // the 1KB CHR bank mapped at `addr`
+    fn chr_bank(&self, addr: u16) -> usize {
+        let slot = (addr as usize >> 10) & 0x07;
+        if self.board == 76 {
+            return (self.registers[2 + slot / 2] & 0x3F) as usize * 2 + slot % 2;
+        }
+
+        let bank = match slot {
+            0..=3 => (self.registers[slot / 2] & 0x3E) as usize + slot % 2,
+            _ => (self.registers[slot - 2] & 0x3F) as usize,
+        };
+        match self.board {
+            88 | 154 if slot >= 4 => bank | 0x40,
+            _ => bank,
+        }
+    }
+}
+
+impl Mapper for Namco108 {
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0xFFFF => {
+                let offset = self.bank(addr) * PRG_BANK_8K + (addr as usize & (PRG_BANK_8K - 1));
+                self.prg_rom[offset % self.prg_rom.len()]
+            },
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x8000..=0x9FFF if addr & 0x01 == 0 => self.select = data & 0x07,
+            0x8000..=0x9FFF => self.registers[self.select as usize] = data,
+            _ => {},
+        }
+    }
+
+    fn poke(&mut self, addr: u16, data: u8) {
+        if let Some(offset) = self.prg_offset(addr) {
+            self.prg_rom[offset] = data;
+        }
+    }
+
+    fn prg_bank(&self, addr: u16) -> u16 {
+        self.bank(addr) as u16
+    }
+
+    fn prg_offset(&self, addr: u16) -> Option<usize> {
+        let offset = self.bank(addr) * PRG_BANK_8K + (addr as usize & (PRG_BANK_8K - 1));
+        (addr >= 0x8000).then(|| offset % self.prg_rom.len())
+    }
+
+    fn chr_offset(&self, addr: u16) -> Option<usize> {
+        let offset = self.chr_bank(addr) * CHR_BANK_1K + (addr as usize & (CHR_BANK_1K - 1));
+        (self.chr_size > 0).then(|| offset % self.chr_size)
+    }
+
+    fn power_on(&mut self) {
+        self.select = 0;
+        self.registers = [0, 0, 0, 0, 0, 0, 0, 1];
+    }
+
+    fn save_state(&self, out: &mut [u8]) -> usize {
+        let mut state = BoardWriter::new(out);
+        state.u8(self.select);
+        state.bytes(&self.registers);
+        state.len()
+    }
+
+    fn load_state(&mut self, data: &[u8]) {
+        let mut state = BoardReader::new(data);
+        self.select = state.u8() & 0x07;
+        state.bytes(&mut self.registers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::{Console, Mirroring};
+    use crate::constants::Region;
+
+    // a cartridge for `mapper` whose 8KB PRG banks are filled with their
+    // own numbers
+    fn cartridge(mapper: u16, prg_banks: u8) -> Cartridge {
+        Cartridge {
+            mapper,
+            submapper: 0,
+            nes2: false,
+            region: Region::Ntsc,
+            console: Console::Nes,
+            mirroring: Mirroring::Horizontal,
+            battery: false,
+            prg_ram_size: 8 * 1024,
+            trainer: None,
+            prg_rom: (0..prg_banks).flat_map(|bank| vec![bank; PRG_BANK_8K]).collect(),
+            chr_rom: Vec::new(),
+            playchoice: None,
+        }
+    }
+
+    // an MMC1 register write: five writes of one bit each, low bit first
+    fn serial(nwc: &mut Nwc, addr: u16, value: u8) {
+        for bit in 0..5 {
+            nwc.write(addr, value >> bit & 1);
+        }
+    }
+
+    #[test]
+    fn nwc_takes_five_serial_writes_per_register() {
+        let mut nwc = Nwc::new(cartridge(105, 32));
+        for bit in [1, 0, 1, 1] {
+            nwc.write(0x8000, bit);
+        }
+        assert_eq!(nwc.control, 0x0C);
+        nwc.write(0x8000, 0);
+        assert_eq!(nwc.control, 0x0D);
+
+        // bit 7 drops the bits so far and fixes the last bank again
+        serial(&mut nwc, 0x8000, 0x00);
+        nwc.write(0x8000, 1);
+        nwc.write(0x8000, 0x80);
+        assert_eq!((nwc.shift, nwc.shift_count, nwc.control), (0, 0, 0x0C));
+        serial(&mut nwc, 0xE000, 0x03);
+        assert_eq!(nwc.prg_bank, 0x03);
+    }
+
+    #[test]
+    fn nwc_unlocks_prg_once_the_timer_bit_toggles() {
+        let mut nwc = Nwc::new(cartridge(105, 32));
+        assert_eq!((nwc.peek(0x8000), nwc.peek(0xC000)), (0, 2));
+
+        // clearing I without having set it leaves the first 32KB in
+        serial(&mut nwc, 0xA000, 0x02);
+        assert_eq!(nwc.peek(0x8000), 0);
+
+        // first chip in 32KB banks from bits 1-2
+        serial(&mut nwc, 0xA000, 0x10);
+        serial(&mut nwc, 0xA000, 0x02);
+        assert_eq!((nwc.peek(0x8000), nwc.peek(0xC000)), (4, 6));
+
+        // second chip, banked like an MMC1 after the first chip's 8 banks
+        serial(&mut nwc, 0xA000, 0x08);
+        serial(&mut nwc, 0xE000, 0x03);
+        assert_eq!((nwc.prg_bank(0x8000), nwc.prg_bank(0xC000)), (8 + 3, 8 + 7));
+        serial(&mut nwc, 0x8000, 0x08);
+        assert_eq!((nwc.prg_bank(0x8000), nwc.prg_bank(0xC000)), (8, 8 + 3));
+        serial(&mut nwc, 0x8000, 0x00);
+        assert_eq!((nwc.prg_bank(0x8000), nwc.prg_bank(0xC000)), (8 + 2, 8 + 3));
+        assert_eq!(nwc.peek(0xC000), 2 * (8 + 3));
+    }
+
+    #[test]
+    fn nwc_timer_fires_after_the_dip_switch_time() {
+        let mut nwc = Nwc::new(cartridge(105, 32));
+        nwc.set_dip_switches(0x01);
+        nwc.clock();
+        assert_eq!(nwc.counter, 0);
+
+        serial(&mut nwc, 0xA000, 0x00);
+        nwc.counter = NWC_TIMER_BASE + (1 << 25) - 2;
+        nwc.clock();
+        assert!(!nwc.irq());
+        nwc.clock();
+        assert!(nwc.irq());
+
+        // setting I acknowledges and resets it
+        serial(&mut nwc, 0xA000, 0x10);
+        assert!(!nwc.irq());
+        assert_eq!(nwc.counter, 0);
+    }
+
+    #[test]
+    fn bandai_banks_16kb_with_the_last_fixed() {
+        let mut bandai = Bandai::new(cartridge(16, 16));
+        bandai.write(0x8008, 3);
+        assert_eq!((bandai.peek(0x8000), bandai.peek(0xA000)), (6, 7));
+        assert_eq!((bandai.peek(0xC000), bandai.peek(0xE000)), (14, 15));
+        bandai.write(0x6008, 5);
+        assert_eq!(bandai.peek(0x8000), 10);
+
+        // the LZ93D50 only answers at $8000
+        let mut lz93d50 = Bandai::new(cartridge(159, 16));
+        lz93d50.write(0x6008, 5);
+        assert_eq!(lz93d50.peek(0x8000), 0);
+    }
+
+    #[test]
+    fn bandai_irq_counts_down_to_zero() {
+        let mut fcg = cartridge(16, 16);
+        fcg.submapper = 4;
+        let mut fcg = Bandai::new(fcg);
+        fcg.write(0x600B, 2);
+        fcg.write(0x600C, 0);
+        assert_eq!(fcg.counter, 2);
+        fcg.write(0x600A, 1);
+        for _ in 0..2 {
+            fcg.clock();
+            assert!(!fcg.irq());
+        }
+        fcg.clock();
+        assert!(fcg.irq());
+        fcg.write(0x600A, 0);
+        assert!(!fcg.irq());
+
+        // on the LZ93D50, B and C only load the latch
+        let mut lz93d50 = Bandai::new(cartridge(159, 16));
+        lz93d50.write(0x800B, 2);
+        lz93d50.write(0x800C, 0);
+        assert_eq!((lz93d50.latch, lz93d50.counter), (2, 0));
+        lz93d50.write(0x800A, 1);
+        assert_eq!(lz93d50.counter, 2);
+        for _ in 0..3 {
+            lz93d50.clock();
+        }
+        assert!(lz93d50.irq());
+    }
+
+    #[test]
+    fn namco108_banks_prg_and_chr() {
+        let mut namco = Namco108::new(Cartridge { chr_rom: vec![0; 128 * 1024], ..cartridge(206, 8) });
+        for (register, value) in [(6, 3), (7, 5), (0, 0x05), (2, 9)] {
+            namco.write(0x8000, register);
+            namco.write(0x8001, value);
+        }
+        assert_eq!([0x8000, 0xA000, 0xC000, 0xE000].map(|addr| namco.peek(addr)), [3, 5, 6, 7]);
+        // R0 ignores its low bit, banking 2KB
+        assert_eq!(namco.chr_offset(0x0000), Some(4 * CHR_BANK_1K));
+        assert_eq!(namco.chr_offset(0x0401), Some(5 * CHR_BANK_1K + 1));
+        assert_eq!(namco.chr_offset(0x1000), Some(9 * CHR_BANK_1K));
+
+        let mut namcot3446 = Namco108::new(Cartridge { chr_rom: vec![0; 128 * 1024], ..cartridge(76, 8) });
+        namcot3446.write(0x8000, 2);
+        namcot3446.write(0x8001, 3);
+        assert_eq!(namcot3446.chr_offset(0x0000), Some(6 * CHR_BANK_1K));
+        assert_eq!(namcot3446.chr_offset(0x0400), Some(7 * CHR_BANK_1K));
+
+        let mut namcot3443 = Namco108::new(Cartridge { chr_rom: vec![0; 128 * 1024], ..cartridge(88, 8) });
+        for (register, value) in [(0, 0x05), (2, 9)] {
+            namcot3443.write(0x8000, register);
+            namcot3443.write(0x8001, value);
+        }
+        assert_eq!(namcot3443.chr_offset(0x0000), Some(4 * CHR_BANK_1K));
+        assert_eq!(namcot3443.chr_offset(0x1000), Some((0x40 + 9) * CHR_BANK_1K));
+    }
+
+    #[test]
+    fn save_state_round_trips_the_board() {
+        let mut nwc = Nwc::new(cartridge(105, 32));
+        serial(&mut nwc, 0xA000, 0x10);
+        serial(&mut nwc, 0xA000, 0x0A);
+        serial(&mut nwc, 0xE000, 0x03);
+        nwc.write(0x6000, 0x42);
+        nwc.write(0x8000, 1);
+        let mut state = vec![0; STATE_SIZE];
+        let len = nwc.save_state(&mut state);
+
+        let mut loaded = Nwc::new(cartridge(105, 32));
+        loaded.load_state(&state[..len]);
+        assert_eq!(loaded.peek(0x6000), 0x42);
+        assert_eq!((loaded.prg_bank(0x8000), loaded.prg_bank(0xC000)), (8 + 3, 8 + 7));
+        assert_eq!((loaded.shift, loaded.shift_count), (1, 1));
+
+        let mut namco = Namco108::new(cartridge(206, 8));
+        namco.write(0x8000, 7);
+        namco.write(0x8001, 4);
+        let len = namco.save_state(&mut state);
+        let mut loaded = Namco108::new(cartridge(206, 8));
+        loaded.load_state(&state[..len]);
+        assert_eq!((loaded.select, loaded.peek(0xA000)), (7, 4));
+    }
+
+    #[test]
+    fn poke_edits_rom_without_writing_registers() {
+        let mut namco = Namco108::new(cartridge(206, 8));
+        namco.poke(0x8000, 0x07);
+        namco.poke(0x8001, 0x03);
+        assert_eq!(namco.peek(0x8000), 0x07);
+        assert_eq!(namco.peek(0x8001), 0x03);
+        assert_eq!(namco.registers, [0, 0, 0, 0, 0, 0, 0, 1]);
+
+        let mut nwc = Nwc::new(cartridge(105, 32));
+        nwc.poke(0x8000, 0x01);
+        nwc.poke(0x6000, 0x42);
+        assert_eq!(nwc.shift_count, 0);
+        assert_eq!(nwc.peek(0x8000), 0x01);
+        assert_eq!(nwc.peek(0x6000), 0x42);
+    }
+
+    #[test]
+    fn bandai_mirrors_prg_under_16kb() {
+        let mut small = cartridge(16, 1);
+        small.prg_rom[0x1FFF] = 0x60;
+        let bandai = Bandai::new(small);
+        assert_eq!(bandai.prg_bank(0xC000), 0);
+        assert_eq!(bandai.peek(0xFFFF), 0x60);
+        assert_eq!(bandai.peek(0xDFFF), 0x60);
+    }
+
+    #[test]
+    fn datach_clocks_its_cartridge_eeprom_from_the_chr_registers() {
+        fn state(chip: &Eeprom) -> Vec<u8> {
+            let mut out = vec![0; STATE_SIZE];
+            let mut state = BoardWriter::new(&mut out);
+            chip.save_state(&mut state);
+            let len = state.len();
+            out.truncate(len);
+            out
+        }
+
+        // a start condition and a byte of ones on register D's clock
+        let mut datach = Bandai::new(cartridge(157, 16));
+        for value in [0x60, 0x20, 0x00] {
+            datach.write(0x800D, value);
+        }
+        for _ in 0..8 {
+            datach.write(0x800D, 0x60);
+            datach.write(0x800D, 0x40);
+        }
+
+        // the board's own chip saw all of it, the cartridge's only SDA
+        let mut external = Eeprom::new(Model::X24C01);
+        external.set_sda(true);
+        assert_eq!(state(datach.external.as_ref().unwrap()), state(&external));
+        assert_ne!(state(datach.eeprom.as_ref().unwrap()), state(&Eeprom::new(Model::C24C02)));
+    }
+}
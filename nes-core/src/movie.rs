@@ -0,0 +1,184 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::controller::Button;
+use crate::error::EmuError;
+
+// FM2 lists the buttons of each port in this order
+const FM2_BUTTONS: [Button; 8] = [
+    Button::Right,
+    Button::Left,
+    Button::Down,
+    Button::Up,
+    Button::Start,
+    Button::Select,
+    Button::B,
+    Button::A,
+];
+
+// what FM2 marks each of those buttons with when held
+const FM2_SYMBOLS: [char; 8] = ['R', 'L', 'D', 'U', 'T', 'S', 'B', 'A'];
+
+/// [`MovieFrame::commands`] bit for the console's reset button.
+pub const SOFT_RESET: u8 = 0x01;
+/// [`MovieFrame::commands`] bit for switching the console off and on.
+pub const POWER_CYCLE: u8 = 0x02;
+
+/// Input for one frame of a movie.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct MovieFrame {
+    /// FM2 command bits: 1 is a soft reset, 2 a power cycle.
+    pub commands: u8,
+    /// Button masks for both controller ports, `A` in bit 0.
+    pub ports: [u8; 2],
+}
+
+impl MovieFrame {
+    pub fn soft_reset(&self) -> bool {
+        (self.commands & SOFT_RESET) != 0
+    }
+
+    pub fn power_cycle(&self) -> bool {
+        (self.commands & POWER_CYCLE) != 0
+    }
+}
+
+/// An input recording in FCEUX's text `.fm2` format.
+#[derive(Clone, Debug, Default)]
+pub struct Movie {
+    /// `key value` header lines in file order.
+    pub header: Vec<(String, String)>,
+    pub frames: Vec<MovieFrame>,
+}
+
+impl Movie {
+    /// An empty movie with the header FCEUX needs to play it back: two
+    /// standard controllers, from power on.
+    pub fn new(pal: bool) -> Movie {
+        let header = [
+            ("version", "3"),
+            ("emuVersion", "0"),
+            ("palFlag", if pal { "1" } else { "0" }),
+            ("fourscore", "0"),
+            ("port0", "1"),
+            ("port1", "1"),
+            ("port2", "0"),
+        ];
+
+        Movie {
+            header: header.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn from_fm2(text: &str) -> Result<Movie, EmuError> {
+        let mut movie = Movie::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+
+            if let Some(record) = line.strip_prefix('|') {
+                movie.frames.push(parse_frame(record).ok_or_else(|| {
+                    EmuError::InvalidMovie(format!("bad input on line {}", number + 1))
+                })?);
+            } else if let Some((key, value)) = line.split_once(' ') {
+                movie.header.push((key.to_string(), value.to_string()));
+            } else if !line.is_empty() {
+                movie.header.push((line.to_string(), String::new()));
+            }
+        }
+
+        Ok(movie)
+    }
+
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.header
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The movie as `.fm2` text, header first.
+    pub fn to_fm2(&self) -> String {
+        let mut text = String::new();
+        for (key, value) in &self.header {
+            let _ = writeln!(text, "{} {}", key, value);
+        }
+
+        for frame in &self.frames {
+            let _ = write!(text, "|{}|", frame.commands);
+            for port in frame.ports {
+                for (button, symbol) in FM2_BUTTONS.iter().zip(FM2_SYMBOLS) {
+                    text.push(if port & button.mask() != 0 { symbol } else { '.' });
+                }
+                text.push('|');
+            }
+            text.push_str("|\n");
+        }
+
+        text
+    }
+}
+
+// `commands|port0|port1|port2|`, with either port possibly empty
+fn parse_frame(record: &str) -> Option<MovieFrame> {
+    let mut fields = record.split('|');
+    let commands = fields.next()?.trim();
+
+    let mut frame = MovieFrame {
+        commands: if commands.is_empty() { 0 } else { commands.parse().ok()? },
+        ports: [0; 2],
+    };
+
+    for port in frame.ports.iter_mut() {
+        let field = fields.next().unwrap_or("");
+        for (button, symbol) in FM2_BUTTONS.iter().zip(field.chars()) {
+            if symbol != '.' && symbol != ' ' {
+                *port |= button.mask();
+            }
+        }
+    }
+
+    Some(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fm2_round_trips() {
+        let mut movie = Movie::new(true);
+        movie.frames.push(MovieFrame { commands: POWER_CYCLE, ports: [0, 0] });
+        movie.frames.push(MovieFrame { commands: 0, ports: [Button::A.mask() | Button::Right.mask(), 0xFF] });
+        movie.frames.push(MovieFrame { commands: SOFT_RESET, ports: [Button::Start.mask(), 0] });
+
+        let text = movie.to_fm2();
+        assert!(text.contains("|0|R......A|RLDUTSBA||\n"));
+
+        let parsed = Movie::from_fm2(&text).unwrap();
+        assert_eq!(parsed.header, movie.header);
+        assert_eq!(parsed.frames, movie.frames);
+        assert_eq!(parsed.header("palFlag"), Some("1"));
+        assert!(parsed.frames[0].power_cycle());
+        assert!(parsed.frames[2].soft_reset());
+    }
+
+    #[test]
+    fn fm2_reads_crlf_and_empty_ports() {
+        let movie = Movie::from_fm2("version 3\r\ncomment\r\n|0|....T...|\r\n||\r\n").unwrap();
+        assert_eq!(movie.header("comment"), Some(""));
+        assert_eq!(movie.frames, [
+            MovieFrame { commands: 0, ports: [Button::Start.mask(), 0] },
+            MovieFrame::default(),
+        ]);
+    }
+
+    #[test]
+    fn fm2_rejects_bad_commands() {
+        let error = Movie::from_fm2("version 3\n|x|........|........||\n").unwrap_err();
+        assert_eq!(error, EmuError::InvalidMovie("bad input on line 2".to_string()));
+    }
+}
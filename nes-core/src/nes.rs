@@ -0,0 +1,1018 @@
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+use crate::bus::Bus;
+use crate::cartridge::{Cartridge, Console, RomLayout};
+use crate::constants::{Region, Status};
+use crate::controller::Joypad;
+use crate::cpu::CPU;
+use crate::debugger::{BreakReason, Debugger, RunTarget, WatchHit};
+use crate::error::EmuError;
+use crate::events::Timestamp;
+use crate::history::History;
+use crate::irq::{IrqLine, IrqSource};
+use crate::mapper::{self, STATE_SIZE};
+use crate::movie::{self, Movie, MovieFrame};
+use crate::palette::Palette;
+use crate::profiler::{OpcodeStats, Profiler};
+use crate::state::{FastState, StateReader, StateWriter};
+use crate::vs::VsSystem;
+
+/// Trade-off between speed and hardware fidelity for the optional quirks.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum Accuracy {
+    Fast,
+    #[default]
+    Accurate,
+}
+
+/// Contents of internal RAM at power-on. Real consoles come up with
+/// whatever the chips settle to, and a few games depend on it.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum RamPattern {
+    /// Every byte the same, usually `$00` or `$FF`.
+    Fill(u8),
+    /// Runs of four `$00` bytes and four `$FF` bytes, as many consoles
+    /// power up.
+    Striped,
+    /// Noise from a seeded generator, so a run can be repeated.
+    Random(u64),
+}
+
+impl Default for RamPattern {
+    fn default() -> Self {
+        RamPattern::Fill(0x00)
+    }
+}
+
+impl RamPattern {
+    pub fn fill(&self, ram: &mut [u8]) {
+        match *self {
+            RamPattern::Fill(value) => ram.fill(value),
+            RamPattern::Striped => {
+                for (i, byte) in ram.iter_mut().enumerate() {
+                    *byte = if i & 0x04 == 0 { 0x00 } else { 0xFF };
+                }
+            },
+            RamPattern::Random(seed) => {
+                // splitmix64, eight bytes per step
+                let mut state = seed;
+                for chunk in ram.chunks_mut(8) {
+                    state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                    let mut z = state;
+                    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                    z ^= z >> 31;
+                    chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+                }
+            },
+        }
+    }
+}
+
+/// Where boards with a real-time clock get the time of day from.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ClockSource {
+    /// The host's wall clock.
+    #[cfg(feature = "std")]
+    Host,
+    /// `start` (seconds since the Unix epoch) at power-on, then advancing
+    /// with the frames emulated, so replays see the same time on every run.
+    Emulated { start: u64 },
+}
+
+impl Default for ClockSource {
+    fn default() -> Self {
+        #[cfg(feature = "std")]
+        return ClockSource::Host;
+        #[cfg(not(feature = "std"))]
+        return ClockSource::Emulated { start: 0 };
+    }
+}
+
+impl ClockSource {
+    /// Seconds since the Unix epoch, `frame` frames after power-on.
+    pub fn now(&self, region: Region, frame: u64) -> u64 {
+        match *self {
+            #[cfg(feature = "std")]
+            ClockSource::Host => std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            ClockSource::Emulated { start } => start + (frame as f64 / region.frame_rate()) as u64,
+        }
+    }
+}
+
+/// A configured console: the CPU plus the settings it was built with.
+///
+/// `Nes` is `Send`, so it can be moved to a worker thread; see
+/// `examples/threaded.rs`.
+pub struct Nes {
+    pub cpu: CPU,
+    pub debugger: Debugger,
+    pub profiler: Profiler,
+    pub opcode_stats: OpcodeStats,
+    history: History,
+    // the builder's region, which raw programs always run at
+    region: Region,
+    // whether cartridges run at the builder's region instead of their own
+    region_pinned: bool,
+    palette: Palette,
+    accuracy: Accuracy,
+    sample_rate: u32,
+    ram_pattern: RamPattern,
+    dip_switches: u8,
+    clock_source: ClockSource,
+    overclock_scanlines: u16,
+    deterministic: bool,
+    frame: u64,
+    // fractional CPU cycles carried over between frames
+    cycle_debt: f64,
+    // where a frame interrupted by the debugger picks up again
+    scanline: u16,
+    scanline_started: bool,
+    speed: f64,
+    // the movie being recorded, and the commands for its next frame
+    movie: Option<Movie>,
+    movie_commands: u8,
+    // where the inserted cartridge's ROMs start in its file
+    layout: Option<RomLayout>,
+    inputs_polled: bool,
+}
+
+impl Nes {
+    pub fn builder() -> NesBuilder {
+        NesBuilder::new()
+    }
+
+    pub fn region(&self) -> Region {
+        self.cpu.region
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn accuracy(&self) -> Accuracy {
+        self.accuracy
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn ram_pattern(&self) -> RamPattern {
+        self.ram_pattern
+    }
+
+    /// The DIP switches VS System games and boards that have their own
+    /// start with.
+    pub fn dip_switches(&self) -> u8 {
+        self.dip_switches
+    }
+
+    pub fn clock_source(&self) -> ClockSource {
+        self.clock_source
+    }
+
+    /// Whether the console was built [deterministic](NesBuilder::deterministic).
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Extra scanlines of CPU time run each frame once vblank starts.
+    pub fn overclock_scanlines(&self) -> u16 {
+        self.overclock_scanlines
+    }
+
+    /// The time of day cartridge clocks see, in seconds since the Unix
+    /// epoch.
+    pub fn time(&self) -> u64 {
+        self.clock_source.now(self.region(), self.frame)
+    }
+
+    /// Emulation speed relative to the console: 2.0 is double speed, 0.5
+    /// slow motion and `f64::INFINITY` uncapped. The core only records it;
+    /// frontends pace [`Nes::run_frame`] with [`Nes::frame_duration`] and
+    /// should mute or resample audio while it isn't 1.0.
+    pub fn set_speed(&mut self, speed: f64) {
+        if speed > 0.0 {
+            self.speed = speed;
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Wall-clock seconds one frame should take at the current speed; zero
+    /// when uncapped.
+    pub fn frame_duration(&self) -> f64 {
+        1.0 / (self.region().frame_rate() * self.speed)
+    }
+
+    /// Number of frames completed by [`Nes::run_frame`].
+    pub fn frame_count(&self) -> u64 {
+        self.frame
+    }
+
+    /// Whether the program read the controllers during the last frame. A
+    /// frame it didn't is a lag frame: input given for it is never seen.
+    pub fn inputs_polled(&self) -> bool {
+        self.inputs_polled
+    }
+
+    /// Runs a frame each time the iterator is advanced, for consumers that
+    /// just want frame after frame. It ends once the program finishes or
+    /// the debugger stops emulation, or after yielding an error.
+    ///
+    /// ```
+    /// use nes_core::nes::Nes;
+    ///
+    /// let mut nes = Nes::builder().build();
+    /// nes.load(&[0xE8, 0x4C, 0x00, 0x06]).unwrap(); // INX; JMP $0600
+    ///
+    /// let lag_frames = nes.frames().take(60).filter(|frame| !frame.as_ref().unwrap().inputs_polled).count();
+    /// assert_eq!(lag_frames, 60);
+    /// ```
+    pub fn frames(&mut self) -> Frames<'_> {
+        Frames { nes: self, failed: false }
+    }
+
+    /// Loads a program at `$0600` into a freshly initialized console, in
+    /// the builder's region, and resets the CPU into it.
+    pub fn load(&mut self, program: &[u8]) -> Result<(), EmuError> {
+        self.reinitialize();
+        self.cpu.region = self.region;
+        self.layout = None;
+        self.cpu.load(program)?;
+        self.cpu.power_on();
+        Ok(())
+    }
+
+    /// Swaps in a cartridge, returning the console to its power-on state
+    /// first, switches to the cartridge's region unless the builder
+    /// [pinned](NesBuilder::pin_region) one, and resets the CPU through the
+    /// reset vector. Nothing changes if the cartridge's mapper is
+    /// unsupported.
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) -> Result<(), EmuError> {
+        let vs = (cartridge.console == Console::VsSystem).then(|| VsSystem::new(self.dip_switches));
+        let layout = cartridge.layout();
+        let region = cartridge.region;
+        let mut mapper = mapper::create(cartridge)?;
+        mapper.set_dip_switches(self.dip_switches);
+
+        self.reinitialize();
+        self.cpu.region = if self.region_pinned { self.region } else { region };
+        self.cpu.bus.cartridge = Some(mapper);
+        self.cpu.bus.vs = vs;
+        self.layout = Some(layout);
+        self.sync_time();
+        self.cpu.power_on();
+        Ok(())
+    }
+
+    /// Parses an iNES / NES 2.0 image and inserts it, replacing whatever
+    /// was running.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), EmuError> {
+        self.insert_cartridge(Cartridge::from_ines(rom)?)
+    }
+
+    /// Presses the reset button. Only the CPU restarts: RAM and the
+    /// cartridge keep their contents, and see [`CPU::reset`] for the
+    /// registers. A movie being recorded gets a soft reset command.
+    pub fn reset(&mut self) {
+        self.movie_commands |= movie::SOFT_RESET;
+        self.cpu.reset();
+    }
+
+    /// Switches the console off and on again with the same cartridge: RAM
+    /// is refilled with the power-on pattern and the board's registers go
+    /// back to their power-on values, though battery-backed memory and
+    /// frozen addresses stay. A raw program lives in RAM, so without a cartridge only the CPU
+    /// starts over. A movie being recorded gets a power cycle command.
+    pub fn power_cycle(&mut self) {
+        self.movie_commands |= movie::POWER_CYCLE;
+        let Some(mut cartridge) = self.cpu.bus.cartridge.take() else {
+            self.cpu.power_on();
+            return;
+        };
+        let vs = self.cpu.bus.vs.map(|vs| VsSystem::new(vs.dip_switches));
+        let frozen = mem::take(&mut self.cpu.bus.frozen);
+
+        self.reinitialize();
+        cartridge.power_on();
+        self.cpu.bus.cartridge = Some(cartridge);
+        self.cpu.bus.vs = vs;
+        self.cpu.bus.frozen = frozen;
+        self.sync_time();
+        self.cpu.power_on();
+    }
+
+    /// The cartridge's battery-backed memory, for the frontend to write
+    /// to a save file; `None` without a battery.
+    pub fn save_data(&self) -> Option<&[u8]> {
+        self.cpu.bus.cartridge.as_ref().and_then(|cartridge| cartridge.save_data())
+    }
+
+    /// A copy of the battery-backed memory that doesn't borrow the console,
+    /// for cloud saves or moving a save to another emulator.
+    pub fn export_save_data(&self) -> Option<Vec<u8>> {
+        self.save_data().map(<[u8]>::to_vec)
+    }
+
+    /// Replaces the battery-backed memory with a save from a file, the
+    /// cloud or another emulator. Saves padded past the cartridge's size,
+    /// as some emulators write them, are accepted and the padding dropped.
+    /// Nothing is changed if the cartridge has no battery or `data` is too
+    /// short.
+    pub fn import_save_data(&mut self, data: &[u8]) -> Result<(), EmuError> {
+        let Some(size) = self.save_data().map(<[u8]>::len) else {
+            return Err(EmuError::SaveData("the cartridge has no battery".to_string()));
+        };
+        if data.len() < size {
+            return Err(EmuError::SaveData(alloc::format!("expected {} bytes, got {}", size, data.len())));
+        }
+
+        if let Some(cartridge) = self.cpu.bus.cartridge.as_mut() {
+            cartridge.load_save_data(&data[..size]);
+        }
+        Ok(())
+    }
+
+    // hands the time to a cartridge with a real-time clock
+    fn sync_time(&mut self) {
+        let time = self.time();
+        if let Some(cartridge) = self.cpu.bus.cartridge.as_mut() {
+            cartridge.set_time(time);
+        }
+    }
+
+    // back to power-on state with nothing frozen; the configuration, hooks
+    // and trace sink stay
+    fn reinitialize(&mut self) {
+        let cpu = &mut self.cpu;
+
+        cpu.bus.cartridge = None;
+        cpu.bus.vs = None;
+        cpu.bus.irq.clear();
+        cpu.bus.controllers = [Joypad::new(); 2];
+        cpu.bus.clear_freezes();
+        cpu.bus.ram.fill(0);
+        self.ram_pattern.fill(&mut cpu.bus.ram[..0x0800]);
+
+        cpu.status = Status::new();
+        cpu.a = 0;
+        cpu.x = 0;
+        cpu.y = 0;
+        cpu.complete = false;
+        cpu.cycles = 0;
+        cpu.total_cycles = 0;
+
+        self.frame = 0;
+        self.cycle_debt = 0.0;
+        self.scanline = 0;
+        self.scanline_started = false;
+        self.debugger.resume();
+        self.debugger.cancel_run();
+        self.history.clear();
+        self.profiler.clear();
+        self.opcode_stats.clear();
+        cpu.bus.events.clear();
+        cpu.bus.watchpoints.take_hit();
+    }
+
+    /// Where the CPU reads `addr` from in the cartridge's iNES file, so a
+    /// debugger can point at the ROM bytes behind an address; `None` for
+    /// anything but PRG ROM.
+    pub fn prg_file_offset(&self, addr: u16) -> Option<usize> {
+        Some(self.layout?.prg + self.cpu.bus.prg_offset(addr)?)
+    }
+
+    /// Where the PPU reads pattern table address `addr` from in the
+    /// cartridge's iNES file; `None` on boards with CHR RAM.
+    pub fn chr_file_offset(&self, addr: u16) -> Option<usize> {
+        Some(self.layout?.chr + self.cpu.bus.chr_offset(addr)?)
+    }
+
+    /// Serializes the CPU, memory, frame counter, IRQ line, controllers,
+    /// VS System inputs and cartridge board into a versioned blob.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = StateWriter::new();
+        let cpu = &self.cpu;
+
+        state.u8(cpu.a);
+        state.u8(cpu.x);
+        state.u8(cpu.y);
+        state.u8(cpu.stack_pointer);
+        state.u16(cpu.program_counter);
+        state.u8(cpu.status.to_byte());
+        state.u64(cpu.cycles);
+        state.u64(cpu.total_cycles);
+        state.bool(cpu.complete);
+        state.bytes(&cpu.bus.ram);
+        state.u64(self.frame);
+        state.f64(self.cycle_debt);
+        state.u16(self.scanline);
+        state.bool(self.scanline_started);
+        cpu.bus.irq.save_state(&mut state);
+        for controller in &cpu.bus.controllers {
+            controller.save_state(&mut state);
+        }
+        state.bool(cpu.bus.vs.is_some());
+        if let Some(vs) = &cpu.bus.vs {
+            vs.save_state(&mut state);
+        }
+        state.bool(cpu.bus.polled);
+
+        // the board's banks, counters and PRG RAM, behind their length
+        let mut board = vec![0; STATE_SIZE];
+        let len = cpu.bus.cartridge.as_ref().map_or(0, |cartridge| cartridge.save_state(&mut board));
+        state.u16(len as u16);
+        state.bytes(&board[..len]);
+
+        state.finish()
+    }
+
+    /// Restores a blob produced by [`Nes::save_state`]. Nothing is changed
+    /// if the state is malformed or was saved with a different kind of
+    /// board in.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), EmuError> {
+        let mut state = StateReader::new(data)?;
+
+        let a = state.u8()?;
+        let x = state.u8()?;
+        let y = state.u8()?;
+        let stack_pointer = state.u8()?;
+        let program_counter = state.u16()?;
+        let status = state.u8()?;
+        let cycles = state.u64()?;
+        let total_cycles = state.u64()?;
+        let complete = state.bool()?;
+        let mut ram = vec![0; self.cpu.bus.ram.len()];
+        state.bytes(&mut ram)?;
+        let frame = state.u64()?;
+        let cycle_debt = state.f64()?;
+        let scanline = state.u16()?;
+        let scanline_started = state.bool()?;
+        let mut irq = IrqLine::new();
+        irq.load_state(&mut state)?;
+        let mut controllers = [Joypad::new(); 2];
+        for controller in &mut controllers {
+            controller.load_state(&mut state)?;
+        }
+        let vs = if state.bool()? {
+            let mut vs = VsSystem::default();
+            vs.load_state(&mut state)?;
+            Some(vs)
+        } else {
+            None
+        };
+        let polled = state.bool()?;
+        let mut board = vec![0; state.u16()? as usize];
+        state.bytes(&mut board)?;
+
+        // every board writes the same length each time, so a mismatch means
+        // the state belongs to another cartridge
+        let mut current = vec![0; STATE_SIZE];
+        let len = self.cpu.bus.cartridge.as_ref().map_or(0, |cartridge| cartridge.save_state(&mut current));
+        if board.len() != len {
+            return Err(EmuError::StateLoad("state is for a different cartridge".to_string()));
+        }
+
+        let cpu = &mut self.cpu;
+        cpu.a = a;
+        cpu.x = x;
+        cpu.y = y;
+        cpu.stack_pointer = stack_pointer;
+        cpu.program_counter = program_counter;
+        cpu.status = Status::from_byte(status);
+        cpu.cycles = cycles;
+        cpu.total_cycles = total_cycles;
+        cpu.complete = complete;
+        cpu.bus.ram.copy_from_slice(&ram);
+        cpu.bus.irq = irq;
+        cpu.bus.controllers = controllers;
+        cpu.bus.vs = vs;
+        cpu.bus.polled = polled;
+        if let Some(cartridge) = cpu.bus.cartridge.as_mut() {
+            cartridge.load_state(&board);
+        }
+        // the frames aren't saved; they rebuild as the game calls and returns
+        cpu.call_stack.clear();
+        self.history.clear();
+        self.frame = frame;
+        self.cycle_debt = cycle_debt;
+        self.scanline = scanline;
+        self.scanline_started = scanline_started;
+
+        Ok(())
+    }
+
+    /// Copies the console into `state` without allocating, for run-ahead
+    /// and rollback. See [`FastState`].
+    pub fn save_fast_state(&self, state: &mut FastState) {
+        let cpu = &self.cpu;
+        state.a = cpu.a;
+        state.x = cpu.x;
+        state.y = cpu.y;
+        state.stack_pointer = cpu.stack_pointer;
+        state.program_counter = cpu.program_counter;
+        state.status = cpu.status.to_byte();
+        state.cycles = cpu.cycles;
+        state.total_cycles = cpu.total_cycles;
+        state.complete = cpu.complete;
+        state.ram.copy_from_slice(&cpu.bus.ram);
+        state.frame = self.frame;
+        state.cycle_debt = self.cycle_debt;
+        state.scanline = self.scanline;
+        state.scanline_started = self.scanline_started;
+        state.irq = cpu.bus.irq;
+        state.controllers = cpu.bus.controllers;
+        state.vs = cpu.bus.vs;
+        state.polled = cpu.bus.polled;
+        state.board_len = match cpu.bus.cartridge.as_ref() {
+            Some(cartridge) => cartridge.save_state(&mut state.board[..]),
+            None => 0,
+        };
+    }
+
+    /// Restores a snapshot taken by [`Nes::save_fast_state`], without
+    /// allocating. As with [`Nes::load_state`], the call stack and the
+    /// step-back history start over.
+    pub fn load_fast_state(&mut self, state: &FastState) {
+        let cpu = &mut self.cpu;
+        cpu.a = state.a;
+        cpu.x = state.x;
+        cpu.y = state.y;
+        cpu.stack_pointer = state.stack_pointer;
+        cpu.program_counter = state.program_counter;
+        cpu.status = Status::from_byte(state.status);
+        cpu.cycles = state.cycles;
+        cpu.total_cycles = state.total_cycles;
+        cpu.complete = state.complete;
+        cpu.bus.ram.copy_from_slice(&state.ram[..]);
+        cpu.bus.irq = state.irq;
+        cpu.bus.controllers = state.controllers;
+        cpu.bus.vs = state.vs;
+        cpu.bus.polled = state.polled;
+        if let Some(cartridge) = cpu.bus.cartridge.as_mut() {
+            cartridge.load_state(&state.board[..state.board_len]);
+        }
+        cpu.call_stack.clear();
+        self.history.clear();
+        self.frame = state.frame;
+        self.cycle_debt = state.cycle_debt;
+        self.scanline = state.scanline;
+        self.scanline_started = state.scanline_started;
+    }
+
+    /// Runs one CPU cycle, unless the debugger stops emulation at this
+    /// instruction boundary. Returns false while stopped.
+    pub fn clock(&mut self) -> Result<bool, EmuError> {
+        let boundary = self.cpu.cycles == 0;
+        if boundary && self.debugger.is_idle() {
+            self.debugger.unchecked();
+        } else if boundary && self.debugger.check(&self.cpu) {
+            return Ok(false);
+        }
+
+        let pc = self.cpu.program_counter;
+        self.clock_cpu()?;
+
+        // instructions run whole on their first cycle, so a watched access
+        // stops before the next one
+        if let Some(hit) = self.cpu.bus.watchpoints.take_hit() {
+            let hit = WatchHit { pc, ..hit };
+            self.debugger.stop(self.cpu.program_counter, BreakReason::Watchpoint(hit));
+        }
+
+        Ok(true)
+    }
+
+    // one CPU cycle, which also clocks the cartridge and takes an IRQ; one
+    // that starts an instruction is also journaled for stepping back,
+    // profiled and counted when those are on
+    fn clock_cpu(&mut self) -> Result<(), EmuError> {
+        let bus = &mut self.cpu.bus;
+        if let Some(cartridge) = bus.cartridge.as_mut() {
+            cartridge.clock();
+            let asserted = cartridge.irq();
+            if asserted && !bus.irq.is_held_by(IrqSource::Mapper) {
+                self.debugger.mapper_irq_asserted();
+            }
+            bus.irq.set(IrqSource::Mapper, asserted);
+        }
+
+        if self.cpu.cycles == 0 && self.cpu.bus.events.is_enabled() {
+            let time = Timestamp { frame: self.frame, scanline: self.scanline, cycle: self.cpu.total_cycles };
+            self.cpu.bus.events.set_time(time);
+        }
+
+        // the line is level-triggered, so an IRQ masked now is taken as
+        // soon as the I flag clears. Taking one is journaled like an
+        // instruction, so stepping back pops what it pushed
+        if self.cpu.cycles == 0 && self.cpu.bus.irq.is_asserted() && !self.cpu.status.interrupt {
+            if self.history.capacity() > 0 {
+                self.history.record(&mut self.cpu, CPU::irq);
+            } else {
+                self.cpu.irq();
+            }
+        }
+
+        if self.cpu.cycles != 0 {
+            return self.cpu.clock();
+        }
+
+        let profiling = self.profiler.is_enabled();
+        if profiling {
+            self.profiler.begin(&self.cpu.call_stack);
+        }
+        if self.opcode_stats.is_enabled() {
+            self.opcode_stats.record(self.cpu.peek(self.cpu.program_counter));
+        }
+
+        if self.history.capacity() > 0 {
+            self.history.record(&mut self.cpu, CPU::clock)?;
+        } else {
+            self.cpu.clock()?;
+        }
+
+        // the cycle just run plus those the instruction has left
+        if profiling {
+            self.profiler.end(&self.cpu.call_stack, self.cpu.cycles + 1);
+        }
+        Ok(())
+    }
+
+    /// Keeps the last `capacity` instructions so [`Nes::step_back`] can
+    /// undo them; 0 turns recording off. Drops what was recorded so far.
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.history = History::new(capacity);
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Undoes the last recorded instruction and stops in the debugger
+    /// before it. Returns false once the history is used up.
+    pub fn step_back(&mut self) -> bool {
+        if !self.history.undo(&mut self.cpu) {
+            return false;
+        }
+
+        self.debugger.stop(self.cpu.program_counter, BreakReason::Step);
+        true
+    }
+
+    /// Runs the next instruction to completion while stopped in the
+    /// debugger, then stops again before the one after it.
+    pub fn step_instruction(&mut self) -> Result<(), EmuError> {
+        self.debugger.resume();
+
+        let pc = self.cpu.program_counter;
+
+        // finish whatever is in flight, then run one whole instruction;
+        // the cycles come out of the frame budget like any others
+        let mut cycles = 0;
+        while self.cpu.cycles > 0 {
+            self.clock_cpu()?;
+            cycles += 1;
+        }
+        self.clock_cpu()?;
+        cycles += 1;
+        while self.cpu.cycles > 0 {
+            self.clock_cpu()?;
+            cycles += 1;
+        }
+        self.cycle_debt -= cycles as f64;
+
+        // a watched access made by the stepped instruction is reported too
+        let reason = match self.cpu.bus.watchpoints.take_hit() {
+            Some(hit) => BreakReason::Watchpoint(WatchHit { pc, ..hit }),
+            None => BreakReason::Step,
+        };
+        self.debugger.stop(self.cpu.program_counter, reason);
+        Ok(())
+    }
+
+    /// Steps over a JSR: the subroutine runs as emulation carries on and
+    /// stops once it returns. Anything else is a single
+    /// [`step_instruction`](Nes::step_instruction).
+    pub fn step_over(&mut self) -> Result<(), EmuError> {
+        let pc = self.cpu.program_counter;
+        // JSR
+        if self.cpu.peek(pc) != 0x20 {
+            return self.step_instruction();
+        }
+
+        self.debugger.run_until(RunTarget::Return { pc: pc.wrapping_add(3), sp: self.cpu.stack_pointer });
+        Ok(())
+    }
+
+    /// Lets emulation carry on until the current subroutine or interrupt
+    /// handler returns.
+    pub fn step_out(&mut self) {
+        self.debugger.run_until(RunTarget::Exit { sp: self.cpu.stack_pointer });
+    }
+
+    /// Lets emulation carry on until it reaches the instruction at `pc`.
+    pub fn run_to(&mut self, pc: u16) {
+        self.debugger.run_until(RunTarget::Address(pc));
+    }
+
+    /// Starts recording the controllers, resets and power cycles into a
+    /// movie, one record per frame from the next one on. Movies play back
+    /// from power on, so call [`Nes::power_cycle`] first for one other
+    /// emulators can play.
+    pub fn start_movie(&mut self) {
+        let pal = self.region() == Region::Pal;
+        self.movie = Some(Movie::new(pal));
+        self.movie_commands = 0;
+    }
+
+    /// Stops recording and returns the movie, if one was being recorded.
+    pub fn stop_movie(&mut self) -> Option<Movie> {
+        self.movie.take()
+    }
+
+    pub fn is_recording_movie(&self) -> bool {
+        self.movie.is_some()
+    }
+
+    // the input the frame about to run starts with
+    fn record_frame(&mut self) {
+        let commands = core::mem::take(&mut self.movie_commands);
+        if let Some(movie) = self.movie.as_mut() {
+            let controllers = &self.cpu.bus.controllers;
+            movie.frames.push(MovieFrame { commands, ports: [controllers[0].buttons(), controllers[1].buttons()] });
+        }
+    }
+
+    /// Runs one frame's worth of CPU cycles for the current region, one
+    /// scanline at a time, stopping early if the program finishes. Any
+    /// [overclocked](NesBuilder::overclock_scanlines) scanlines come on top.
+    ///
+    /// If the debugger stops emulation the frame is left unfinished; check
+    /// [`Debugger::stopped`], and call this again after
+    /// [`Debugger::resume`] to carry on where it left off.
+    pub fn run_frame(&mut self) -> Result<(), EmuError> {
+        if self.scanline == 0 && !self.scanline_started {
+            self.record_frame();
+            self.cpu.bus.polled = false;
+        }
+
+        let region = self.region();
+        let cycles_per_scanline = region.cpu_cycles_per_frame() / region.scanlines_per_frame() as f64;
+        let vblank = region.vblank_scanline();
+        let overclock = self.overclock_scanlines;
+
+        // overclocked scanlines are counted in `self.scanline` straight
+        // after the one vblank starts on, so a stopped frame resumes in them
+        while self.scanline < region.scanlines_per_frame() + overclock {
+            if !self.scanline_started {
+                if self.scanline == vblank {
+                    self.debugger.vblank_started();
+                }
+                if self.scanline <= vblank {
+                    self.cpu.hooks.scanline(self.scanline);
+                } else if self.scanline > vblank + overclock {
+                    self.cpu.hooks.scanline(self.scanline - overclock);
+                }
+                self.cycle_debt += cycles_per_scanline;
+                self.scanline_started = true;
+            }
+
+            while self.cycle_debt >= 1.0 && !self.cpu.complete {
+                if !self.clock()? {
+                    return Ok(());
+                }
+                self.cycle_debt -= 1.0;
+            }
+
+            self.scanline_started = false;
+            self.scanline += 1;
+        }
+
+        self.scanline = 0;
+        self.frame += 1;
+        self.inputs_polled = self.cpu.bus.polled;
+        self.sync_time();
+        if self.cpu.hooks.watches_frame_changes() {
+            self.cpu.hooks.frame_changes(&self.cpu.bus);
+        }
+        self.cpu.hooks.frame(self.frame);
+
+        Ok(())
+    }
+}
+
+/// A frame run by [`Nes::frames`].
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Frame {
+    /// Its number, counting from 1 as [`Nes::frame_count`] does.
+    pub number: u64,
+    /// Whether the program read the controllers during it.
+    pub inputs_polled: bool,
+}
+
+/// The iterator returned by [`Nes::frames`].
+pub struct Frames<'a> {
+    nes: &'a mut Nes,
+    failed: bool,
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Result<Frame, EmuError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.nes.cpu.complete || self.nes.debugger.is_stopped() {
+            return None;
+        }
+
+        if let Err(e) = self.nes.run_frame() {
+            self.failed = true;
+            return Some(Err(e));
+        }
+        // the frame is left unfinished
+        if self.nes.debugger.is_stopped() {
+            return None;
+        }
+
+        Some(Ok(Frame { number: self.nes.frame, inputs_polled: self.nes.inputs_polled }))
+    }
+}
+
+// frontends move the emulator onto worker threads, so it has to stay Send
+const _: () = {
+    fn assert_send<T: Send>() {}
+
+    #[allow(dead_code)]
+    fn check() {
+        assert_send::<Nes>();
+    }
+};
+
+/// Builds a [`Nes`] in one call instead of wiring `Bus` and `CPU` by hand.
+///
+/// ```
+/// use nes_core::constants::Region;
+/// use nes_core::nes::{Nes, RamPattern};
+///
+/// let nes = Nes::builder()
+///     .region(Region::Pal)
+///     .sample_rate(48_000)
+///     .ram_pattern(RamPattern::Fill(0xFF))
+///     .build();
+///
+/// assert_eq!(nes.region(), Region::Pal);
+/// ```
+#[derive(Clone, Debug)]
+pub struct NesBuilder {
+    region: Region,
+    region_pinned: bool,
+    palette: Palette,
+    accuracy: Accuracy,
+    sample_rate: u32,
+    ram_pattern: RamPattern,
+    dip_switches: u8,
+    clock_source: ClockSource,
+    overclock_scanlines: u16,
+    deterministic: bool,
+}
+
+impl Default for NesBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NesBuilder {
+    pub fn new() -> NesBuilder {
+        NesBuilder {
+            region: Region::Ntsc,
+            region_pinned: false,
+            palette: Palette::default(),
+            accuracy: Accuracy::default(),
+            sample_rate: 44_100,
+            ram_pattern: RamPattern::default(),
+            dip_switches: 0,
+            clock_source: ClockSource::default(),
+            overclock_scanlines: 0,
+            deterministic: false,
+        }
+    }
+
+    /// The TV system to start in. A cartridge switches the console to the
+    /// one its header names when it goes in, unless
+    /// [`NesBuilder::pin_region`] is set.
+    pub fn region(mut self, region: Region) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Keeps the builder's region whatever cartridges ask for, e.g. to
+    /// play a PAL game at NTSC speed.
+    pub fn pin_region(mut self, pinned: bool) -> Self {
+        self.region_pinned = pinned;
+        self
+    }
+
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    pub fn accuracy(mut self, accuracy: Accuracy) -> Self {
+        self.accuracy = accuracy;
+        self
+    }
+
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    pub fn ram_pattern(mut self, ram_pattern: RamPattern) -> Self {
+        self.ram_pattern = ram_pattern;
+        self
+    }
+
+    /// Switch 1 in bit 0 through switch 8 in bit 7, for VS System games
+    /// and boards with DIP switches, such as the NWC timer.
+    pub fn dip_switches(mut self, dip_switches: u8) -> Self {
+        self.dip_switches = dip_switches;
+        self
+    }
+
+    /// Where cartridge clocks get the time; the host's clock by default.
+    pub fn clock_source(mut self, clock_source: ClockSource) -> Self {
+        self.clock_source = clock_source;
+        self
+    }
+
+    /// Runs this many extra scanlines of CPU cycles each frame at the start
+    /// of vblank, to cut slowdown in games that fall behind. Scanline hooks,
+    /// the frame rate and cartridge clocks see a normal frame; the game
+    /// just gets more CPU time before it has to draw the next one. 0 by
+    /// default.
+    pub fn overclock_scanlines(mut self, scanlines: u16) -> Self {
+        self.overclock_scanlines = scanlines;
+        self
+    }
+
+    /// Rules out everything that could make two runs with the same input
+    /// differ, for movies and netplay: the host's clock gives way to
+    /// [`ClockSource::Emulated`], from the start set with
+    /// [`NesBuilder::clock_source`] or else 1970. Every [`RamPattern`] is
+    /// already repeatable, and the core has no unofficial opcodes with
+    /// unstable results and no PPU to start out of phase.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    pub fn build(self) -> Nes {
+        let clock_source = match self.clock_source {
+            #[cfg(feature = "std")]
+            ClockSource::Host if self.deterministic => ClockSource::Emulated { start: 0 },
+            clock_source => clock_source,
+        };
+
+        let mut bus = Bus::new();
+        self.ram_pattern.fill(&mut bus.ram[..0x0800]);
+
+        let mut cpu = CPU::new(bus);
+        cpu.region = self.region;
+
+        Nes {
+            cpu,
+            debugger: Debugger::new(),
+            profiler: Profiler::new(),
+            opcode_stats: OpcodeStats::new(),
+            history: History::default(),
+            region: self.region,
+            region_pinned: self.region_pinned,
+            palette: self.palette,
+            accuracy: self.accuracy,
+            sample_rate: self.sample_rate,
+            ram_pattern: self.ram_pattern,
+            dip_switches: self.dip_switches,
+            clock_source,
+            overclock_scanlines: self.overclock_scanlines,
+            deterministic: self.deterministic,
+            frame: 0,
+            cycle_debt: 0.0,
+            scanline: 0,
+            scanline_started: false,
+            speed: 1.0,
+            movie: None,
+            movie_commands: 0,
+            layout: None,
+            inputs_polled: false,
+        }
+    }
+}
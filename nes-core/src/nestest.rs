@@ -0,0 +1,174 @@
+//! Runs nestest.nes in its automated mode and checks the CPU against the
+//! reference log, instruction by instruction.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use crate::constants::Status;
+use crate::cpu::CPU;
+use crate::error::EmuError;
+use crate::nes::Nes;
+use crate::trace::TraceEntry;
+
+/// The automated mode starts here instead of at the reset vector.
+pub const START: u16 = 0xC000;
+
+// the cycles the log counts for the reset sequence before its first line
+const START_CYCLES: u64 = 7;
+
+// where a log line marks an unofficial opcode with `*`, before the mnemonic
+const UNOFFICIAL_MARK: usize = 15;
+
+/// The first line where the emulator and the reference log disagree.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Divergence {
+    /// 1-based line in the log.
+    pub line: usize,
+    pub expected: String,
+    pub actual: String,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Outcome {
+    /// Every line up to the first unofficial opcode matched; `lines` is
+    /// how many that was.
+    Passed { lines: usize },
+    Diverged(Divergence),
+    /// Emulation failed before the instruction on `line`.
+    Failed { line: usize, error: EmuError },
+}
+
+/// Runs `rom` from [`START`] for as many instructions as `log` has lines,
+/// stopping at the first line the log marks as an unofficial opcode, since
+/// the core doesn't implement them.
+///
+/// The PC, registers and `CYC:` cycle count are compared. The disassembly
+/// and PPU columns differ between emulators' logs and are ignored, as is
+/// `CYC:` in older logs, which count PPU dots there next to an `SL:`
+/// column.
+pub fn run(rom: &[u8], log: &str) -> Result<Outcome, EmuError> {
+    let mut nes = Nes::builder().build();
+    nes.load_rom(rom)?;
+
+    // the state the reference log starts from
+    let cpu = &mut nes.cpu;
+    cpu.program_counter = START;
+    cpu.status = Status::from_byte(0x24);
+    cpu.stack_pointer = 0xFD;
+    cpu.cycles = 0;
+    cpu.total_cycles = START_CYCLES;
+
+    let mut lines = 0;
+    for (i, expected) in log.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let line = i + 1;
+        if expected.as_bytes().get(UNOFFICIAL_MARK) == Some(&b'*') {
+            break;
+        }
+        lines += 1;
+
+        // an unknown opcode has no entry, and `step` reports it below
+        if let Some(entry) = cpu.trace_entry() {
+            if !matches(&entry, expected) || !cycles_match(cpu.total_cycles, expected) {
+                let actual = format!("{} CYC:{}", entry, cpu.total_cycles);
+                return Ok(Outcome::Diverged(Divergence { line, expected: expected.to_string(), actual }));
+            }
+        }
+
+        if let Err(error) = step(cpu) {
+            return Ok(Outcome::Failed { line, error });
+        }
+    }
+
+    Ok(Outcome::Passed { lines })
+}
+
+fn step(cpu: &mut CPU) -> Result<(), EmuError> {
+    cpu.clock()?;
+    while cpu.cycles > 0 {
+        cpu.clock()?;
+    }
+    Ok(())
+}
+
+/// Whether a log line in the standard nestest format shows the same PC and
+/// registers as `entry`.
+pub fn matches(entry: &TraceEntry, line: &str) -> bool {
+    let registers = [
+        ("A:", entry.a),
+        ("X:", entry.x),
+        ("Y:", entry.y),
+        ("P:", entry.status),
+        ("SP:", entry.stack_pointer),
+    ];
+
+    let pc = line.get(..4).and_then(|pc| u16::from_str_radix(pc, 16).ok());
+    pc == Some(entry.pc) && registers.iter().all(|&(name, value)| field(line, name) == Some(value))
+}
+
+// whether a log line's `CYC:` column, when it counts CPU cycles, is `cycles`
+fn cycles_match(cycles: u64, line: &str) -> bool {
+    if line.contains(" SL:") {
+        return true;
+    }
+    let Some(start) = line.find(" CYC:") else {
+        return true;
+    };
+    let digits = line[start + 5..].split_whitespace().next();
+    digits.and_then(|digits| digits.parse().ok()).is_none_or(|expected: u64| expected == cycles)
+}
+
+// the hex byte after ` NAME` in a log line
+fn field(line: &str, name: &str) -> Option<u8> {
+    let start = line.match_indices(name).find(|&(i, _)| i > 0 && line.as_bytes()[i - 1] == b' ')?.0;
+    let digits = line.get(start + name.len()..start + name.len() + 2)?;
+    u8::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    // an NROM image with `program` at $C000
+    fn rom(program: &[u8]) -> Vec<u8> {
+        let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut prg = vec![0xEA; 0x4000];
+        prg[..program.len()].copy_from_slice(program);
+        rom.extend(prg);
+        rom
+    }
+
+    const LDA_1: &str = "C000  A9 01     LDA #$01                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7";
+
+    #[test]
+    fn stops_at_the_first_unofficial_opcode() {
+        let log = [LDA_1, "C002  04 A9    *NOP $A9 = 00                    A:01 X:00 Y:00 P:24 SP:FD PPU:  0, 27 CYC:9"];
+        let outcome = run(&rom(&[0xA9, 0x01, 0x04, 0xA9]), &log.join("\n")).unwrap();
+        assert_eq!(outcome, Outcome::Passed { lines: 1 });
+    }
+
+    #[test]
+    fn compares_cycles() {
+        let program = [0xA9, 0x01, 0xA9, 0x02];
+        let lda_2 = "C002  A9 02     LDA #$02                        A:01 X:00 Y:00 P:24 SP:FD PPU:  0, 27 CYC:";
+        let log = format!("{}\n{}9", LDA_1, lda_2);
+        assert_eq!(run(&rom(&program), &log).unwrap(), Outcome::Passed { lines: 2 });
+
+        let log = format!("{}\n{}10", LDA_1, lda_2);
+        match run(&rom(&program), &log).unwrap() {
+            Outcome::Diverged(divergence) => {
+                assert_eq!(divergence.line, 2);
+                assert!(divergence.actual.ends_with("CYC:9"));
+            },
+            outcome => panic!("expected a divergence, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn ignores_ppu_dots_in_old_logs() {
+        let line = "C000  A9 01     LDA #$01                        A:00 X:00 Y:00 P:24 SP:FD CYC:  0 SL:241";
+        assert_eq!(run(&rom(&[0xA9, 0x01]), line).unwrap(), Outcome::Passed { lines: 1 });
+    }
+}
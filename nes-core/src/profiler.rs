@@ -0,0 +1,155 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::debugger::CallStack;
+
+/// A subroutine or interrupt handler, by entry point and the PRG bank it
+/// was entered in.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Hash)]
+pub struct Routine {
+    pub bank: u16,
+    pub addr: u16,
+}
+
+impl fmt::Display for Routine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02X}:{:04X}", self.bank, self.addr)
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct RoutineStats {
+    pub calls: u64,
+    /// Cycles spent in the routine's own instructions.
+    pub self_cycles: u64,
+    /// Cycles spent in the routine and everything it called.
+    pub total_cycles: u64,
+}
+
+/// Cycles spent per routine, attributed through the CPU's
+/// [`CallStack`]. Code outside any call counts towards the `None` entry.
+#[derive(Default, Debug, Clone)]
+pub struct Profiler {
+    enabled: bool,
+    routines: BTreeMap<Option<Routine>, RoutineStats>,
+    // routines on the stack for the instruction running now, innermost
+    // first and each only once
+    active: Vec<Option<Routine>>,
+    entered: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn clear(&mut self) {
+        self.routines.clear();
+    }
+
+    pub fn total_cycles(&self) -> u64 {
+        self.routines.get(&None).map_or(0, |stats| stats.total_cycles)
+    }
+
+    /// Every routine seen so far, hottest first by self cycles.
+    pub fn report(&self) -> Vec<(Option<Routine>, RoutineStats)> {
+        let mut report: Vec<_> = self.routines.iter().map(|(&routine, &stats)| (routine, stats)).collect();
+        report.sort_by_key(|&(_, stats)| core::cmp::Reverse(stats.self_cycles));
+        report
+    }
+
+    /// Notes the routines an instruction is about to run in.
+    pub(crate) fn begin(&mut self, stack: &CallStack) {
+        self.active.clear();
+        for frame in stack.frames().iter().rev() {
+            let routine = Some(Routine { bank: frame.bank, addr: frame.target });
+            // recursion would count the same cycles twice
+            if !self.active.contains(&routine) {
+                self.active.push(routine);
+            }
+        }
+        self.active.push(None);
+        self.entered = stack.entered();
+    }
+
+    /// Charges the instruction's cycles, and counts the call it made, if
+    /// any.
+    pub(crate) fn end(&mut self, stack: &CallStack, cycles: u64) {
+        for (i, routine) in self.active.iter().enumerate() {
+            let stats = self.routines.entry(*routine).or_default();
+            stats.total_cycles += cycles;
+            if i == 0 {
+                stats.self_cycles += cycles;
+            }
+        }
+
+        if stack.entered() != self.entered {
+            if let Some(frame) = stack.frames().last() {
+                let routine = Some(Routine { bank: frame.bank, addr: frame.target });
+                self.routines.entry(routine).or_default().calls += 1;
+            }
+        }
+    }
+}
+
+/// How many times each opcode byte has executed.
+#[derive(Debug, Clone)]
+pub struct OpcodeStats {
+    enabled: bool,
+    counts: [u64; 256],
+}
+
+impl Default for OpcodeStats {
+    fn default() -> Self {
+        OpcodeStats { enabled: false, counts: [0; 256] }
+    }
+}
+
+impl OpcodeStats {
+    pub fn new() -> OpcodeStats {
+        OpcodeStats::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn clear(&mut self) {
+        self.counts = [0; 256];
+    }
+
+    pub fn count(&self, opcode: u8) -> u64 {
+        self.counts[opcode as usize]
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The opcodes that ran, most frequent first.
+    pub fn report(&self) -> Vec<(u8, u64)> {
+        let mut report: Vec<_> = (0..=255u8)
+            .map(|opcode| (opcode, self.counts[opcode as usize]))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        report.sort_by_key(|&(_, count)| core::cmp::Reverse(count));
+        report
+    }
+
+    pub(crate) fn record(&mut self, opcode: u8) {
+        self.counts[opcode as usize] += 1;
+    }
+}
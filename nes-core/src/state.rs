@@ -0,0 +1,276 @@
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::controller::Joypad;
+use crate::error::EmuError;
+use crate::irq::IrqLine;
+use crate::mapper::STATE_SIZE;
+use crate::vs::VsSystem;
+
+pub const MAGIC: &[u8; 4] = b"NESS";
+pub const VERSION: u8 = 4;
+
+/// Appends little-endian fields to a save state buffer.
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> StateWriter {
+        let mut data = Vec::new();
+        data.extend_from_slice(MAGIC);
+        data.push(VERSION);
+        StateWriter { data }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.data.push(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, value: f64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) {
+        self.data.extend_from_slice(value);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl Default for StateWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads fields back in the order a [`StateWriter`] wrote them.
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<StateReader<'a>, EmuError> {
+        if data.len() < 5 || &data[..4] != MAGIC {
+            return Err(EmuError::StateLoad("not a save state".to_string()));
+        }
+
+        if data[4] != VERSION {
+            return Err(EmuError::StateLoad(alloc::format!("unsupported state version {}", data[4])));
+        }
+
+        Ok(StateReader { data, position: 5 })
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], EmuError> {
+        if self.position + len > self.data.len() {
+            return Err(EmuError::StateLoad("state is truncated".to_string()));
+        }
+
+        let slice = &self.data[self.position..self.position + len];
+        self.position += len;
+        Ok(slice)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, EmuError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, EmuError> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, EmuError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, EmuError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, EmuError> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn bytes(&mut self, out: &mut [u8]) -> Result<(), EmuError> {
+        out.copy_from_slice(self.take(out.len())?);
+        Ok(())
+    }
+}
+
+/// Writes a cartridge board's fields into a fixed buffer without
+/// allocating, for [`Mapper::save_state`](crate::mapper::Mapper::save_state).
+///
+/// The buffer is [`mapper::STATE_SIZE`](crate::mapper::STATE_SIZE) bytes,
+/// which every board fits in.
+pub struct BoardWriter<'a> {
+    out: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> BoardWriter<'a> {
+    pub fn new(out: &'a mut [u8]) -> BoardWriter<'a> {
+        BoardWriter { out, len: 0 }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) {
+        self.out[self.len..self.len + value.len()].copy_from_slice(value);
+        self.len += value.len();
+    }
+
+    /// How many bytes have been written.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Reads fields back in the order a [`BoardWriter`] wrote them.
+///
+/// Reading past the end gives zeros rather than an error, so a board never
+/// stops halfway through loading; [`Nes::load_state`](crate::nes::Nes::load_state)
+/// checks the length before handing a board its data.
+pub struct BoardReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BoardReader<'a> {
+    pub fn new(data: &'a [u8]) -> BoardReader<'a> {
+        BoardReader { data, position: 0 }
+    }
+
+    pub fn u8(&mut self) -> u8 {
+        let mut value = [0];
+        self.bytes(&mut value);
+        value[0]
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.u8() != 0
+    }
+
+    pub fn u16(&mut self) -> u16 {
+        let mut value = [0; 2];
+        self.bytes(&mut value);
+        u16::from_le_bytes(value)
+    }
+
+    pub fn u32(&mut self) -> u32 {
+        let mut value = [0; 4];
+        self.bytes(&mut value);
+        u32::from_le_bytes(value)
+    }
+
+    pub fn bytes(&mut self, out: &mut [u8]) {
+        let start = self.position.min(self.data.len());
+        let end = (self.position + out.len()).min(self.data.len());
+        out.fill(0);
+        out[..end - start].copy_from_slice(&self.data[start..end]);
+        self.position += out.len();
+    }
+}
+
+/// A console snapshot held in memory for run-ahead and rollback, filled
+/// by [`crate::nes::Nes::save_fast_state`].
+///
+/// It holds what a save state does, plus the IRQ line, controllers and
+/// VS cabinet, in fixed-size fields that are copied in and out without a
+/// header, version or allocation, so saving and loading are cheap enough
+/// to do several times a frame. It only means something to the build that
+/// made it; use [`crate::nes::Nes::save_state`] for anything written to
+/// disk.
+#[derive(Clone)]
+pub struct FastState {
+    pub(crate) a: u8,
+    pub(crate) x: u8,
+    pub(crate) y: u8,
+    pub(crate) stack_pointer: u8,
+    pub(crate) program_counter: u16,
+    pub(crate) status: u8,
+    pub(crate) cycles: u64,
+    pub(crate) total_cycles: u64,
+    pub(crate) complete: bool,
+    pub(crate) ram: Box<[u8; 64 * 1024]>,
+    pub(crate) frame: u64,
+    pub(crate) cycle_debt: f64,
+    pub(crate) scanline: u16,
+    pub(crate) scanline_started: bool,
+    pub(crate) irq: IrqLine,
+    pub(crate) controllers: [Joypad; 2],
+    pub(crate) vs: Option<VsSystem>,
+    pub(crate) polled: bool,
+    // the cartridge board's registers and RAM, the first `board_len` bytes
+    pub(crate) board: Box<[u8; STATE_SIZE]>,
+    pub(crate) board_len: usize,
+}
+
+impl FastState {
+    /// An empty snapshot; this is the only allocation, so keep it around
+    /// and save into it again.
+    pub fn new() -> FastState {
+        FastState {
+            a: 0,
+            x: 0,
+            y: 0,
+            stack_pointer: 0,
+            program_counter: 0,
+            status: 0,
+            cycles: 0,
+            total_cycles: 0,
+            complete: false,
+            ram: Box::new([0; 64 * 1024]),
+            frame: 0,
+            cycle_debt: 0.0,
+            scanline: 0,
+            scanline_started: false,
+            irq: IrqLine::new(),
+            controllers: [Joypad::new(); 2],
+            vs: None,
+            polled: false,
+            board: Box::new([0; STATE_SIZE]),
+            board_len: 0,
+        }
+    }
+}
+
+impl Default for FastState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,100 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::ops::RangeInclusive;
+
+use crate::constants::{AddressingMode, OpCode};
+
+/// One executed instruction, captured just before it runs.
+#[derive(Debug, Clone)]
+pub struct TraceEntry {
+    pub pc: u16,
+    /// Raw instruction bytes; only the first `len` are meaningful.
+    pub bytes: [u8; 3],
+    pub len: u8,
+    /// Disassembled instruction, e.g. `LDA #$01`.
+    pub text: String,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub status: u8,
+    pub stack_pointer: u8,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut hex = String::new();
+        for byte in &self.bytes[..self.len as usize] {
+            write!(hex, "{:02X} ", byte)?;
+        }
+
+        write!(
+            f,
+            "{:04X}  {:<9} {:<31} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+            self.pc, hex, self.text, self.a, self.x, self.y, self.status, self.stack_pointer,
+        )
+    }
+}
+
+/// Receives diagnostic output from the CPU. Nothing is traced (and no
+/// formatting work is done) unless a sink is installed.
+pub trait TraceSink {
+    fn trace(&mut self, entry: &TraceEntry);
+}
+
+impl<F: FnMut(&TraceEntry)> TraceSink for F {
+    fn trace(&mut self, entry: &TraceEntry) {
+        self(entry)
+    }
+}
+
+/// Which instructions reach the trace sink, to keep traces of real games
+/// down to the part of interest. Each list left empty lets everything
+/// through; an instruction is traced when it passes all three.
+#[derive(Clone, Debug, Default)]
+pub struct TraceFilter {
+    /// PC ranges to trace.
+    pub ranges: Vec<RangeInclusive<u16>>,
+    /// PRG banks to trace, as the mapper numbers them.
+    pub banks: Vec<u16>,
+    /// Mnemonics to trace, in upper case, e.g. `JSR`.
+    pub mnemonics: Vec<String>,
+}
+
+impl TraceFilter {
+    /// Whether the instruction at `pc` is traced. `bank` is only asked
+    /// for when banks are being filtered on.
+    pub fn allows(&self, pc: u16, opcode: &OpCode, bank: impl FnOnce() -> u16) -> bool {
+        (self.ranges.is_empty() || self.ranges.iter().any(|range| range.contains(&pc)))
+            && (self.mnemonics.is_empty() || self.mnemonics.iter().any(|name| name == opcode.name))
+            && (self.banks.is_empty() || self.banks.contains(&bank()))
+    }
+}
+
+/// Formats an instruction as assembly; `bytes` starts at the opcode.
+pub fn disassemble(opcode: &OpCode, bytes: &[u8]) -> String {
+    let byte = |i: usize| bytes.get(i).copied().unwrap_or(0);
+
+    let operand = match opcode.addressing_mode {
+        AddressingMode::Immediate => format!("#${:02X}", byte(1)),
+        AddressingMode::ZeroPage => format!("${:02X}", byte(1)),
+        AddressingMode::ZeroPageX => format!("${:02X},X", byte(1)),
+        AddressingMode::ZeroPageY => format!("${:02X},Y", byte(1)),
+        AddressingMode::Absolute => format!("${:02X}{:02X}", byte(2), byte(1)),
+        AddressingMode::AbsoluteX => format!("${:02X}{:02X},X", byte(2), byte(1)),
+        AddressingMode::AbsoluteY => format!("${:02X}{:02X},Y", byte(2), byte(1)),
+        AddressingMode::Indirect => format!("(${:02X}{:02X})", byte(2), byte(1)),
+        AddressingMode::IndirectX => format!("(${:02X},X)", byte(1)),
+        AddressingMode::IndirectY => format!("(${:02X}),Y", byte(1)),
+        AddressingMode::Relative => format!("*{:+}", byte(1) as i8),
+        AddressingMode::Accumulator => "A".to_string(),
+        AddressingMode::Implicit => String::new(),
+    };
+
+    if operand.is_empty() {
+        opcode.name.to_string()
+    } else {
+        format!("{} {}", opcode.name, operand)
+    }
+}
@@ -0,0 +1,61 @@
+use crate::error::EmuError;
+use crate::state::{StateReader, StateWriter};
+
+/// The cabinet inputs of a VS UniSystem board, read alongside the
+/// controllers on `$4016`/`$4017`.
+///
+/// `$4016` carries the service button in bit 2, DIP switches 1-2 in bits
+/// 3-4 and the two coin slots in bits 5-6; `$4017` carries DIP switches
+/// 3-8 in bits 2-7.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct VsSystem {
+    /// Switch 1 in bit 0 through switch 8 in bit 7; a set bit is on.
+    pub dip_switches: u8,
+    coins: [bool; 2],
+    service: bool,
+}
+
+impl VsSystem {
+    pub fn new(dip_switches: u8) -> VsSystem {
+        VsSystem { dip_switches, ..VsSystem::default() }
+    }
+
+    /// Holds a coin in `slot` (0 or 1). Games count a coin when the switch
+    /// closes for a few frames and opens again, like a key press.
+    pub fn set_coin(&mut self, slot: usize, inserted: bool) {
+        if let Some(coin) = self.coins.get_mut(slot) {
+            *coin = inserted;
+        }
+    }
+
+    pub fn set_service(&mut self, pressed: bool) {
+        self.service = pressed;
+    }
+
+    /// The bits the cabinet adds to a read of controller port `port`.
+    pub(crate) fn port_bits(&self, port: usize) -> u8 {
+        match port {
+            0 => {
+                (self.service as u8) << 2
+                    | (self.dip_switches & 0x03) << 3
+                    | (self.coins[0] as u8) << 5
+                    | (self.coins[1] as u8) << 6
+            },
+            _ => self.dip_switches & 0xFC,
+        }
+    }
+
+    pub(crate) fn save_state(&self, state: &mut StateWriter) {
+        state.u8(self.dip_switches);
+        state.bool(self.coins[0]);
+        state.bool(self.coins[1]);
+        state.bool(self.service);
+    }
+
+    pub(crate) fn load_state(&mut self, state: &mut StateReader) -> Result<(), EmuError> {
+        self.dip_switches = state.u8()?;
+        self.coins = [state.bool()?, state.bool()?];
+        self.service = state.bool()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+//! Checks that running frames doesn't touch the heap once the console has
+//! warmed up, so frontends can run the core from an audio callback or a
+//! tight loop without allocator stalls.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use nes_core::nes::Nes;
+use nes_core::state::FastState;
+
+// counts every allocation and reallocation in the process; this file holds
+// a single test so nothing else runs alongside it
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// frames to run before counting, long enough to fill a 10k instruction
+// history and meet every routine the profiler will see
+const WARM_UP_FRAMES: usize = 10;
+const FRAMES: usize = 60;
+
+#[rustfmt::skip]
+const PROGRAM: &[u8] = &[
+    0xA2, 0x00,       // C000: LDX #$00
+    0xBD, 0x00, 0x02, // C002: LDA $0200,X
+    0x69, 0x03,       // C005: ADC #$03
+    0x9D, 0x00, 0x02, // C007: STA $0200,X
+    0x20, 0x20, 0xC0, // C00A: JSR $C020
+    0xE8,             // C00D: INX
+    0xD0, 0xF2,       // C00E: BNE $C002
+    0x4C, 0x00, 0xC0, // C010: JMP $C000
+];
+
+#[rustfmt::skip]
+const SUBROUTINE: &[u8] = &[
+    0x48, // C020: PHA
+    0x68, // C021: PLA
+    0x60, // C022: RTS
+];
+
+fn nes() -> Nes {
+    let mut prg = vec![0xEA; 0x4000];
+    prg[..PROGRAM.len()].copy_from_slice(PROGRAM);
+    prg[0x20..0x20 + SUBROUTINE.len()].copy_from_slice(SUBROUTINE);
+    prg[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0xC0]);
+
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend(prg);
+
+    let mut nes = Nes::builder().build();
+    nes.load_rom(&rom).expect("test ROM loads");
+    nes
+}
+
+fn allocations_per_run(nes: &mut Nes) -> usize {
+    for _ in 0..WARM_UP_FRAMES {
+        nes.run_frame().unwrap();
+    }
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..FRAMES {
+        nes.run_frame().unwrap();
+    }
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+#[test]
+fn frames_do_not_allocate() {
+    let mut plain = nes();
+    assert_eq!(allocations_per_run(&mut plain), 0, "plain frames allocated");
+
+    let mut instrumented = nes();
+    instrumented.set_history_capacity(10_000);
+    instrumented.profiler.set_enabled(true);
+    instrumented.opcode_stats.set_enabled(true);
+    assert_eq!(allocations_per_run(&mut instrumented), 0, "instrumented frames allocated");
+
+    // run-ahead: save, run a frame, roll back and run it again
+    let mut state = FastState::new();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..FRAMES {
+        plain.save_fast_state(&mut state);
+        plain.run_frame().unwrap();
+        plain.load_fast_state(&state);
+        plain.run_frame().unwrap();
+    }
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed) - before, 0, "fast states allocated");
+}
@@ -0,0 +1,106 @@
+//! Checks ADC, SBC and CMP against a plain reference model of the 6502's
+//! arithmetic, for every accumulator, operand and carry in.
+//!
+//! The model works in signed and unsigned integers wide enough not to
+//! wrap, rather than the bit tricks the CPU uses, so the two don't share
+//! mistakes.
+
+use nes_core::nes::Nes;
+
+const CLC: u8 = 0x18;
+const SEC: u8 = 0x38;
+const CLV: u8 = 0xB8;
+const LDA: u8 = 0xA9;
+const ADC: u8 = 0x69;
+const SBC: u8 = 0xE9;
+const CMP: u8 = 0xC9;
+const BRK: u8 = 0x00;
+
+#[derive(PartialEq, Eq, Debug)]
+struct Outcome {
+    a: u8,
+    carry: bool,
+    zero: bool,
+    overflow: bool,
+    negative: bool,
+}
+
+impl Outcome {
+    fn new(a: u8, result: u8, carry: bool, overflow: bool) -> Outcome {
+        Outcome { a, carry, zero: result == 0, overflow, negative: result & 0x80 != 0 }
+    }
+}
+
+// runs `opcode #operand` with the accumulator and carry set up beforehand
+// and the overflow flag clear, powering the same console back on each time
+// rather than building one per case
+fn run(nes: &mut Nes, opcode: u8, a: u8, operand: u8, carry: bool) -> Outcome {
+    let program = [if carry { SEC } else { CLC }, CLV, LDA, a, opcode, operand, BRK];
+    for (i, &byte) in program.iter().enumerate() {
+        nes.cpu.bus.poke(0x0600 + i as u16, byte);
+    }
+    nes.cpu.power_on();
+    while !nes.cpu.complete {
+        nes.clock().unwrap();
+    }
+
+    let status = &nes.cpu.status;
+    Outcome {
+        a: nes.cpu.a,
+        carry: status.carry,
+        zero: status.zero,
+        overflow: status.overflow,
+        negative: status.negative,
+    }
+}
+
+fn adc(a: u8, operand: u8, carry: bool) -> Outcome {
+    let sum = a as u16 + operand as u16 + carry as u16;
+    let signed = a as i8 as i16 + operand as i8 as i16 + carry as i16;
+    Outcome::new(sum as u8, sum as u8, sum > 0xFF, !(-128..=127).contains(&signed))
+}
+
+// the carry is an inverted borrow: clear means subtract one more
+fn sbc(a: u8, operand: u8, carry: bool) -> Outcome {
+    let difference = a as i16 - operand as i16 - !carry as i16;
+    let signed = a as i8 as i16 - operand as i8 as i16 - !carry as i16;
+    Outcome::new(difference as u8, difference as u8, difference >= 0, !(-128..=127).contains(&signed))
+}
+
+// leaves the accumulator and overflow alone and ignores the carry in
+fn cmp(a: u8, operand: u8, _carry: bool) -> Outcome {
+    let difference = a as i16 - operand as i16;
+    Outcome::new(a, difference as u8, difference >= 0, false)
+}
+
+// every accumulator, operand and carry in: 128K cases per instruction
+fn check(opcode: u8, model: fn(u8, u8, bool) -> Outcome) {
+    let mut nes = Nes::builder().build();
+    nes.load(&[BRK]).unwrap();
+    for a in 0..=255 {
+        for operand in 0..=255 {
+            for carry in [false, true] {
+                assert_eq!(
+                    run(&mut nes, opcode, a, operand, carry),
+                    model(a, operand, carry),
+                    "A={a:02X} M={operand:02X} C={carry}",
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn adc_matches_model() {
+    check(ADC, adc);
+}
+
+#[test]
+fn sbc_matches_model() {
+    check(SBC, sbc);
+}
+
+#[test]
+fn cmp_matches_model() {
+    check(CMP, cmp);
+}
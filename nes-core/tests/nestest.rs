@@ -0,0 +1,37 @@
+//! Checks the CPU against nestest's reference log, registers and cycle
+//! counts, up to the unofficial opcodes the core doesn't implement.
+//!
+//! The ROM and log are not in the repository. Put them at
+//! `tests/roms/nestest.nes` and `tests/roms/nestest.log`, or point
+//! `NESTEST_ROM` and `NESTEST_LOG` at them; without them the test is
+//! skipped.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use nes_core::nestest::{self, Outcome};
+
+fn path(var: &str, default: &str) -> PathBuf {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(default))
+}
+
+#[test]
+fn nestest_matches_reference_log() {
+    let rom = path("NESTEST_ROM", "tests/roms/nestest.nes");
+    let log = path("NESTEST_LOG", "tests/roms/nestest.log");
+    let (Ok(rom), Ok(log)) = (fs::read(&rom), fs::read_to_string(&log)) else {
+        eprintln!("skipping: nestest ROM or log not found ({} / {})", rom.display(), log.display());
+        return;
+    };
+
+    match nestest::run(&rom, &log).expect("nestest.nes should load") {
+        Outcome::Passed { .. } => {},
+        Outcome::Diverged(d) => {
+            panic!("diverged at line {}\nexpected: {}\nactual:   {}", d.line, d.expected, d.actual)
+        },
+        Outcome::Failed { line, error } => panic!("failed at line {}: {}", line, error),
+    }
+}
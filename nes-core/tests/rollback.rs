@@ -0,0 +1,90 @@
+//! Checks that fast states and save states bring back everything a banked
+//! board and the ports hold, so run-ahead and quick loads don't desync on
+//! anything but NROM.
+
+use nes_core::controller::Button;
+use nes_core::irq::IrqSource;
+use nes_core::nes::Nes;
+use nes_core::state::FastState;
+
+const PRG_BANK_8K: usize = 8 * 1024;
+
+// mapper 206 with eight 8KB banks, each filled with its own number, and a
+// reset vector pointing at an endless loop in the last bank
+fn namco108() -> Nes {
+    let mut prg: Vec<u8> = (0..8u8).flat_map(|bank| vec![bank; PRG_BANK_8K]).collect();
+    let end = prg.len();
+    prg[end - 0x2000..end - 0x1FFD].copy_from_slice(&[0x4C, 0x00, 0xE0]); // E000: JMP $E000
+    prg[end - 4..end - 2].copy_from_slice(&[0x00, 0xE0]);
+
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 4, 0, 0xE0, 0xC0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend(prg);
+
+    let mut nes = Nes::builder().build();
+    nes.load_rom(&rom).expect("test ROM loads");
+    nes
+}
+
+#[test]
+fn fast_state_restores_banks_irq_and_controllers() {
+    let mut nes = namco108();
+    let mut state = FastState::new();
+    nes.save_fast_state(&mut state);
+    assert_eq!(nes.cpu.bus.peek(0x8000), 0);
+
+    // R6 = bank 5 at $8000
+    nes.cpu.bus.write(0x8000, 6);
+    nes.cpu.bus.write(0x8001, 5);
+    nes.cpu.bus.irq.assert(IrqSource::External);
+    nes.cpu.bus.controllers[0].set_button(Button::Start, true);
+    nes.cpu.bus.write(0x4016, 1);
+    assert_eq!(nes.cpu.bus.peek(0x8000), 5);
+
+    nes.load_fast_state(&state);
+    assert_eq!(nes.cpu.bus.peek(0x8000), 0);
+    assert!(!nes.cpu.bus.irq.is_asserted());
+    assert!(!nes.cpu.bus.controllers[0].is_pressed(Button::Start));
+
+    // the board's select register comes back too: this writes R6 again
+    nes.cpu.bus.write(0x8000, 6);
+    nes.load_fast_state(&state);
+    nes.cpu.bus.write(0x8001, 2);
+    assert_eq!(nes.cpu.bus.peek(0x8000), 0);
+}
+
+#[test]
+fn save_state_restores_banks_cycles_irq_and_controllers() {
+    let mut nes = namco108();
+    for _ in 0..100 {
+        nes.clock().unwrap();
+    }
+    // A held and latched, with one bit already read
+    nes.cpu.bus.controllers[0].set_button(Button::A, true);
+    nes.cpu.bus.write(0x4016, 1);
+    nes.cpu.bus.write(0x4016, 0);
+    nes.cpu.bus.read(0x4016);
+    let saved = nes.save_state();
+    let cycles = nes.cpu.total_cycles;
+
+    nes.cpu.bus.write(0x8000, 7);
+    nes.cpu.bus.write(0x8001, 6);
+    nes.cpu.bus.irq.assert(IrqSource::External);
+    nes.cpu.bus.controllers[0].set_button(Button::A, false);
+    nes.cpu.bus.write(0x4016, 1);
+    nes.clock().unwrap();
+    assert_eq!(nes.cpu.bus.peek(0xA000), 6);
+
+    nes.load_state(&saved).unwrap();
+    assert_eq!(nes.cpu.bus.peek(0xA000), 1);
+    assert_eq!(nes.cpu.total_cycles, cycles);
+    assert!(!nes.cpu.bus.irq.is_asserted());
+    assert!(nes.cpu.bus.controllers[0].is_pressed(Button::A));
+    // the shift register carries on past the bit already read
+    assert_eq!(nes.cpu.bus.controllers[0].peek(), 0);
+
+    // a state from a board that saves nothing doesn't fit this one
+    let mut plain = Nes::builder().build();
+    plain.load(&[0x4C, 0x00, 0x06]).unwrap();
+    assert!(nes.load_state(&plain.save_state()).is_err());
+    assert_eq!(nes.cpu.bus.peek(0xA000), 1);
+}
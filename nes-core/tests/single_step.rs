@@ -0,0 +1,150 @@
+//! Runs Tom Harte's single-step 6502 test vectors against every official
+//! opcode.
+//!
+//! Build with `--features single-step-tests`. The vectors (one
+//! `<opcode>.json` per opcode, from the `nes6502/v1` directory of
+//! SingleStepTests/65x02) are not in the repository; put them in
+//! `tests/roms/single-step` or point `SINGLE_STEP_DIR` at them. Without
+//! them the test is skipped. The plain `6502` set won't pass: it expects
+//! decimal-mode ADC and SBC, which the NES's 2A03 doesn't have.
+//!
+//! Each case checks the registers and memory after the instruction, the
+//! cycle count, and that the bus accesses the CPU makes happen in the same
+//! order as on hardware. Instructions run whole rather than cycle by
+//! cycle, so the dummy reads and writes of the real chip are not expected.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use nes_core::bus::Bus;
+use nes_core::constants::{Status, OPCODES};
+use nes_core::cpu::CPU;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Case {
+    name: String,
+    initial: State,
+    #[serde(rename = "final")]
+    expected: State,
+    cycles: Vec<(u16, u8, String)>,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct State {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+struct Access {
+    addr: u16,
+    value: u8,
+    write: bool,
+}
+
+fn vectors_dir() -> PathBuf {
+    env::var_os("SINGLE_STEP_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms/single-step"))
+}
+
+// runs one case, describing the first mismatch
+fn run(case: &Case) -> Result<(), String> {
+    let mut cpu = CPU::new(Bus::new());
+    let initial = &case.initial;
+    cpu.program_counter = initial.pc;
+    cpu.stack_pointer = initial.s;
+    cpu.a = initial.a;
+    cpu.x = initial.x;
+    cpu.y = initial.y;
+    cpu.status = Status::from_byte(initial.p);
+    for &(addr, value) in &initial.ram {
+        cpu.bus.ram[addr as usize] = value;
+    }
+
+    let accesses = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&accesses);
+    cpu.hooks.on_read(0x0000..=0xFFFF, move |addr, value| {
+        log.lock().unwrap().push(Access { addr, value, write: false })
+    });
+    let log = Arc::clone(&accesses);
+    cpu.hooks.on_write(0x0000..=0xFFFF, move |addr, value| {
+        log.lock().unwrap().push(Access { addr, value, write: true })
+    });
+
+    cpu.clock().map_err(|e| e.to_string())?;
+    let cycles = cpu.cycles as usize + 1;
+    while cpu.cycles > 0 {
+        cpu.clock().map_err(|e| e.to_string())?;
+    }
+
+    let ram = case.expected.ram.iter().map(|&(addr, _)| (addr, cpu.bus.ram[addr as usize])).collect();
+    let actual = State {
+        pc: cpu.program_counter,
+        s: cpu.stack_pointer,
+        a: cpu.a,
+        x: cpu.x,
+        y: cpu.y,
+        p: cpu.status.to_byte(),
+        ram,
+    };
+    if actual != case.expected {
+        return Err(format!("expected {:?}\n  actual {:?}", case.expected, actual));
+    }
+
+    if cycles != case.cycles.len() {
+        return Err(format!("took {} cycles, expected {}", cycles, case.cycles.len()));
+    }
+
+    // every access has to appear, in order, among the hardware's
+    let mut expected = case.cycles.iter().map(|(addr, value, kind)| Access {
+        addr: *addr,
+        value: *value,
+        write: kind == "write",
+    });
+    let accesses = accesses.lock().unwrap();
+    for access in accesses.iter() {
+        if !expected.any(|e| e == *access) {
+            return Err(format!("bus access {:?} out of order; hardware did {:?}", access, case.cycles));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn official_opcodes_match_single_step_vectors() {
+    let dir = vectors_dir();
+    if !dir.is_dir() {
+        eprintln!("skipping: no single-step vectors in {}", dir.display());
+        return;
+    }
+
+    let mut failures = Vec::new();
+    let mut opcodes: Vec<u8> = OPCODES.iter().map(|op| op.opcode).collect();
+    opcodes.sort();
+
+    for opcode in opcodes {
+        let path = dir.join(format!("{:02x}.json", opcode));
+        let Ok(json) = fs::read_to_string(&path) else {
+            eprintln!("no vectors for ${:02X}", opcode);
+            continue;
+        };
+        let cases: Vec<Case> = serde_json::from_str(&json).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+
+        // the first failing case is enough to go on per opcode
+        if let Some((case, error)) = cases.iter().find_map(|case| run(case).err().map(|error| (case, error))) {
+            failures.push(format!("${:02X} '{}': {}", opcode, case.name, error));
+        }
+    }
+
+    assert!(failures.is_empty(), "{} opcodes failed:\n{}", failures.len(), failures.join("\n"));
+}
@@ -0,0 +1,161 @@
+#ifndef NES_FFI_H
+#define NES_FFI_H
+
+/* Generated by cbindgen from nes-ffi; don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define NES_SCREEN_WIDTH 32
+
+#define NES_SCREEN_HEIGHT 32
+
+#define NES_REGION_NTSC 0
+
+#define NES_REGION_PAL 1
+
+#define NES_REGION_DENDY 2
+
+/**
+ * Framebuffer layouts for [`nes_set_pixel_format`]. The 8888 formats
+ * give the byte order in memory, with alpha always 0xFF; RGB565 pixels
+ * are native-endian `uint16_t`s.
+ */
+#define NES_PIXEL_FORMAT_RGB24 0
+
+#define NES_PIXEL_FORMAT_RGBA8888 1
+
+#define NES_PIXEL_FORMAT_BGRA8888 2
+
+#define NES_PIXEL_FORMAT_RGB565 3
+
+/**
+ * Controller button bits for [`nes_set_buttons`].
+ */
+#define NES_BUTTON_A 1
+
+#define NES_BUTTON_B 2
+
+#define NES_BUTTON_SELECT 4
+
+#define NES_BUTTON_START 8
+
+#define NES_BUTTON_UP 16
+
+#define NES_BUTTON_DOWN 32
+
+#define NES_BUTTON_LEFT 64
+
+#define NES_BUTTON_RIGHT 128
+
+/**
+ * An emulator instance, opaque to C.
+ */
+typedef struct NesEmu NesEmu;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an emulator for one of the `NES_REGION_*` TV systems, or returns
+ * NULL for an unknown one. ROMs switch it to the region in their header.
+ */
+NesEmu *nes_create(uint32_t region);
+
+void nes_destroy(NesEmu *emu);
+
+/**
+ * Inserts an iNES or NES 2.0 image and powers on into it, in the region
+ * its header names.
+ */
+int32_t nes_load_rom(NesEmu *emu, const uint8_t *data, size_t size);
+
+/**
+ * Loads a raw 6502 program at `$0600` and resets into it.
+ */
+int32_t nes_load_program(NesEmu *emu, const uint8_t *data, size_t size);
+
+/**
+ * Presses the reset button; only the CPU restarts.
+ */
+void nes_reset(NesEmu *emu);
+
+/**
+ * Switches the console off and on again; RAM and the cartridge start
+ * over too.
+ */
+void nes_power_cycle(NesEmu *emu);
+
+/**
+ * Runs one frame and redraws the framebuffer.
+ */
+int32_t nes_run_frame(NesEmu *emu);
+
+/**
+ * The screen as `NES_SCREEN_WIDTH` x `NES_SCREEN_HEIGHT` pixels, row by
+ * row, in the format set by [`nes_set_pixel_format`]. Cartridges have no
+ * PPU to draw with yet, so it shows the playground screen at `$0200`.
+ * Valid until the next call on `emu`.
+ */
+const uint8_t *nes_framebuffer(const NesEmu *emu);
+
+/**
+ * The size of [`nes_framebuffer`] in bytes.
+ */
+size_t nes_framebuffer_size(const NesEmu *emu);
+
+/**
+ * Has the framebuffer drawn in one of the `NES_PIXEL_FORMAT_*` layouts
+ * from now on, so the frontend can hand it to the display as it is;
+ * RGB24 until set. The screen is redrawn in the new format straight
+ * away. Returns -1 for an unknown format and leaves the old one.
+ */
+int32_t nes_set_pixel_format(NesEmu *emu, uint32_t format);
+
+/**
+ * One frame of interleaved stereo samples at [`nes_sample_rate`], with
+ * the number of sample pairs stored in `frames`. There is no APU yet, so
+ * it is silence, enough to keep audio-paced frontends going. Valid until
+ * the next call on `emu`.
+ */
+const int16_t *nes_audio(const NesEmu *emu, size_t *frames);
+
+uint32_t nes_sample_rate(const NesEmu *emu);
+
+/**
+ * Frames per second of the current region, to pace `nes_run_frame` by.
+ */
+double nes_frame_rate(const NesEmu *emu);
+
+/**
+ * Holds exactly the `NES_BUTTON_*` bits in `buttons` on controller
+ * `player`, 0 or 1; other players are ignored.
+ */
+void nes_set_buttons(NesEmu *emu, uint32_t player, uint8_t buttons);
+
+/**
+ * Reads a byte without the side effects a CPU read would have.
+ */
+uint8_t nes_peek(const NesEmu *emu, uint16_t addr);
+
+/**
+ * Writes a byte as the CPU would.
+ */
+void nes_poke(NesEmu *emu, uint16_t addr, uint8_t value);
+
+/**
+ * Why the last call that returned -1 failed, as a NUL-terminated string
+ * owned by `emu`; empty if nothing has failed. Valid until the next call
+ * on `emu`.
+ */
+const char *nes_last_error(const NesEmu *emu);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* NES_FFI_H */
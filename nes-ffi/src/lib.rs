@@ -0,0 +1,272 @@
+//! C API for nes-core, so frontends written in other languages can link
+//! against the emulator. `include/nes_ffi.h` declares it.
+//!
+//! Every function takes the handle from [`nes_create`], which is owned by
+//! the caller until passed to [`nes_destroy`]. Functions that can fail
+//! return 0 on success and -1 on failure, with the reason left in
+//! [`nes_last_error`]. A handle must only be used from one thread at a
+//! time.
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{c_char, CString};
+use std::ptr;
+use std::slice;
+
+use nes_core::constants::Region;
+use nes_core::error::EmuError;
+use nes_core::nes::Nes;
+
+pub const NES_SCREEN_WIDTH: u32 = 32;
+pub const NES_SCREEN_HEIGHT: u32 = 32;
+
+pub const NES_REGION_NTSC: u32 = 0;
+pub const NES_REGION_PAL: u32 = 1;
+pub const NES_REGION_DENDY: u32 = 2;
+
+/// Framebuffer layouts for [`nes_set_pixel_format`]. The 8888 formats
+/// give the byte order in memory, with alpha always 0xFF; RGB565 pixels
+/// are native-endian `uint16_t`s.
+pub const NES_PIXEL_FORMAT_RGB24: u32 = 0;
+pub const NES_PIXEL_FORMAT_RGBA8888: u32 = 1;
+pub const NES_PIXEL_FORMAT_BGRA8888: u32 = 2;
+pub const NES_PIXEL_FORMAT_RGB565: u32 = 3;
+
+/// Controller button bits for [`nes_set_buttons`].
+pub const NES_BUTTON_A: u8 = 0x01;
+pub const NES_BUTTON_B: u8 = 0x02;
+pub const NES_BUTTON_SELECT: u8 = 0x04;
+pub const NES_BUTTON_START: u8 = 0x08;
+pub const NES_BUTTON_UP: u8 = 0x10;
+pub const NES_BUTTON_DOWN: u8 = 0x20;
+pub const NES_BUTTON_LEFT: u8 = 0x40;
+pub const NES_BUTTON_RIGHT: u8 = 0x80;
+
+const SCREEN_SIZE: usize = (NES_SCREEN_WIDTH * NES_SCREEN_HEIGHT) as usize;
+
+/// An emulator instance, opaque to C.
+pub struct NesEmu {
+    nes: Nes,
+    // in `format`, refreshed by each frame
+    video: Vec<u8>,
+    format: u32,
+    // interleaved stereo, one frame's worth
+    audio: Vec<i16>,
+    error: CString,
+}
+
+impl NesEmu {
+    fn fail(&mut self, error: EmuError) -> i32 {
+        // messages never contain NUL, but don't panic across the boundary if one does
+        self.error = CString::new(error.to_string()).unwrap_or_default();
+        -1
+    }
+
+    fn status(&mut self, result: Result<(), EmuError>) -> i32 {
+        match result {
+            Ok(()) => 0,
+            Err(e) => self.fail(e),
+        }
+    }
+
+    // one frame of silence at the current region's frame rate
+    fn resize_audio(&mut self) {
+        let samples_per_frame = (self.nes.sample_rate() as f64 / self.nes.region().frame_rate()).ceil() as usize;
+        self.audio = vec![0; samples_per_frame * 2];
+    }
+
+    fn draw(&mut self) {
+        let size = bytes_per_pixel(self.format);
+        for (i, pixel) in self.video.chunks_exact_mut(size).enumerate() {
+            let [r, g, b] = color(self.nes.cpu.peek(0x0200 + i as u16));
+            match self.format {
+                NES_PIXEL_FORMAT_RGBA8888 => pixel.copy_from_slice(&[r, g, b, 0xFF]),
+                NES_PIXEL_FORMAT_BGRA8888 => pixel.copy_from_slice(&[b, g, r, 0xFF]),
+                NES_PIXEL_FORMAT_RGB565 => {
+                    let rgb565 = (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3;
+                    pixel.copy_from_slice(&rgb565.to_ne_bytes());
+                },
+                _ => pixel.copy_from_slice(&[r, g, b]),
+            }
+        }
+    }
+}
+
+fn bytes_per_pixel(format: u32) -> usize {
+    match format {
+        NES_PIXEL_FORMAT_RGBA8888 | NES_PIXEL_FORMAT_BGRA8888 => 4,
+        NES_PIXEL_FORMAT_RGB565 => 2,
+        _ => 3,
+    }
+}
+
+fn color(byte: u8) -> [u8; 3] {
+    match byte {
+        0 => [30, 30, 46],
+        1 => [205, 214, 244],
+        2 | 9 => [108, 112, 134],
+        3 | 10 => [243, 139, 168],
+        4 | 11 => [166, 227, 161],
+        5 | 12 => [137, 180, 250],
+        6 | 13 => [245, 194, 231],
+        7 | 14 => [249, 226, 175],
+        _ => [137, 220, 235],
+    }
+}
+
+/// Creates an emulator for one of the `NES_REGION_*` TV systems, or returns
+/// NULL for an unknown one. ROMs switch it to the region in their header.
+#[no_mangle]
+pub extern "C" fn nes_create(region: u32) -> *mut NesEmu {
+    let region = match region {
+        NES_REGION_NTSC => Region::Ntsc,
+        NES_REGION_PAL => Region::Pal,
+        NES_REGION_DENDY => Region::Dendy,
+        _ => return ptr::null_mut(),
+    };
+
+    let mut emu = NesEmu {
+        nes: Nes::builder().region(region).build(),
+        video: vec![0; SCREEN_SIZE * 3],
+        format: NES_PIXEL_FORMAT_RGB24,
+        audio: Vec::new(),
+        error: CString::default(),
+    };
+    emu.resize_audio();
+    Box::into_raw(Box::new(emu))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nes_destroy(emu: *mut NesEmu) {
+    if !emu.is_null() {
+        drop(Box::from_raw(emu));
+    }
+}
+
+/// Inserts an iNES or NES 2.0 image and powers on into it, in the region
+/// its header names.
+#[no_mangle]
+pub unsafe extern "C" fn nes_load_rom(emu: *mut NesEmu, data: *const u8, size: usize) -> i32 {
+    let emu = &mut *emu;
+    let rom = slice::from_raw_parts(data, size);
+    let result = emu.nes.load_rom(rom);
+    emu.resize_audio();
+    emu.status(result)
+}
+
+/// Loads a raw 6502 program at `$0600` and resets into it.
+#[no_mangle]
+pub unsafe extern "C" fn nes_load_program(emu: *mut NesEmu, data: *const u8, size: usize) -> i32 {
+    let emu = &mut *emu;
+    let program = slice::from_raw_parts(data, size);
+    let result = emu.nes.load(program);
+    emu.status(result)
+}
+
+/// Presses the reset button; only the CPU restarts.
+#[no_mangle]
+pub unsafe extern "C" fn nes_reset(emu: *mut NesEmu) {
+    (*emu).nes.reset();
+}
+
+/// Switches the console off and on again; RAM and the cartridge start
+/// over too.
+#[no_mangle]
+pub unsafe extern "C" fn nes_power_cycle(emu: *mut NesEmu) {
+    (*emu).nes.power_cycle();
+}
+
+/// Runs one frame and redraws the framebuffer.
+#[no_mangle]
+pub unsafe extern "C" fn nes_run_frame(emu: *mut NesEmu) -> i32 {
+    let emu = &mut *emu;
+    let result = emu.nes.run_frame();
+    emu.draw();
+    emu.status(result)
+}
+
+/// The screen as `NES_SCREEN_WIDTH` x `NES_SCREEN_HEIGHT` pixels, row by
+/// row, in the format set by [`nes_set_pixel_format`]. Cartridges have no
+/// PPU to draw with yet, so it shows the playground screen at `$0200`.
+/// Valid until the next call on `emu`.
+#[no_mangle]
+pub unsafe extern "C" fn nes_framebuffer(emu: *const NesEmu) -> *const u8 {
+    (*emu).video.as_ptr()
+}
+
+/// The size of [`nes_framebuffer`] in bytes.
+#[no_mangle]
+pub unsafe extern "C" fn nes_framebuffer_size(emu: *const NesEmu) -> usize {
+    (*emu).video.len()
+}
+
+/// Has the framebuffer drawn in one of the `NES_PIXEL_FORMAT_*` layouts
+/// from now on, so the frontend can hand it to the display as it is;
+/// RGB24 until set. The screen is redrawn in the new format straight
+/// away. Returns -1 for an unknown format and leaves the old one.
+#[no_mangle]
+pub unsafe extern "C" fn nes_set_pixel_format(emu: *mut NesEmu, format: u32) -> i32 {
+    let emu = &mut *emu;
+    if format > NES_PIXEL_FORMAT_RGB565 {
+        emu.error = CString::new(format!("unknown pixel format {}", format)).unwrap_or_default();
+        return -1;
+    }
+
+    emu.format = format;
+    emu.video = vec![0; SCREEN_SIZE * bytes_per_pixel(format)];
+    emu.draw();
+    0
+}
+
+/// One frame of interleaved stereo samples at [`nes_sample_rate`], with
+/// the number of sample pairs stored in `frames`. There is no APU yet, so
+/// it is silence, enough to keep audio-paced frontends going. Valid until
+/// the next call on `emu`.
+#[no_mangle]
+pub unsafe extern "C" fn nes_audio(emu: *const NesEmu, frames: *mut usize) -> *const i16 {
+    let emu = &*emu;
+    if !frames.is_null() {
+        *frames = emu.audio.len() / 2;
+    }
+    emu.audio.as_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nes_sample_rate(emu: *const NesEmu) -> u32 {
+    (*emu).nes.sample_rate()
+}
+
+/// Frames per second of the current region, to pace `nes_run_frame` by.
+#[no_mangle]
+pub unsafe extern "C" fn nes_frame_rate(emu: *const NesEmu) -> f64 {
+    (*emu).nes.region().frame_rate()
+}
+
+/// Holds exactly the `NES_BUTTON_*` bits in `buttons` on controller
+/// `player`, 0 or 1; other players are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn nes_set_buttons(emu: *mut NesEmu, player: u32, buttons: u8) {
+    if let Some(controller) = (*emu).nes.cpu.bus.controllers.get_mut(player as usize) {
+        controller.set_buttons(buttons);
+    }
+}
+
+/// Reads a byte without the side effects a CPU read would have.
+#[no_mangle]
+pub unsafe extern "C" fn nes_peek(emu: *const NesEmu, addr: u16) -> u8 {
+    (*emu).nes.cpu.peek(addr)
+}
+
+/// Writes a byte as the CPU would.
+#[no_mangle]
+pub unsafe extern "C" fn nes_poke(emu: *mut NesEmu, addr: u16, value: u8) {
+    (*emu).nes.cpu.write(addr, value);
+}
+
+/// Why the last call that returned -1 failed, as a NUL-terminated string
+/// owned by `emu`; empty if nothing has failed. Valid until the next call
+/// on `emu`.
+#[no_mangle]
+pub unsafe extern "C" fn nes_last_error(emu: *const NesEmu) -> *const c_char {
+    (*emu).error.as_ptr()
+}
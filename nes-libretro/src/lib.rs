@@ -0,0 +1,95 @@
+//! libretro core for nes-core, so the emulator can be loaded by RetroArch.
+//!
+//! Content is a raw 6502 program loaded at `$0600`, the same convention as
+//! the SDL frontend: the 32x32 screen lives at `$0200`, the last key press
+//! at `$FF` and a random byte at `$FE`.
+
+#![allow(clippy::missing_safety_doc)]
+
+#[cfg(feature = "libretro")]
+mod api;
+
+use nes_core::nes::Nes;
+
+pub const SCREEN_WIDTH: u32 = 32;
+pub const SCREEN_HEIGHT: u32 = 32;
+
+/// The emulator plus the buffers handed to the libretro frontend.
+pub struct Core {
+    pub nes: Nes,
+    pub video: Vec<u32>,
+    pub audio: Vec<i16>,
+    rng: u32,
+}
+
+impl Core {
+    pub fn new(nes: Nes) -> Core {
+        let samples_per_frame = (nes.sample_rate() as f64 / nes.region().frame_rate()).ceil() as usize;
+
+        Core {
+            nes,
+            video: vec![0; (SCREEN_WIDTH * SCREEN_HEIGHT) as usize],
+            audio: vec![0; samples_per_frame * 2],
+            rng: 0x2545_F491,
+        }
+    }
+
+    // xorshift keeps $FE reproducible across runs, which netplay relies on
+    fn next_random(&mut self) -> u8 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng % 15) as u8 + 1
+    }
+
+    pub fn press(&mut self, direction: Direction) {
+        let code = match direction {
+            Direction::Up => 0x77,
+            Direction::Down => 0x73,
+            Direction::Left => 0x61,
+            Direction::Right => 0x64,
+        };
+
+        self.nes.cpu.write(0xFF, code);
+    }
+
+    pub fn run_frame(&mut self) {
+        if self.nes.cpu.complete {
+            return;
+        }
+
+        let random = self.next_random();
+        self.nes.cpu.write(0xFE, random);
+
+        // a jammed program just freezes the picture; there is no way to
+        // report errors through retro_run
+        let _ = self.nes.run_frame();
+
+        for (i, pixel) in self.video.iter_mut().enumerate() {
+            let [r, g, b] = color(self.nes.cpu.peek(0x0200 + i as u16));
+            *pixel = (r as u32) << 16 | (g as u32) << 8 | b as u32;
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+fn color(byte: u8) -> [u8; 3] {
+    match byte {
+        0 => [30, 30, 46],
+        1 => [205, 214, 244],
+        2 | 9 => [108, 112, 134],
+        3 | 10 => [243, 139, 168],
+        4 | 11 => [166, 227, 161],
+        5 | 12 => [137, 180, 250],
+        6 | 13 => [245, 194, 231],
+        7 | 14 => [249, 226, 175],
+        _ => [137, 220, 235],
+    }
+}
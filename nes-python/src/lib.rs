@@ -0,0 +1,160 @@
+//! Python bindings for nes-core, for scripting the emulator from Python,
+//! e.g. to train agents on a game.
+//!
+//! Build a wheel with `maturin build --release -m nes-python/Cargo.toml`,
+//! then:
+//!
+//! ```python
+//! import nes_emu
+//!
+//! nes = nes_emu.Nes()
+//! nes.load_rom(open("game.nes", "rb").read())
+//! nes.set_buttons(0, nes_emu.BUTTON_START)
+//! nes.run_frame()
+//! pixels = nes.framebuffer()
+//! ```
+
+use nes_core::constants::Region;
+use nes_core::controller::Button;
+use nes_core::error::EmuError;
+use nes_core::nes::Nes as Console;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+const SCREEN_START: u16 = 0x0200;
+const SCREEN_SIZE: usize = 32 * 32;
+
+fn color(byte: u8) -> [u8; 3] {
+    match byte {
+        0 => [30, 30, 46],
+        1 => [205, 214, 244],
+        2 | 9 => [108, 112, 134],
+        3 | 10 => [243, 139, 168],
+        4 | 11 => [166, 227, 161],
+        5 | 12 => [137, 180, 250],
+        6 | 13 => [245, 194, 231],
+        7 | 14 => [249, 226, 175],
+        _ => [137, 220, 235],
+    }
+}
+
+fn error(e: EmuError) -> PyErr {
+    PyRuntimeError::new_err(e.to_string())
+}
+
+/// A console. Cartridges have no PPU to draw with yet, so the framebuffer
+/// is the 32x32 playground screen at `$0200`, as in the other frontends.
+///
+/// Hooks and mappers are `Send` but not `Sync`, so a console stays on the
+/// Python thread that made it.
+#[pyclass(unsendable)]
+pub struct Nes {
+    nes: Console,
+}
+
+#[pymethods]
+impl Nes {
+    /// `region` is "ntsc", "pal" or "dendy"; ROMs switch to their own.
+    #[new]
+    #[pyo3(signature = (region = "ntsc"))]
+    fn new(region: &str) -> PyResult<Nes> {
+        let region = match region.to_ascii_lowercase().as_str() {
+            "ntsc" => Region::Ntsc,
+            "pal" => Region::Pal,
+            "dendy" => Region::Dendy,
+            _ => return Err(PyValueError::new_err(format!("unknown region '{}'", region))),
+        };
+
+        Ok(Nes { nes: Console::builder().region(region).build() })
+    }
+
+    /// Inserts an iNES or NES 2.0 image and powers on into it.
+    fn load_rom(&mut self, rom: &[u8]) -> PyResult<()> {
+        self.nes.load_rom(rom).map_err(error)
+    }
+
+    /// Loads a raw 6502 program at `$0600` and resets into it.
+    fn load(&mut self, program: &[u8]) -> PyResult<()> {
+        self.nes.load(program).map_err(error)
+    }
+
+    /// Presses the reset button; only the CPU restarts.
+    fn reset(&mut self) {
+        self.nes.reset();
+    }
+
+    /// Switches the console off and on again; RAM and the cartridge start
+    /// over too, unlike `reset`.
+    fn power_cycle(&mut self) {
+        self.nes.power_cycle();
+    }
+
+    fn run_frame(&mut self) -> PyResult<()> {
+        self.nes.run_frame().map_err(error)
+    }
+
+    /// Frames completed since the console was built.
+    #[getter]
+    fn frame_count(&self) -> u64 {
+        self.nes.frame_count()
+    }
+
+    /// Whether a raw program has run to its end.
+    #[getter]
+    fn complete(&self) -> bool {
+        self.nes.cpu.complete
+    }
+
+    /// The screen as 32x32 RGB24 bytes, row by row.
+    fn framebuffer<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let mut rgb = Vec::with_capacity(SCREEN_SIZE * 3);
+        for i in 0..SCREEN_SIZE {
+            rgb.extend_from_slice(&color(self.nes.cpu.peek(SCREEN_START + i as u16)));
+        }
+        PyBytes::new(py, &rgb)
+    }
+
+    /// Reads a byte without the side effects a CPU read would have.
+    fn peek(&self, addr: u16) -> u8 {
+        self.nes.cpu.peek(addr)
+    }
+
+    /// Writes a byte as the CPU would.
+    fn poke(&mut self, addr: u16, value: u8) {
+        self.nes.cpu.write(addr, value);
+    }
+
+    /// The 2KB of internal RAM.
+    fn ram<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.nes.cpu.bus.ram[..0x0800])
+    }
+
+    /// Holds exactly the buttons in `mask` on controller `player`, 0 or 1;
+    /// see the `BUTTON_*` constants.
+    fn set_buttons(&mut self, player: usize, mask: u8) -> PyResult<()> {
+        let controller = self.nes.cpu.bus.controllers
+            .get_mut(player)
+            .ok_or_else(|| PyValueError::new_err(format!("no controller {}", player)))?;
+        controller.set_buttons(mask);
+        Ok(())
+    }
+
+    fn save_state<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.nes.save_state())
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> PyResult<()> {
+        self.nes.load_state(state).map_err(error)
+    }
+}
+
+#[pymodule]
+fn nes_emu(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Nes>()?;
+    for button in Button::ALL {
+        let name = format!("BUTTON_{:?}", button).to_uppercase();
+        m.add(name.as_str(), button.mask())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! WebAssembly bindings used by the browser frontend in `www/`.
+//!
+//! Build with `wasm-pack build --target web nes-web`, then serve `nes-web/`
+//! and open `www/index.html`.
+
+use nes_core::nes::Nes;
+use wasm_bindgen::prelude::*;
+
+const SCREEN_START: u16 = 0x0200;
+const SCREEN_SIZE: usize = 32 * 32;
+
+fn color(byte: u8) -> [u8; 3] {
+    match byte {
+        0 => [30, 30, 46],
+        1 => [205, 214, 244],
+        2 | 9 => [108, 112, 134],
+        3 | 10 => [243, 139, 168],
+        4 | 11 => [166, 227, 161],
+        5 | 12 => [137, 180, 250],
+        6 | 13 => [245, 194, 231],
+        7 | 14 => [249, 226, 175],
+        _ => [137, 220, 235],
+    }
+}
+
+#[wasm_bindgen]
+pub struct WebNes {
+    nes: Nes,
+    rgba: Vec<u8>,
+}
+
+impl Default for WebNes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WebNes {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WebNes {
+        WebNes {
+            nes: Nes::builder().build(),
+            rgba: vec![0xFF; SCREEN_SIZE * 4],
+        }
+    }
+
+    /// Loads a 6502 program at `$0600` and resets into it.
+    pub fn load(&mut self, program: &[u8]) -> Result<(), JsError> {
+        self.nes = Nes::builder().build();
+        self.nes.load(program)?;
+        Ok(())
+    }
+
+    pub fn run_frame(&mut self, random: u8) -> Result<(), JsError> {
+        self.nes.cpu.write(0xFE, random);
+        self.nes.run_frame()?;
+        Ok(())
+    }
+
+    /// Forwards a `KeyboardEvent.key` to the program's input byte at `$FF`.
+    pub fn key_down(&mut self, key: &str) {
+        let code = match key {
+            "ArrowUp" | "w" => 0x77,
+            "ArrowDown" | "s" => 0x73,
+            "ArrowLeft" | "a" => 0x61,
+            "ArrowRight" | "d" => 0x64,
+            _ => return,
+        };
+
+        self.nes.cpu.write(0xFF, code);
+    }
+
+    pub fn complete(&self) -> bool {
+        self.nes.cpu.complete
+    }
+
+    /// The 32x32 screen as RGBA, ready for `ImageData`.
+    pub fn frame(&mut self) -> Vec<u8> {
+        for i in 0..SCREEN_SIZE {
+            let [r, g, b] = color(self.nes.cpu.peek(SCREEN_START + i as u16));
+            self.rgba[i * 4..i * 4 + 3].copy_from_slice(&[r, g, b]);
+        }
+
+        self.rgba.clone()
+    }
+}
@@ -0,0 +1,38 @@
+import init, { WebNes } from "../pkg/nes_web.js";
+
+await init();
+
+const canvas = document.getElementById("screen");
+const context = canvas.getContext("2d");
+const image = context.createImageData(32, 32);
+
+let nes = null;
+
+document.getElementById("program").addEventListener("change", async (event) => {
+    const file = event.target.files[0];
+    if (!file) {
+        return;
+    }
+
+    const program = new Uint8Array(await file.arrayBuffer());
+    nes = new WebNes();
+    nes.load(program);
+});
+
+window.addEventListener("keydown", (event) => {
+    if (nes) {
+        nes.key_down(event.key);
+    }
+});
+
+function frame() {
+    if (nes && !nes.complete()) {
+        nes.run_frame(1 + Math.floor(Math.random() * 15));
+        image.data.set(nes.frame());
+        context.putImageData(image, 0, 0);
+    }
+
+    requestAnimationFrame(frame);
+}
+
+requestAnimationFrame(frame);
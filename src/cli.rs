@@ -0,0 +1,458 @@
+#[cfg(feature = "remote")]
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use nes_core::constants::{Region, OPCODES};
+use nes_core::debugger::{Access, Watchpoint};
+use nes_core::expr::Expr;
+use nes_core::nes::RamPattern;
+
+use crate::clip::ClipFormat;
+use crate::filter::Filter;
+use crate::headless::Until;
+use crate::input::{Action, Input};
+use crate::pacing::Pacing;
+use crate::saves::SaveFlush;
+
+#[derive(Parser)]
+#[command(version, about = "A NES / 6502 emulator")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a ROM or raw program in a window (the snake demo if none is given).
+    Run {
+        rom: Option<PathBuf>,
+        #[command(flatten)]
+        emu: EmuArgs,
+        #[command(flatten)]
+        window: WindowArgs,
+    },
+    /// List recently opened ROMs.
+    Recent,
+    /// Print a disassembly of the program or PRG ROM.
+    Disasm {
+        rom: PathBuf,
+        /// Write a listing of the whole file that asm6 assembles back to
+        /// the same bytes, with labels, instead.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// FCEUX code/data log telling code from data for the listing;
+        /// defaults to the ROM's name with .cdl, if there is one.
+        #[arg(long)]
+        cdl: Option<PathBuf>,
+    },
+    /// Print the iNES header fields, the mapper's name and the CRC32 games
+    /// are known by.
+    Info {
+        rom: PathBuf,
+    },
+    /// Run headless and exit with a status, for scripts and CI.
+    Test {
+        rom: PathBuf,
+        #[command(flatten)]
+        emu: EmuArgs,
+        /// Give up after this many frames.
+        #[arg(long, default_value_t = 600)]
+        frames: u64,
+        /// Stop once memory matches, e.g. `0x6000=0x00`.
+        #[arg(long, value_parser = Until::parse)]
+        until: Option<Until>,
+        /// Run nestest.nes from `$C000` instead and compare each instruction
+        /// against this reference log.
+        #[arg(long, value_name = "LOG")]
+        nestest: Option<PathBuf>,
+        /// Run from power on in lockstep with a trace from another
+        /// emulator, e.g. Mesen, and stop at the first instruction whose
+        /// registers, flags or cycle count differ.
+        #[arg(long, value_name = "TRACE", conflicts_with = "nestest")]
+        lockstep: Option<PathBuf>,
+    },
+    /// Run headless as fast as possible and report emulated frames and
+    /// CPU cycles per second.
+    Bench {
+        rom: PathBuf,
+        #[command(flatten)]
+        emu: EmuArgs,
+        #[arg(long, default_value_t = 3600)]
+        frames: u64,
+    },
+    /// Run blargg-style test ROMs headless and report each result.
+    /// Directories are searched for .nes files.
+    Blargg {
+        #[arg(required = true)]
+        roms: Vec<PathBuf>,
+        #[command(flatten)]
+        emu: EmuArgs,
+        /// Give up on a ROM after this many frames.
+        #[arg(long, default_value_t = 3600)]
+        frames: u64,
+    },
+    /// Check the screen each ROM in a goldens file ends on against its
+    /// stored hash.
+    Regress {
+        goldens: PathBuf,
+        #[command(flatten)]
+        emu: EmuArgs,
+        /// Store the hashes seen instead of failing on a mismatch.
+        #[arg(long)]
+        update: bool,
+    },
+    /// Run a directory of test ROMs headless, each passing by its rule in
+    /// suite.txt (blargg's status byte by default), and summarize.
+    Suite {
+        dir: PathBuf,
+        /// Rules file to use instead of the directory's suite.txt.
+        #[arg(long)]
+        rules: Option<PathBuf>,
+        #[command(flatten)]
+        emu: EmuArgs,
+        /// Give up on a ROM after this many frames, unless its rule says.
+        #[arg(long, default_value_t = 3600)]
+        frames: u64,
+        /// Also write the results to this file as CSV.
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    /// Replay an FCEUX .fm2 input movie.
+    PlayMovie {
+        rom: PathBuf,
+        movie: PathBuf,
+        #[command(flatten)]
+        emu: EmuArgs,
+        #[command(flatten)]
+        window: WindowArgs,
+    },
+}
+
+/// Options for building the emulator.
+#[derive(Args)]
+pub struct EmuArgs {
+    /// TV system; defaults to the one in the ROM header.
+    #[arg(long, value_enum)]
+    pub region: Option<RegionArg>,
+    /// A .pal file to draw the screen with.
+    #[arg(long)]
+    pub palette: Option<PathBuf>,
+    /// Stop before the instruction at this address; repeatable. P resumes,
+    /// backslash steps one instruction, `]` steps over a JSR and `[` steps
+    /// out of the current subroutine. `ADDR:EXPR` only stops when the
+    /// expression holds there, e.g. `$C002:A == 0x20 && [0x00F0] > 5`.
+    #[arg(long = "break", value_name = "ADDR[:EXPR]", value_parser = parse_breakpoint)]
+    pub breakpoints: Vec<(u16, Option<Expr>)>,
+    /// Also stop on interrupts, unofficial opcodes, the start of vblank
+    /// or the cartridge asserting IRQ, e.g.
+    /// `--break-on nmi,irq,brk,unofficial,vblank,mapper-irq`.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub break_on: Vec<BreakOn>,
+    /// Stop after an instruction touches an address or range, e.g. `$2002`,
+    /// `$0300-$03FF` or `$4016:r`; `:r`/`:w` limit it to reads or writes.
+    /// Repeatable.
+    #[arg(long = "watch", value_name = "RANGE", value_parser = parse_watch)]
+    pub watchpoints: Vec<Watchpoint>,
+    /// Instructions to remember for stepping back with `;` while stopped
+    /// in the debugger; 0 turns it off. 10000 by default when --break,
+    /// --break-on or --watch is given, otherwise 0.
+    #[arg(long, value_name = "INSTRUCTIONS")]
+    pub history: Option<usize>,
+    /// Show an expression's value whenever emulation stops, e.g.
+    /// `[0x00F0] + X`; repeatable.
+    #[arg(long, value_name = "EXPR", value_parser = parse_expr)]
+    pub display: Vec<String>,
+    /// Show the --display expressions after every frame too.
+    #[arg(long)]
+    pub display_each_frame: bool,
+    /// Pin a byte of memory, e.g. `$075A:09` for a lives counter; both are
+    /// hex. Repeatable.
+    #[arg(long, value_name = "ADDR:VALUE", value_parser = parse_freeze)]
+    pub freeze: Vec<(u16, u8)>,
+    /// DIP switches for VS System games and the NWC timer in hex, switch 1
+    /// in bit 0.
+    #[arg(long, value_name = "HEX", value_parser = parse_byte, default_value = "0")]
+    pub dip: u8,
+    /// Internal RAM at power-on: a hex byte to fill it with, `striped`, or
+    /// `random[:SEED]` (seed 0 if none is given).
+    #[arg(long, value_name = "PATTERN", value_parser = parse_ram_pattern, default_value = "00")]
+    pub ram_pattern: RamPattern,
+    /// Start cartridge clocks at this time, in seconds since 1970, and
+    /// advance them with the frames emulated instead of following the
+    /// host's clock, so movies replay the same.
+    #[arg(long, value_name = "SECONDS")]
+    pub clock_start: Option<u64>,
+    /// Make runs bitwise repeatable for movies and comparisons: cartridge
+    /// clocks start at --clock-start (or 1970) instead of the host's time,
+    /// and the playground's random byte at `$FE` uses a fixed seed.
+    #[arg(long)]
+    pub deterministic: bool,
+    /// Give the CPU this many extra scanlines of time each frame during
+    /// vblank, to cut slowdown; 0 keeps console timing.
+    #[arg(long, value_name = "SCANLINES", default_value_t = 0)]
+    pub overclock: u16,
+    /// When to write battery saves: `change[:MS]` once the save has been
+    /// still for MS milliseconds (1000 by default), `every:SECONDS`, or
+    /// `exit` only.
+    #[arg(long, value_name = "POLICY", value_parser = SaveFlush::parse, default_value = "change")]
+    pub save_flush: SaveFlush,
+    /// Print the cycles spent per subroutine on exit.
+    #[arg(long)]
+    pub profile: bool,
+    /// Print how often each opcode ran on exit.
+    #[arg(long)]
+    pub opcode_stats: bool,
+    /// Log DMA, interrupts and bank switches with when they happened, and
+    /// write the newest of them to this file as JSON on exit.
+    #[arg(long, value_name = "PATH")]
+    pub events: Option<PathBuf>,
+    /// Write a CPU trace to this file (`-` for stdout), gzipped if it ends
+    /// in `.gz`.
+    #[arg(long)]
+    pub trace: Option<PathBuf>,
+    /// Only trace instructions in this PC range, e.g. `C000-C0FF`;
+    /// repeatable.
+    #[arg(long = "trace-range", value_name = "START-END", value_parser = parse_range)]
+    pub trace_ranges: Vec<RangeInclusive<u16>>,
+    /// Only trace instructions in this PRG bank; repeatable.
+    #[arg(long = "trace-bank", value_name = "BANK")]
+    pub trace_banks: Vec<u16>,
+    /// Only trace these instructions, e.g. `JSR,RTS`; repeatable.
+    #[arg(long = "trace-op", value_name = "MNEMONIC", value_delimiter = ',', value_parser = parse_mnemonic)]
+    pub trace_ops: Vec<String>,
+    /// Start a new numbered trace file every this many megabytes of trace.
+    #[arg(long, value_name = "MB")]
+    pub trace_split: Option<u64>,
+    /// Delete all but the newest this many split trace files.
+    #[arg(long, value_name = "FILES", requires = "trace_split")]
+    pub trace_keep: Option<usize>,
+    /// Print what the program writes to a debug console port, a line at a
+    /// time; the port is at `$4020` unless given, as `--debug-port=$401F`.
+    #[arg(
+        long,
+        value_name = "ADDR",
+        value_parser = parse_hex,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "4020"
+    )]
+    pub debug_port: Option<u16>,
+    /// Emulation speed, e.g. 0.5 or 4; `inf` runs uncapped. `-`/`=` step
+    /// it at runtime and Backspace restores 1x.
+    #[arg(long, default_value_t = 1.0)]
+    pub speed: f64,
+    /// Run an FCEUX-style Lua script alongside the game.
+    #[cfg(feature = "lua")]
+    #[arg(long)]
+    pub script: Option<PathBuf>,
+    /// Record video from the first frame with ffmpeg (F9 toggles it in a window).
+    #[arg(long)]
+    pub record: Option<PathBuf>,
+}
+
+/// Options for the window.
+#[derive(Args)]
+pub struct WindowArgs {
+    /// Window size as a multiple of the screen.
+    #[arg(long, default_value_t = 10)]
+    pub scale: u32,
+    /// Start fullscreen (F11 toggles it).
+    #[arg(long)]
+    pub fullscreen: bool,
+    /// Fill the window instead of scaling by whole multiples.
+    #[arg(long)]
+    pub stretch: bool,
+    /// Correct to the 8:7 pixel aspect ratio of a TV.
+    #[arg(long)]
+    pub aspect_correction: bool,
+    /// Upscaler applied before presenting (F7 cycles through them).
+    #[arg(long, value_enum, default_value_t = Filter::Nearest)]
+    pub filter: Filter,
+    /// Rows to crop from the top and bottom of screenshots (F12).
+    #[arg(long)]
+    pub overscan: Option<u32>,
+    /// Seconds of gameplay kept for clips (F10).
+    #[arg(long, default_value_t = 10)]
+    pub clip_seconds: u32,
+    #[arg(long, value_enum, default_value_t = ClipFormat::Gif)]
+    pub clip_format: ClipFormat,
+    /// What paces emulation: the display's vsync, or a timer at the
+    /// console's frame rate for displays that refresh at another rate.
+    #[arg(long, value_enum, default_value_t = Pacing::Vsync)]
+    pub pacing: Pacing,
+    /// Bind a key, or a gamepad button as `pad:BUTTON`, to an NES button
+    /// or hotkey, e.g. `--bind Space=A` or `--bind pad:leftshoulder=FastForward`;
+    /// repeatable. Names are the frontend's own, or gilrs' (`South`,
+    /// `DPadUp`, ...) for pads when built with the gamepad feature.
+    #[arg(long = "bind", value_name = "INPUT=ACTION", value_parser = parse_binding)]
+    pub bindings: Vec<(Input, Action)>,
+    /// How far an analog stick has to lean, from 0 to 1, before it presses
+    /// a direction on the D-pad.
+    #[cfg(feature = "gamepad")]
+    #[arg(long, default_value_t = 0.5)]
+    pub deadzone: f32,
+    /// Give the gamepad whose name contains NAME to player 1 or 2, e.g.
+    /// `--gamepad 2=8BitDo`; repeatable. Other pads take the players left
+    /// in the order they're plugged in.
+    #[cfg(feature = "gamepad")]
+    #[arg(long = "gamepad", value_name = "PLAYER=NAME", value_parser = parse_gamepad)]
+    pub gamepads: Vec<(usize, String)>,
+    /// Serve JSON-RPC over WebSocket at this address, e.g.
+    /// `127.0.0.1:4370`, so external tools and bots can pause the game,
+    /// read and write memory, set breakpoints and take screenshots.
+    #[cfg(feature = "remote")]
+    #[arg(long, value_name = "ADDR")]
+    pub remote: Option<SocketAddr>,
+    /// Record the input into an FCEUX .fm2 movie, written on exit,
+    /// starting from a power cycle; reset (F4) and power cycle (F5) are
+    /// recorded too.
+    #[arg(long, value_name = "PATH")]
+    pub record_movie: Option<PathBuf>,
+    /// Draw the buttons held on each controller at the bottom of the
+    /// screen (F8 toggles it).
+    #[arg(long)]
+    pub input_display: bool,
+    /// Graph how long each frame takes to emulate at the top of the screen
+    /// and print the frame rate every second (F6 toggles it).
+    #[arg(long)]
+    pub stats: bool,
+    /// Use the winit + pixels frontend instead of SDL, as builds without
+    /// the `sdl` feature always do.
+    #[cfg(feature = "winit")]
+    #[arg(long)]
+    pub winit: bool,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum RegionArg {
+    Ntsc,
+    Pal,
+    Dendy,
+}
+
+impl From<RegionArg> for Region {
+    fn from(region: RegionArg) -> Region {
+        match region {
+            RegionArg::Ntsc => Region::Ntsc,
+            RegionArg::Pal => Region::Pal,
+            RegionArg::Dendy => Region::Dendy,
+        }
+    }
+}
+
+/// Events `--break-on` can stop at.
+#[derive(Copy, Clone, ValueEnum)]
+pub enum BreakOn {
+    Nmi,
+    Irq,
+    Brk,
+    Unofficial,
+    Vblank,
+    MapperIrq,
+}
+
+/// Parses a 16-bit hex number, with or without a `0x` or `$` prefix.
+pub fn parse_hex(text: &str) -> Result<u16, String> {
+    let digits = text.trim_start_matches("0x").trim_start_matches('$');
+    u16::from_str_radix(digits, 16).map_err(|e| format!("bad hex number '{}': {}", text, e))
+}
+
+/// Checks that an expression parses, keeping its text.
+pub fn parse_expr(text: &str) -> Result<String, String> {
+    Expr::parse(text).map_err(|e| e.to_string())?;
+    Ok(text.to_string())
+}
+
+/// Parses `ADDR:VALUE`, both hex.
+pub fn parse_freeze(text: &str) -> Result<(u16, u8), String> {
+    let (addr, value) = text.split_once(':').ok_or_else(|| format!("expected ADDR:VALUE, got '{}'", text))?;
+    let value = u8::try_from(parse_hex(value)?).map_err(|_| format!("'{}' doesn't fit in a byte", value))?;
+    Ok((parse_hex(addr)?, value))
+}
+
+/// Parses a hex byte, as `--dip` and `--ram-pattern` take.
+pub fn parse_byte(text: &str) -> Result<u8, String> {
+    u8::try_from(parse_hex(text)?).map_err(|_| format!("'{}' doesn't fit in a byte", text))
+}
+
+/// Parses a `--ram-pattern`: a hex fill byte, `striped` or
+/// `random[:SEED]`.
+pub fn parse_ram_pattern(text: &str) -> Result<RamPattern, String> {
+    match text.split_once(':') {
+        _ if text == "striped" => Ok(RamPattern::Striped),
+        _ if text == "random" => Ok(RamPattern::Random(0)),
+        Some(("random", seed)) => {
+            let seed = seed.parse().map_err(|e| format!("bad seed '{}': {}", seed, e))?;
+            Ok(RamPattern::Random(seed))
+        },
+        _ => Ok(RamPattern::Fill(parse_byte(text)?)),
+    }
+}
+
+/// Parses `INPUT=ACTION`; splits at the last `=` so the `=` key can be
+/// bound too.
+pub fn parse_binding(text: &str) -> Result<(Input, Action), String> {
+    let (input, action) = text.rsplit_once('=').ok_or_else(|| format!("expected INPUT=ACTION, got '{}'", text))?;
+    Ok((Input::parse(input), Action::parse(action)?))
+}
+
+/// Parses `PLAYER=NAME` into a 0-based player and part of a gamepad's
+/// name.
+#[cfg(feature = "gamepad")]
+pub fn parse_gamepad(text: &str) -> Result<(usize, String), String> {
+    match text.split_once('=') {
+        Some(("1", name)) => Ok((0, name.to_string())),
+        Some(("2", name)) => Ok((1, name.to_string())),
+        _ => Err(format!("expected 1=NAME or 2=NAME, got '{}'", text)),
+    }
+}
+
+/// Parses `ADDR[:EXPR]` into a breakpoint and its condition.
+pub fn parse_breakpoint(text: &str) -> Result<(u16, Option<Expr>), String> {
+    match text.split_once(':') {
+        Some((pc, condition)) => {
+            let condition = Expr::parse(condition).map_err(|e| e.to_string())?;
+            Ok((parse_hex(pc)?, Some(condition)))
+        },
+        None => Ok((parse_hex(text)?, None)),
+    }
+}
+
+/// Parses `START[-END][:r|:w|:rw]` into a watchpoint; both are hex.
+pub fn parse_watch(text: &str) -> Result<Watchpoint, String> {
+    let (range, access) = match text.rsplit_once(':') {
+        Some((range, "r")) => (range, Access::Read),
+        Some((range, "w")) => (range, Access::Write),
+        Some((range, "rw")) => (range, Access::ReadWrite),
+        Some((_, access)) => return Err(format!("bad access '{}', expected r, w or rw", access)),
+        None => (text, Access::ReadWrite),
+    };
+
+    Ok(Watchpoint { range: parse_range(range)?, access })
+}
+
+/// Parses `START[-END]` in hex.
+pub fn parse_range(text: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = match text.split_once('-') {
+        Some((start, end)) => (parse_hex(start)?, parse_hex(end)?),
+        None => (parse_hex(text)?, parse_hex(text)?),
+    };
+
+    if start > end {
+        return Err(format!("empty range '{}'", text));
+    }
+
+    Ok(start..=end)
+}
+
+/// Parses an instruction mnemonic, ignoring case.
+pub fn parse_mnemonic(text: &str) -> Result<String, String> {
+    let name = text.to_ascii_uppercase();
+    if OPCODES.iter().any(|opcode| opcode.name == name) {
+        Ok(name)
+    } else {
+        Err(format!("unknown instruction '{}'", text))
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::game::{Game, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::screenshot;
+
+// clips are upscaled so viewers don't blur the 32x32 screen
+const CLIP_SCALE: u32 = 8;
+
+/// File formats a clip can be exported as.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ClipFormat {
+    Gif,
+    Apng,
+}
+
+impl ClipFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ClipFormat::Gif => "gif",
+            ClipFormat::Apng => "png",
+        }
+    }
+}
+
+/// The most recent frames, oldest first, as RGB24.
+pub struct FrameHistory {
+    frames: VecDeque<Vec<u8>>,
+    capacity: usize,
+}
+
+impl FrameHistory {
+    pub fn new(capacity: usize) -> FrameHistory {
+        FrameHistory {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, rgb: &[u8]) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        // reuse the oldest buffer once full
+        let mut frame = if self.frames.len() == self.capacity {
+            self.frames.pop_front().unwrap_or_default()
+        } else {
+            Vec::with_capacity(rgb.len())
+        };
+        frame.clear();
+        frame.extend_from_slice(rgb);
+        self.frames.push_back(frame);
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn write(&self, path: &Path, format: ClipFormat, frame_rate: f64) -> Result<(), String> {
+        if self.is_empty() {
+            return Err("no frames to export".to_string());
+        }
+
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let out = BufWriter::new(file);
+
+        match format {
+            ClipFormat::Gif => self.write_gif(out, frame_rate),
+            ClipFormat::Apng => self.write_apng(out, frame_rate),
+        }
+    }
+
+    fn write_gif(&self, out: BufWriter<File>, frame_rate: f64) -> Result<(), String> {
+        let (width, height) = (SCREEN_WIDTH * CLIP_SCALE, SCREEN_HEIGHT * CLIP_SCALE);
+        let mut encoder = gif::Encoder::new(out, width as u16, height as u16, &[])
+            .map_err(|e| e.to_string())?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(|e| e.to_string())?;
+
+        // GIF delays are in centiseconds and viewers slow down anything
+        // under 2, so frames are dropped to keep the clip at real speed
+        let mut shown = 0.0;
+        let mut pending: Option<Vec<u8>> = None;
+
+        for (i, rgb) in self.frames.iter().enumerate() {
+            let time = (i as f64 * 100.0 / frame_rate).round();
+            if pending.is_some() && time - shown < 2.0 {
+                continue;
+            }
+
+            if let Some(previous) = pending.take() {
+                write_gif_frame(&mut encoder, &previous, (time - shown) as u16)?;
+                shown = time;
+            }
+            pending = Some(upscale(rgb));
+        }
+
+        if let Some(last) = pending {
+            let delay = (100.0 / frame_rate).round().max(2.0) as u16;
+            write_gif_frame(&mut encoder, &last, delay)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_apng(&self, out: BufWriter<File>, frame_rate: f64) -> Result<(), String> {
+        let (width, height) = (SCREEN_WIDTH * CLIP_SCALE, SCREEN_HEIGHT * CLIP_SCALE);
+        let mut encoder = png::Encoder::new(out, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(self.frames.len() as u32, 0).map_err(|e| e.to_string())?;
+        // each frame lasts 1000 / (frame_rate * 1000) seconds
+        encoder
+            .set_frame_delay(1000, (frame_rate * 1000.0).round() as u16)
+            .map_err(|e| e.to_string())?;
+
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        for rgb in self.frames.iter() {
+            writer.write_image_data(&upscale(rgb)).map_err(|e| e.to_string())?;
+        }
+
+        writer.finish().map_err(|e| e.to_string())
+    }
+}
+
+fn write_gif_frame(encoder: &mut gif::Encoder<BufWriter<File>>, rgb: &[u8], delay: u16) -> Result<(), String> {
+    let (width, height) = (SCREEN_WIDTH * CLIP_SCALE, SCREEN_HEIGHT * CLIP_SCALE);
+    let mut frame = gif::Frame::from_rgb_speed(width as u16, height as u16, rgb, 10);
+    frame.delay = delay;
+    encoder.write_frame(&frame).map_err(|e| e.to_string())
+}
+
+fn upscale(rgb: &[u8]) -> Vec<u8> {
+    let width = SCREEN_WIDTH as usize;
+    let scale = CLIP_SCALE as usize;
+    let mut out = Vec::with_capacity(rgb.len() * scale * scale);
+
+    for row in rgb.chunks_exact(width * 3) {
+        let mut line = Vec::with_capacity(row.len() * scale);
+        for pixel in row.chunks_exact(3) {
+            for _ in 0..scale {
+                line.extend_from_slice(pixel);
+            }
+        }
+        for _ in 0..scale {
+            out.extend_from_slice(&line);
+        }
+    }
+
+    out
+}
+
+impl Game {
+    /// Keeps the last `frames` frames around for [`Game::export_clip`].
+    pub fn keep_history(&mut self, frames: usize) {
+        self.history = FrameHistory::new(frames);
+    }
+
+    pub fn export_clip(&self, path: &Path, format: ClipFormat) -> Result<(), String> {
+        self.history.write(path, format, self.nes.region().frame_rate())
+    }
+}
+
+/// Exports the frame history to a timestamped file and reports the result
+/// on the console.
+pub fn capture(game: &Game, name: &str, format: ClipFormat) {
+    let path = screenshot::timestamped_path(name, format.extension());
+    match game.export_clip(&path, format) {
+        Ok(()) => println!("saved {} ({} frames)", path.display(), game.history.len()),
+        Err(e) => eprintln!("clip export failed: {}", e),
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nes_core::cartridge::{self, Cartridge};
+use nes_core::palette::Palette;
+use serde::{Deserialize, Serialize};
+
+use crate::game::Game;
+use crate::input::{Action, Bindings, Input};
+use crate::settings::Settings;
+
+const MAX_RECENT: usize = 10;
+
+/// `~/.config/nes-emu` or the platform's equivalent.
+fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("nes-emu"))
+}
+
+/// Identifies a game by its contents: the PRG and CHR CRC-32 for iNES
+/// images, the whole file otherwise.
+pub fn rom_hash(data: &[u8]) -> String {
+    let crc = match Cartridge::from_ines(data) {
+        Ok(cartridge) => cartridge.crc32(),
+        Err(_) => cartridge::crc32(data),
+    };
+    format!("{:08X}", crc)
+}
+
+/// Most recently opened ROMs, newest first.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct RecentRoms {
+    pub roms: Vec<PathBuf>,
+}
+
+impl RecentRoms {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("recent.toml"))
+    }
+
+    pub fn load() -> RecentRoms {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    /// Moves `rom` to the front of the list and saves it.
+    pub fn add(rom: &Path) -> Result<(), String> {
+        let rom = rom.canonicalize().unwrap_or_else(|_| rom.to_path_buf());
+
+        let mut recent = RecentRoms::load();
+        recent.roms.retain(|path| *path != rom);
+        recent.roms.insert(0, rom);
+        recent.roms.truncate(MAX_RECENT);
+
+        match Self::path() {
+            Some(path) => write_toml(&path, &recent),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Per-game overrides, stored as `games/<hash>.toml` in the config
+/// directory. Anything left out falls back to the command line defaults.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct GameConfig {
+    /// A .pal file to draw with.
+    pub palette: Option<PathBuf>,
+    /// Rows cropped from screenshots.
+    pub overscan: Option<u32>,
+    /// Button or hotkey name (`A`, `Start`, `Coin1`, `SaveState`, ...) to
+    /// the key name the frontend reports, or `pad:BUTTON` for a gamepad,
+    /// e.g. `A = "Space"`.
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+}
+
+impl GameConfig {
+    fn path(hash: &str) -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("games").join(format!("{}.toml", hash)))
+    }
+
+    pub fn load(hash: &str) -> Result<GameConfig, String> {
+        let Some(path) = Self::path(hash) else {
+            return Ok(GameConfig::default());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(_) => Ok(GameConfig::default()),
+        }
+    }
+
+    /// The key bindings with the action names parsed.
+    pub fn bindings(&self) -> Result<Bindings, String> {
+        let mut bindings = Bindings::new();
+        for (action, input) in &self.keys {
+            bindings.bind(Input::parse(input), Action::parse(action)?);
+        }
+        Ok(bindings)
+    }
+}
+
+fn write_toml<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let text = toml::to_string_pretty(value).map_err(|e| e.to_string())?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Loads a dropped or picked file into a running frontend: switches the
+/// ROM, applies its per-game config and records it as recent.
+pub fn open(game: &mut Game, settings: &mut Settings, path: &Path) -> Result<(), String> {
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let config = GameConfig::load(&rom_hash(&data))?;
+    let bindings = config.bindings()?;
+
+    game.load_file(path)?;
+
+    if let Some(palette) = &config.palette {
+        let data = fs::read(palette).map_err(|e| format!("{}: {}", palette.display(), e))?;
+        game.set_palette(Some(Palette::from_pal(&data).map_err(|e| e.to_string())?));
+    }
+    if let Some(overscan) = config.overscan {
+        settings.overscan = overscan;
+    }
+    settings.game_bindings = bindings;
+    settings.title = crate::title(Some(path));
+
+    if let Err(e) = RecentRoms::add(path) {
+        eprintln!("failed to update recent ROMs: {}", e);
+    }
+
+    Ok(())
+}
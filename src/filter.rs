@@ -0,0 +1,184 @@
+use clap::ValueEnum;
+
+/// CPU-side upscalers applied to the screen before it is presented.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Filter {
+    /// Raw pixels; the GPU scales them up.
+    Nearest,
+    Scale2x,
+    Scale3x,
+    Hq2x,
+}
+
+impl Filter {
+    const ALL: [Filter; 4] = [Filter::Nearest, Filter::Scale2x, Filter::Scale3x, Filter::Hq2x];
+
+    pub fn factor(&self) -> u32 {
+        match self {
+            Filter::Nearest => 1,
+            Filter::Scale2x | Filter::Hq2x => 2,
+            Filter::Scale3x => 3,
+        }
+    }
+
+    /// The next filter in the runtime cycle.
+    pub fn next(self) -> Filter {
+        let i = Filter::ALL.iter().position(|&filter| filter == self).unwrap_or(0);
+        Filter::ALL[(i + 1) % Filter::ALL.len()]
+    }
+
+    /// Scales an RGB24 image of `width` x `height` into `out`.
+    pub fn apply(&self, src: &[u8], width: u32, height: u32, out: &mut Vec<u8>) {
+        let factor = self.factor() as usize;
+        out.clear();
+        out.resize(src.len() * factor * factor, 0);
+
+        let image = Image { src, width: width as usize, height: height as usize };
+        let out_width = width as usize * factor;
+
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let mut block = [[0; 3]; 9];
+                match self {
+                    Filter::Nearest => block[0] = image.pixel(x, y, 0, 0),
+                    Filter::Scale2x => block[..4].copy_from_slice(&scale2x(&image, x, y)),
+                    Filter::Scale3x => block.copy_from_slice(&scale3x(&image, x, y)),
+                    Filter::Hq2x => block[..4].copy_from_slice(&hq2x(&image, x, y)),
+                }
+
+                for (i, pixel) in block[..factor * factor].iter().enumerate() {
+                    let (out_x, out_y) = (x * factor + i % factor, y * factor + i / factor);
+                    let offset = (out_y * out_width + out_x) * 3;
+                    out[offset..offset + 3].copy_from_slice(pixel);
+                }
+            }
+        }
+    }
+}
+
+type Rgb = [u8; 3];
+
+struct Image<'a> {
+    src: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl Image<'_> {
+    // neighbors past the edge repeat the border pixel
+    fn pixel(&self, x: usize, y: usize, dx: isize, dy: isize) -> Rgb {
+        let x = (x as isize + dx).clamp(0, self.width as isize - 1) as usize;
+        let y = (y as isize + dy).clamp(0, self.height as isize - 1) as usize;
+        let i = (y * self.width + x) * 3;
+        [self.src[i], self.src[i + 1], self.src[i + 2]]
+    }
+
+    // the 3x3 neighborhood, row by row:
+    // A B C
+    // D E F
+    // G H I
+    fn neighborhood(&self, x: usize, y: usize) -> [Rgb; 9] {
+        let mut pixels = [[0; 3]; 9];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = self.pixel(x, y, i as isize % 3 - 1, i as isize / 3 - 1);
+        }
+        pixels
+    }
+}
+
+// AdvMAME2x / EPX
+fn scale2x(image: &Image, x: usize, y: usize) -> [Rgb; 4] {
+    let [_, b, _, d, e, f, _, h, _] = image.neighborhood(x, y);
+
+    if b != h && d != f {
+        [
+            if d == b { d } else { e },
+            if b == f { f } else { e },
+            if d == h { d } else { e },
+            if h == f { f } else { e },
+        ]
+    } else {
+        [e; 4]
+    }
+}
+
+// AdvMAME3x
+fn scale3x(image: &Image, x: usize, y: usize) -> [Rgb; 9] {
+    let [a, b, c, d, e, f, g, h, i] = image.neighborhood(x, y);
+
+    if b != h && d != f {
+        [
+            if d == b { d } else { e },
+            if (d == b && e != c) || (b == f && e != a) { b } else { e },
+            if b == f { f } else { e },
+            if (d == b && e != g) || (d == h && e != a) { d } else { e },
+            e,
+            if (b == f && e != i) || (h == f && e != c) { f } else { e },
+            if d == h { d } else { e },
+            if (d == h && e != i) || (h == f && e != g) { h } else { e },
+            if h == f { f } else { e },
+        ]
+    } else {
+        [e; 9]
+    }
+}
+
+// hq2x-style: the hqx YUV similarity test decides each corner, which is
+// then blended from the center and its neighbors. This is a compact
+// variant of the per-corner rules, not the full 256-case table.
+fn hq2x(image: &Image, x: usize, y: usize) -> [Rgb; 4] {
+    let [a, b, c, d, e, f, g, h, i] = image.neighborhood(x, y);
+
+    [
+        hq_corner(e, a, b, d),
+        hq_corner(e, c, b, f),
+        hq_corner(e, g, h, d),
+        hq_corner(e, i, h, f),
+    ]
+}
+
+// `diagonal` is the corner neighbor, `side1`/`side2` the two edge neighbors
+// that touch it
+fn hq_corner(e: Rgb, diagonal: Rgb, side1: Rgb, side2: Rgb) -> Rgb {
+    if similar(side1, side2) && !similar(e, side1) {
+        // an edge runs across the corner
+        if similar(e, diagonal) {
+            blend(&[(e, 2), (side1, 1), (side2, 1)])
+        } else {
+            blend(&[(e, 2), (side1, 3), (side2, 3)])
+        }
+    } else if !similar(e, diagonal) {
+        blend(&[(e, 3), (diagonal, 1)])
+    } else {
+        e
+    }
+}
+
+// the hqx thresholds on luma and chroma differences
+fn similar(p: Rgb, q: Rgb) -> bool {
+    let (y1, u1, v1) = yuv(p);
+    let (y2, u2, v2) = yuv(q);
+
+    (y1 - y2).abs() <= 48.0 && (u1 - u2).abs() <= 7.0 && (v1 - v2).abs() <= 6.0
+}
+
+fn yuv([r, g, b]: Rgb) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    (
+        0.299 * r + 0.587 * g + 0.114 * b,
+        -0.169 * r - 0.331 * g + 0.5 * b + 128.0,
+        0.5 * r - 0.419 * g - 0.081 * b + 128.0,
+    )
+}
+
+fn blend(weights: &[(Rgb, u32)]) -> Rgb {
+    let total: u32 = weights.iter().map(|(_, weight)| weight).sum();
+    let mut out = [0; 3];
+
+    for (channel, value) in out.iter_mut().enumerate() {
+        let sum: u32 = weights.iter().map(|(pixel, weight)| pixel[channel] as u32 * weight).sum();
+        *value = (sum / total) as u8;
+    }
+
+    out
+}
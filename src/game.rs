@@ -0,0 +1,677 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use nes_core::cartridge::Cartridge;
+use nes_core::controller::Button;
+use nes_core::cpu::CPU;
+use nes_core::debugger::FrameKind;
+use nes_core::error::EmuError;
+use nes_core::movie::Movie;
+use nes_core::nes::Nes;
+use nes_core::palette::Palette;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::clip::FrameHistory;
+use crate::overlay;
+use crate::stats::FrameStats;
+use crate::record::Recorder;
+use crate::saves::{SaveFile, SaveFlush};
+#[cfg(feature = "lua")]
+use crate::script::Script;
+
+pub const SCREEN_WIDTH: u32 = 32;
+pub const SCREEN_HEIGHT: u32 = 32;
+
+/// Speeds the speed hotkeys step through; the last one is uncapped.
+const SPEEDS: [f64; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, f64::INFINITY];
+
+// how long an uncapped tick may run before the frontend gets to present
+const UNCAPPED_TICK: Duration = Duration::from_millis(16);
+
+// events kept for --events; older ones are dropped
+const EVENT_LOG_SIZE: usize = 100_000;
+
+// the snake demo is tuned for a slow CPU and is unplayable at the real
+// 29780 cycles per frame
+const CYCLES_PER_FRAME: u32 = 300;
+
+fn color(byte: u8) -> [u8; 3] {
+    match byte {
+        0 => [30, 30, 46],
+        1 => [205, 214, 244],
+        2 | 9 => [108, 112, 134],
+        3 | 10 => [243, 139, 168],
+        4 | 11 => [166, 227, 161],
+        5 | 12 => [137, 180, 250],
+        6 | 13 => [245, 194, 231],
+        7 | 14 => [249, 226, 175],
+        _ => [137, 220, 235],
+    }
+}
+
+fn read_screen_state(cpu: &CPU, palette: Option<&Palette>, frame: &mut [u8; 32 * 3 * 32]) {
+    for (i, pixel) in frame.chunks_exact_mut(3).enumerate() {
+        let byte = cpu.peek(0x0200 + i as u16);
+        let rgb = match palette {
+            Some(palette) => palette.rgb(byte),
+            None => color(byte),
+        };
+        pixel.copy_from_slice(&rgb);
+    }
+}
+
+/// A program written for the 6502 playground conventions: screen memory at
+/// `$0200`, a random byte at `$FE` and the last key press at `$FF`. Shared
+/// by every frontend.
+///
+/// Cartridges have no PPU to draw with yet, so for them the same page is
+/// shown as a view of RAM.
+pub struct Game {
+    pub nes: Nes,
+    /// Print the debugger's watch expressions after every frame, not only
+    /// when emulation stops.
+    pub show_watches_each_frame: bool,
+    /// Draw both controllers' buttons over the bottom of the screen.
+    pub show_input: bool,
+    /// Graph frame times over the top of the screen and print the frame
+    /// rate every second.
+    pub show_stats: bool,
+    pub stats: FrameStats,
+    palette: Option<Palette>,
+    /// When the cartridge's battery-backed memory is written to disk.
+    pub save_flush: SaveFlush,
+    save: Option<SaveFile>,
+    movie: Option<(Movie, usize)>,
+    // where the movie being recorded goes
+    movie_out: Option<PathBuf>,
+    // where the event log goes on exit
+    events_out: Option<PathBuf>,
+    pub(crate) recorder: Option<Recorder>,
+    pub(crate) history: FrameHistory,
+    #[cfg(feature = "lua")]
+    pub(crate) script: Option<Script>,
+    // fractional frames owed to the speed setting
+    speed_debt: f64,
+    // the speed to go back to once fast-forward is let go
+    fast_forward_from: Option<f64>,
+    quick_state: Option<Vec<u8>>,
+    paused: bool,
+    frame: u64,
+    screen: [u8; 32 * 3 * 32],
+    rng: StdRng,
+}
+
+impl Game {
+    pub fn new(nes: Nes) -> Game {
+        Game {
+            nes,
+            show_watches_each_frame: false,
+            show_input: false,
+            show_stats: false,
+            stats: FrameStats::new(),
+            palette: None,
+            save_flush: SaveFlush::default(),
+            save: None,
+            movie: None,
+            movie_out: None,
+            events_out: None,
+            recorder: None,
+            history: FrameHistory::new(0),
+            #[cfg(feature = "lua")]
+            script: None,
+            speed_debt: 0.0,
+            fast_forward_from: None,
+            quick_state: None,
+            paused: false,
+            frame: 0,
+            screen: [0; 32 * 3 * 32],
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Makes the random byte at `$FE` repeat from run to run.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Draws the screen through an NES palette instead of the playground
+    /// colors, or goes back to them with `None`.
+    pub fn set_palette(&mut self, palette: Option<Palette>) {
+        self.palette = palette;
+    }
+
+    /// Replaces the running program with an iNES ROM or raw program from
+    /// disk, as if the console had been switched off and on again.
+    pub fn load_file(&mut self, path: &Path) -> Result<(), String> {
+        let data = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        if let Err(e) = self.flush_saves() {
+            eprintln!("failed to write save: {}", e);
+        }
+
+        if Cartridge::is_ines(&data) {
+            let cartridge = Cartridge::from_ines(&data).map_err(|e| e.to_string())?;
+            self.nes.insert_cartridge(cartridge).map_err(|e| e.to_string())?;
+        } else {
+            self.nes.load(&data).map_err(|e| e.to_string())?;
+        }
+
+        // the old program's session ends even if its save can't be opened
+        if let Some(Err(e)) = self.stop_recording() {
+            eprintln!("recording failed: {}", e);
+        }
+        if let Err(e) = self.finish_movie() {
+            eprintln!("{}", e);
+        }
+        self.movie = None;
+        self.history.clear();
+        self.stats.clear();
+        self.speed_debt = 0.0;
+        self.quick_state = None;
+        self.paused = false;
+        self.frame = 0;
+        self.screen.fill(0);
+
+        self.open_save(path)
+    }
+
+    /// Fills the cartridge's battery-backed memory from the `.sav` file
+    /// next to `rom`, if there is one, and keeps it up to date from then
+    /// on as [`Game::save_flush`] says. Does nothing for boards without a
+    /// battery.
+    pub fn open_save(&mut self, rom: &Path) -> Result<(), String> {
+        self.save = None;
+        if self.nes.save_data().is_none() {
+            return Ok(());
+        }
+
+        let (mut save, data) = SaveFile::open(rom)?;
+        if let Some(data) = data {
+            self.nes.import_save_data(&data).map_err(|e| format!("{}: {}", save.path().display(), e))?;
+        }
+        if let Some(data) = self.nes.save_data() {
+            save.sync(data);
+        }
+        self.save = Some(save);
+        Ok(())
+    }
+
+    /// Writes the cartridge's battery-backed memory to its save file now,
+    /// if it changed since the last write.
+    pub fn flush_saves(&mut self) -> Result<(), String> {
+        match (self.save.as_mut(), self.nes.save_data()) {
+            (Some(save), Some(data)) => save.flush(data),
+            _ => Ok(()),
+        }
+    }
+
+    /// Feeds a movie's input in place of the player's, one record per
+    /// frame, until it runs out.
+    pub fn play_movie(&mut self, movie: Movie) {
+        self.movie = Some((movie, 0));
+    }
+
+    /// Switches the console off and on and records the input from there
+    /// into an `.fm2` movie, written to `path` by [`Game::finish_movie`].
+    pub fn record_movie(&mut self, path: &Path) {
+        self.nes.power_cycle();
+        self.nes.start_movie();
+        self.movie_out = Some(path.to_path_buf());
+    }
+
+    /// Writes the movie being recorded, if any, and stops recording.
+    pub fn finish_movie(&mut self) -> Result<(), String> {
+        let (Some(path), Some(movie)) = (self.movie_out.take(), self.nes.stop_movie()) else {
+            return Ok(());
+        };
+
+        fs::write(&path, movie.to_fm2()).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        println!("saved {} ({} frames)", path.display(), movie.frames.len());
+        Ok(())
+    }
+
+    /// Logs DMA, interrupts and bank switches from now on, for
+    /// [`Game::write_events`] to save to `path`.
+    pub fn log_events(&mut self, path: &Path) {
+        self.nes.cpu.bus.events.set_capacity(EVENT_LOG_SIZE);
+        self.events_out = Some(path.to_path_buf());
+    }
+
+    /// Writes the event log as JSON, if one is being kept.
+    pub fn write_events(&self) -> Result<(), String> {
+        let Some(path) = &self.events_out else {
+            return Ok(());
+        };
+
+        let events = &self.nes.cpu.bus.events;
+        fs::write(path, events.to_json()).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+        println!("saved {} ({} events)", path.display(), events.len());
+        Ok(())
+    }
+
+    /// Presses the console's reset button.
+    pub fn reset(&mut self) {
+        self.nes.reset();
+        println!("reset");
+    }
+
+    /// Switches the console off and on again.
+    pub fn power_cycle(&mut self) {
+        self.nes.power_cycle();
+        println!("power cycled");
+    }
+
+    /// Advances emulation for one presented frame at the current speed:
+    /// several frames when fast-forwarding, sometimes none in slow motion.
+    /// Assumes it's called at about the console's frame rate, by vsync or
+    /// a [`FrameTimer`](crate::pacing::FrameTimer).
+    pub fn tick(&mut self) -> Result<bool, EmuError> {
+        if self.paused {
+            return Ok(true);
+        }
+
+        let speed = self.nes.speed();
+
+        if speed.is_infinite() {
+            let start = Instant::now();
+            while start.elapsed() < UNCAPPED_TICK {
+                if !self.run_frame()? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+
+        self.speed_debt += speed;
+        while self.speed_debt >= 1.0 {
+            self.speed_debt -= 1.0;
+            if !self.run_frame()? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Pauses, or resumes, continuing past a breakpoint the debugger is
+    /// stopped at.
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        if !self.paused {
+            self.nes.debugger.resume();
+        }
+        println!("{}", if self.paused { "paused" } else { "resumed" });
+    }
+
+    #[cfg(feature = "remote")]
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused != self.paused {
+            self.toggle_pause();
+        }
+    }
+
+    #[cfg(feature = "remote")]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Runs exactly one frame while paused, for debugging and TAS work, or
+    /// one instruction while stopped at a breakpoint. Returns false once
+    /// the program has finished.
+    pub fn advance_frame(&mut self) -> Result<bool, EmuError> {
+        if !self.paused {
+            return Ok(true);
+        }
+
+        if self.nes.debugger.is_stopped() {
+            self.nes.step_instruction()?;
+            self.report_break();
+            return Ok(!self.nes.cpu.complete);
+        }
+
+        let running = self.run_frame()?;
+        println!("frame {}", self.frame);
+        Ok(running)
+    }
+
+    /// While stopped in the debugger, steps over a JSR or out of the
+    /// current subroutine; emulation runs on until the step finishes.
+    pub fn step_over(&mut self) -> Result<(), EmuError> {
+        self.debug_step(|nes| nes.step_over())
+    }
+
+    pub fn step_out(&mut self) -> Result<(), EmuError> {
+        self.debug_step(|nes| {
+            nes.step_out();
+            Ok(())
+        })
+    }
+
+    /// While stopped in the debugger, undoes the last instruction.
+    pub fn step_back(&mut self) {
+        if !self.nes.debugger.is_stopped() {
+            return;
+        }
+
+        if self.nes.step_back() {
+            self.report_break();
+        } else {
+            println!("no more history");
+        }
+    }
+
+    fn debug_step(&mut self, step: impl FnOnce(&mut Nes) -> Result<(), EmuError>) -> Result<(), EmuError> {
+        if !self.nes.debugger.is_stopped() {
+            return Ok(());
+        }
+
+        step(&mut self.nes)?;
+        if self.nes.debugger.is_stopped() {
+            self.report_break();
+        } else {
+            self.paused = false;
+        }
+        Ok(())
+    }
+
+    pub fn toggle_input_display(&mut self) {
+        self.show_input = !self.show_input;
+        println!("input display {}", if self.show_input { "on" } else { "off" });
+    }
+
+    pub fn toggle_stats(&mut self) {
+        self.show_stats = !self.show_stats;
+        self.stats.clear();
+    }
+
+    // the graph every frame, the figures once a second
+    fn show_frame_stats(&mut self) {
+        let frame_rate = self.nes.region().frame_rate();
+        self.stats.draw(&mut self.screen, Duration::from_secs_f64(1.0 / frame_rate));
+
+        if self.frame.is_multiple_of(frame_rate.round() as u64) {
+            println!(
+                "{:.1} fps, emulation {:.2} ms average, {:.2} ms worst",
+                self.stats.fps(),
+                self.stats.average_emulation().as_secs_f64() * 1000.0,
+                self.stats.worst_emulation().as_secs_f64() * 1000.0
+            );
+        }
+    }
+
+    /// Moves one step up or down the speed list.
+    pub fn step_speed(&mut self, faster: bool) {
+        let current = SPEEDS.iter().position(|&speed| speed >= self.nes.speed()).unwrap_or(2);
+        let next = if faster {
+            (current + 1).min(SPEEDS.len() - 1)
+        } else {
+            current.saturating_sub(1)
+        };
+
+        self.set_speed(SPEEDS[next]);
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.nes.set_speed(speed);
+        self.speed_debt = 0.0;
+
+        if speed.is_infinite() {
+            println!("speed: uncapped");
+        } else {
+            println!("speed: {}x", speed);
+        }
+    }
+
+    /// Runs uncapped while `held`, then goes back to the speed from before.
+    pub fn fast_forward(&mut self, held: bool) {
+        match (held, self.fast_forward_from) {
+            (true, None) => {
+                self.fast_forward_from = Some(self.nes.speed());
+                self.set_speed(f64::INFINITY);
+            },
+            (false, Some(speed)) => {
+                self.fast_forward_from = None;
+                self.set_speed(speed);
+            },
+            _ => {/* already there */}
+        }
+    }
+
+    /// Keeps a save state in memory for [`Game::load_quick_state`], in
+    /// place of the last one.
+    pub fn save_quick_state(&mut self) {
+        self.quick_state = Some(self.nes.save_state());
+        println!("state saved");
+    }
+
+    pub fn load_quick_state(&mut self) -> Result<(), String> {
+        let Some(state) = self.quick_state.as_ref() else {
+            return Err("no state saved".to_string());
+        };
+
+        self.nes.load_state(state).map_err(|e| format!("failed to load state: {}", e))?;
+        println!("state loaded");
+        Ok(())
+    }
+
+    /// Runs one frame; returns false once the program has finished.
+    pub fn run_frame(&mut self) -> Result<bool, EmuError> {
+        if let Some((movie, position)) = self.movie.as_mut() {
+            match movie.frames.get(*position).copied() {
+                Some(frame) => {
+                    *position += 1;
+                    if frame.power_cycle() {
+                        self.nes.power_cycle();
+                    } else if frame.soft_reset() {
+                        self.nes.reset();
+                    }
+                    self.set_buttons(frame.ports[0]);
+                    self.nes.cpu.bus.controllers[1].set_buttons(frame.ports[1]);
+                },
+                None => self.movie = None,
+            }
+        }
+
+        #[cfg(feature = "lua")]
+        self.run_script(Script::before_frame);
+
+        let start = Instant::now();
+        if self.nes.cpu.bus.cartridge.is_some() {
+            self.nes.run_frame()?;
+        } else {
+            for _ in 0..CYCLES_PER_FRAME {
+                self.nes.cpu.write(0xfe, self.rng.gen_range(1..16));
+
+                if !self.nes.clock()? || self.nes.cpu.complete {
+                    break;
+                }
+            }
+        }
+
+        if let Some(port) = self.nes.cpu.bus.debug_port.as_mut() {
+            for line in port.take_lines() {
+                println!("[debug] {}", line);
+            }
+        }
+
+        // a breakpoint pauses the frontend until it is resumed
+        if self.nes.debugger.is_stopped() && !self.paused {
+            self.paused = true;
+            self.report_break();
+        }
+
+        if self.nes.cpu.complete {
+            return Ok(false);
+        }
+
+        self.frame += 1;
+        self.stats.record(start.elapsed());
+        if self.show_watches_each_frame && !self.nes.debugger.watches().is_empty() {
+            println!("frame {}:", self.frame);
+            self.print_watches();
+        }
+
+        read_screen_state(&self.nes.cpu, self.palette.as_ref(), &mut self.screen);
+
+        #[cfg(feature = "lua")]
+        self.run_script(Script::after_frame);
+
+        if self.show_input {
+            let controllers = &self.nes.cpu.bus.controllers;
+            overlay::draw_input(&mut self.screen, [controllers[0].buttons(), controllers[1].buttons()]);
+        }
+        if self.show_stats {
+            self.show_frame_stats();
+        }
+
+        self.history.push(&self.screen);
+
+        if let (Some(save), Some(data)) = (self.save.as_mut(), self.nes.save_data()) {
+            if save.due(data, self.save_flush) {
+                if let Err(e) = save.flush(data) {
+                    eprintln!("failed to write save: {}", e);
+                }
+            }
+        }
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.push(&self.screen) {
+                eprintln!("recording of {} stopped: {}", recorder.path().display(), e);
+                self.recorder = None;
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn report_break(&self) {
+        let Some(stopped) = self.nes.debugger.stopped() else {
+            return;
+        };
+
+        match self.nes.cpu.trace_entry() {
+            Some(entry) => println!("{}: {}", stopped.reason, entry),
+            None => println!("{} at ${:04X}", stopped.reason, stopped.pc),
+        }
+
+        self.print_watches();
+
+        // the bank in each 8KB window, and where it starts in the ROM file
+        let windows: Vec<String> = [0x8000, 0xA000, 0xC000, 0xE000]
+            .into_iter()
+            .filter_map(|addr| {
+                let offset = self.nes.prg_file_offset(addr)?;
+                Some(format!("${:04X}: bank {} @ ${:X}", addr, self.nes.cpu.bus.prg_bank(addr), offset))
+            })
+            .collect();
+        if !windows.is_empty() {
+            println!("  PRG {}", windows.join(", "));
+        }
+
+        for frame in self.nes.cpu.call_stack.frames().iter().rev() {
+            let kind = match frame.kind {
+                FrameKind::Subroutine => "JSR".to_string(),
+                FrameKind::Interrupt(interrupt) => format!("{:?}", interrupt).to_uppercase(),
+            };
+            println!(
+                "  in ${:04X} (bank {}) via {} from ${:04X}",
+                frame.target, frame.bank, kind, frame.caller
+            );
+        }
+    }
+
+    pub(crate) fn print_watches(&self) {
+        for (text, value) in self.nes.debugger.evaluate_watches(&self.nes.cpu) {
+            println!("  {} = {} (${:X})", text, value, value);
+        }
+    }
+
+    /// The screen as RGB24.
+    pub fn screen(&self) -> &[u8] {
+        &self.screen
+    }
+
+    #[cfg(feature = "lua")]
+    pub(crate) fn screen_mut(&mut self) -> &mut [u8] {
+        &mut self.screen
+    }
+
+    /// Frames emulated since the program was loaded.
+    #[cfg(any(feature = "lua", feature = "remote"))]
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn press(&mut self, button: Button, pressed: bool) {
+        self.press_on(0, button, pressed);
+    }
+
+    /// Presses or releases a button on controller `player`, 0 or 1.
+    pub fn press_on(&mut self, player: usize, button: Button, pressed: bool) {
+        self.nes.cpu.bus.controllers[player].set_button(button, pressed);
+
+        // playground programs read the last key from $FF
+        if pressed && player == 0 {
+            let key = match button {
+                Button::Up => 0x77,
+                Button::Down => 0x73,
+                Button::Left => 0x61,
+                Button::Right => 0x64,
+                _ => return,
+            };
+
+            self.nes.cpu.write(0xff, key);
+        }
+    }
+
+    /// Holds a coin in a VS System slot (0 or 1); ignored for other games.
+    pub fn set_coin(&mut self, slot: usize, inserted: bool) {
+        if let Some(vs) = self.nes.cpu.bus.vs.as_mut() {
+            vs.set_coin(slot, inserted);
+        }
+    }
+
+    pub fn set_service(&mut self, pressed: bool) {
+        if let Some(vs) = self.nes.cpu.bus.vs.as_mut() {
+            vs.set_service(pressed);
+        }
+    }
+
+    /// Sets every button on the first controller from a mask, `A` in bit 0.
+    pub fn set_buttons(&mut self, buttons: u8) {
+        let previous = self.nes.cpu.bus.controllers[0].buttons();
+
+        for button in Button::ALL {
+            let pressed = (buttons & button.mask()) != 0;
+            if pressed != ((previous & button.mask()) != 0) {
+                self.press(button, pressed);
+            }
+        }
+    }
+}
+
+pub const SNAKE: &[u8] = &[
+    0x20, 0x06, 0x06, 0x20, 0x38, 0x06, 0x20, 0x0d, 0x06, 0x20, 0x2a, 0x06, 0x60, 0xa9, 0x02,
+    0x85, 0x02, 0xa9, 0x04, 0x85, 0x03, 0xa9, 0x11, 0x85, 0x10, 0xa9, 0x10, 0x85, 0x12, 0xa9,
+    0x0f, 0x85, 0x14, 0xa9, 0x04, 0x85, 0x11, 0x85, 0x13, 0x85, 0x15, 0x60, 0xa5, 0xfe, 0x85,
+    0x00, 0xa5, 0xfe, 0x29, 0x03, 0x18, 0x69, 0x02, 0x85, 0x01, 0x60, 0x20, 0x4d, 0x06, 0x20,
+    0x8d, 0x06, 0x20, 0xc3, 0x06, 0x20, 0x19, 0x07, 0x20, 0x20, 0x07, 0x20, 0x2d, 0x07, 0x4c,
+    0x38, 0x06, 0xa5, 0xff, 0xc9, 0x77, 0xf0, 0x0d, 0xc9, 0x64, 0xf0, 0x14, 0xc9, 0x73, 0xf0,
+    0x1b, 0xc9, 0x61, 0xf0, 0x22, 0x60, 0xa9, 0x04, 0x24, 0x02, 0xd0, 0x26, 0xa9, 0x01, 0x85,
+    0x02, 0x60, 0xa9, 0x08, 0x24, 0x02, 0xd0, 0x1b, 0xa9, 0x02, 0x85, 0x02, 0x60, 0xa9, 0x01,
+    0x24, 0x02, 0xd0, 0x10, 0xa9, 0x04, 0x85, 0x02, 0x60, 0xa9, 0x02, 0x24, 0x02, 0xd0, 0x05,
+    0xa9, 0x08, 0x85, 0x02, 0x60, 0x60, 0x20, 0x94, 0x06, 0x20, 0xa8, 0x06, 0x60, 0xa5, 0x00,
+    0xc5, 0x10, 0xd0, 0x0d, 0xa5, 0x01, 0xc5, 0x11, 0xd0, 0x07, 0xe6, 0x03, 0xe6, 0x03, 0x20,
+    0x2a, 0x06, 0x60, 0xa2, 0x02, 0xb5, 0x10, 0xc5, 0x10, 0xd0, 0x06, 0xb5, 0x11, 0xc5, 0x11,
+    0xf0, 0x09, 0xe8, 0xe8, 0xe4, 0x03, 0xf0, 0x06, 0x4c, 0xaa, 0x06, 0x4c, 0x35, 0x07, 0x60,
+    0xa6, 0x03, 0xca, 0x8a, 0xb5, 0x10, 0x95, 0x12, 0xca, 0x10, 0xf9, 0xa5, 0x02, 0x4a, 0xb0,
+    0x09, 0x4a, 0xb0, 0x19, 0x4a, 0xb0, 0x1f, 0x4a, 0xb0, 0x2f, 0xa5, 0x10, 0x38, 0xe9, 0x20,
+    0x85, 0x10, 0x90, 0x01, 0x60, 0xc6, 0x11, 0xa9, 0x01, 0xc5, 0x11, 0xf0, 0x28, 0x60, 0xe6,
+    0x10, 0xa9, 0x1f, 0x24, 0x10, 0xf0, 0x1f, 0x60, 0xa5, 0x10, 0x18, 0x69, 0x20, 0x85, 0x10,
+    0xb0, 0x01, 0x60, 0xe6, 0x11, 0xa9, 0x06, 0xc5, 0x11, 0xf0, 0x0c, 0x60, 0xc6, 0x10, 0xa5,
+    0x10, 0x29, 0x1f, 0xc9, 0x1f, 0xf0, 0x01, 0x60, 0x4c, 0x35, 0x07, 0xa0, 0x00, 0xa5, 0xfe,
+    0x91, 0x00, 0x60, 0xa6, 0x03, 0xa9, 0x00, 0x81, 0x10, 0xa2, 0x00, 0xa9, 0x01, 0x81, 0x10,
+    0x60, 0xa6, 0xff, 0xea, 0xea, 0xca, 0xd0, 0xfb, 0x60,
+];
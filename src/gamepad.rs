@@ -0,0 +1,191 @@
+use gilrs::{Axis, EventType, GamepadId, Gilrs};
+use nes_core::controller::Button;
+
+use crate::game::Game;
+use crate::input::{self, Action, Bindings, Hotkey, Input};
+use crate::settings::Settings;
+
+const PLAYERS: usize = 2;
+
+// built-in bindings by gilrs' button names, which follow the position on
+// the pad rather than the label: South is A on an Xbox pad, B on a
+// Nintendo one
+const PAD: &[(&str, Action)] = &[
+    ("DPadUp", Action::Button(Button::Up)),
+    ("DPadDown", Action::Button(Button::Down)),
+    ("DPadLeft", Action::Button(Button::Left)),
+    ("DPadRight", Action::Button(Button::Right)),
+    ("South", Action::Button(Button::A)),
+    ("East", Action::Button(Button::B)),
+    ("West", Action::Button(Button::B)),
+    ("Start", Action::Button(Button::Start)),
+    ("Select", Action::Button(Button::Select)),
+];
+
+/// A connected gamepad playing as one of the controllers.
+struct Pad {
+    id: GamepadId,
+    player: usize,
+    // the directions its analog stick is holding down
+    horizontal: Option<Button>,
+    vertical: Option<Button>,
+}
+
+/// Host gamepads, handed out to players as they're plugged in.
+///
+/// Pads named in `assignments` take their player's controller; others
+/// fill the controllers nobody is assigned to first. Any beyond the second
+/// player are left out until one is unplugged.
+pub struct Gamepads {
+    gilrs: Gilrs,
+    defaults: Bindings,
+    /// How far an analog stick has to lean, from 0 to 1, to press a
+    /// direction.
+    deadzone: f32,
+    // player to part of a pad's name
+    assignments: Vec<(usize, String)>,
+    pads: Vec<Pad>,
+}
+
+impl Gamepads {
+    pub fn new(deadzone: f32, assignments: Vec<(usize, String)>) -> Result<Gamepads, String> {
+        let gilrs = Gilrs::new().map_err(|e| format!("gamepads unavailable: {}", e))?;
+
+        let mut gamepads = Gamepads {
+            gilrs,
+            defaults: Bindings::from_names(&[], PAD),
+            deadzone,
+            assignments,
+            pads: Vec::new(),
+        };
+
+        // pads plugged in before startup don't get a Connected event
+        let connected: Vec<GamepadId> = gamepads.gilrs.gamepads().map(|(id, _)| id).collect();
+        for id in connected {
+            gamepads.connect(id);
+        }
+        Ok(gamepads)
+    }
+
+    /// Applies pending gamepad events to the game. Stops at the first
+    /// hotkey only the frontend can carry out and returns it, leaving the
+    /// rest queued.
+    pub fn poll(&mut self, game: &mut Game, settings: &mut Settings) -> Result<Option<Hotkey>, String> {
+        while let Some(event) = self.gilrs.next_event() {
+            let (button, pressed) = match event.event {
+                EventType::Connected => {
+                    self.connect(event.id);
+                    continue;
+                },
+                EventType::Disconnected => {
+                    self.disconnect(game, event.id);
+                    continue;
+                },
+                EventType::AxisChanged(axis, value, _) => {
+                    self.lean(game, event.id, axis, value);
+                    continue;
+                },
+                EventType::ButtonPressed(button, _) => (button, true),
+                EventType::ButtonReleased(button, _) => (button, false),
+                _ => continue,
+            };
+
+            let Some(player) = self.player(event.id) else {
+                continue;
+            };
+
+            let input = Input::Pad(format!("{:?}", button));
+            let Some(action) = settings.action(&input).or_else(|| self.defaults.action(&input)) else {
+                continue;
+            };
+
+            // buttons go to the pad's own controller; everything else is
+            // shared
+            if let Action::Button(button) = action {
+                game.press_on(player, button, pressed);
+            } else if let Some(hotkey) = input::perform(game, settings, action, pressed, false)? {
+                return Ok(Some(hotkey));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn player(&self, id: GamepadId) -> Option<usize> {
+        self.pads.iter().find(|pad| pad.id == id).map(|pad| pad.player)
+    }
+
+    fn connect(&mut self, id: GamepadId) {
+        if self.player(id).is_some() {
+            return;
+        }
+
+        let name = self.gilrs.gamepad(id).name().to_string();
+        let taken = |player: usize| self.pads.iter().any(|pad| pad.player == player);
+        let reserved = |player: usize| self.assignments.iter().any(|(assigned, _)| *assigned == player);
+
+        let lowercase = name.to_lowercase();
+        let assigned = self.assignments
+            .iter()
+            .find(|(player, part)| !taken(*player) && lowercase.contains(&part.to_lowercase()))
+            .map(|(player, _)| *player);
+        let player = assigned
+            .or_else(|| (0..PLAYERS).find(|&player| !taken(player) && !reserved(player)))
+            .or_else(|| (0..PLAYERS).find(|&player| !taken(player)));
+
+        match player {
+            Some(player) => {
+                println!("gamepad '{}' is player {}", name, player + 1);
+                self.pads.push(Pad { id, player, horizontal: None, vertical: None });
+            },
+            None => println!("gamepad '{}' connected, but every player has one", name),
+        }
+    }
+
+    fn disconnect(&mut self, game: &mut Game, id: GamepadId) {
+        let Some(index) = self.pads.iter().position(|pad| pad.id == id) else {
+            return;
+        };
+
+        // nothing stays held down on a pad that's gone
+        let pad = self.pads.remove(index);
+        for button in Button::ALL {
+            game.press_on(pad.player, button, false);
+        }
+        println!("player {}'s gamepad disconnected", pad.player + 1);
+    }
+
+    // presses a direction while the left stick leans past the deadzone,
+    // releasing the one it leaned to before
+    fn lean(&mut self, game: &mut Game, id: GamepadId, axis: Axis, value: f32) {
+        let deadzone = self.deadzone;
+        let Some(pad) = self.pads.iter_mut().find(|pad| pad.id == id) else {
+            return;
+        };
+
+        // gilrs has Y pointing up
+        let (held, direction) = match axis {
+            Axis::LeftStickX | Axis::DPadX => (&mut pad.horizontal, lean_to(value, deadzone, Button::Left, Button::Right)),
+            Axis::LeftStickY | Axis::DPadY => (&mut pad.vertical, lean_to(value, deadzone, Button::Down, Button::Up)),
+            _ => return,
+        };
+
+        if *held != direction {
+            if let Some(button) = *held {
+                game.press_on(pad.player, button, false);
+            }
+            if let Some(button) = direction {
+                game.press_on(pad.player, button, true);
+            }
+            *held = direction;
+        }
+    }
+}
+
+fn lean_to(value: f32, deadzone: f32, negative: Button, positive: Button) -> Option<Button> {
+    match value {
+        value if value < -deadzone => Some(negative),
+        value if value > deadzone => Some(positive),
+        _ => None,
+    }
+}
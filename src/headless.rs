@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+
+use nes_core::blargg::{self, Verdict};
+use nes_core::lockstep;
+use nes_core::nestest::{self, Outcome};
+
+use crate::cli::{parse_hex, EmuArgs};
+use crate::game::Game;
+
+/// When a headless run should stop early.
+#[derive(Copy, Clone, Debug)]
+pub enum Until {
+    /// The byte at the address equals the value.
+    Memory(u16, u8),
+}
+
+impl Until {
+    /// Parses `ADDR=VALUE`, both in hex, e.g. `0x6000=0x00`.
+    pub fn parse(spec: &str) -> Result<Until, String> {
+        let (addr, value) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected ADDR=VALUE, got '{}'", spec))?;
+
+        Ok(Until::Memory(parse_hex(addr)?, parse_hex(value)? as u8))
+    }
+
+    pub fn reached(&self, game: &Game) -> bool {
+        match *self {
+            Until::Memory(addr, value) => game.nes.cpu.peek(addr) == value,
+        }
+    }
+}
+
+/// Runs without a window or audio device for at most `frames` frames.
+///
+/// Exits with 0 when the program finishes, the condition is reached, or the
+/// frame budget runs out with no condition; 2 when a condition was given but
+/// never reached; 3 when a breakpoint is hit; 1 on an emulation error.
+pub fn run(mut game: Game, frames: u64, until: Option<Until>) -> ExitCode {
+    for frame in 0..frames {
+        match game.run_frame() {
+            Ok(true) => {},
+            Ok(false) => {
+                report(&game, frame + 1, "program finished");
+                return ExitCode::SUCCESS;
+            },
+            Err(e) => {
+                report(&game, frame + 1, &e.to_string());
+                return ExitCode::from(1);
+            }
+        }
+
+        if let Some(stopped) = game.nes.debugger.stopped() {
+            report(&game, frame + 1, &format!("{} hit at ${:04X}", stopped.reason, stopped.pc));
+            return ExitCode::from(3);
+        }
+
+        if let Some(until) = until {
+            if until.reached(&game) {
+                report(&game, frame + 1, "condition reached");
+                return ExitCode::SUCCESS;
+            }
+        }
+    }
+
+    match until {
+        Some(_) => {
+            report(&game, frames, "condition not reached");
+            ExitCode::from(2)
+        },
+        None => {
+            report(&game, frames, "frame limit reached");
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+/// Runs `frames` frames as fast as possible and prints how fast that was,
+/// for comparing performance across changes and machines.
+///
+/// Stops early if the program finishes or hits a breakpoint, reporting
+/// the frames run until then.
+pub fn bench(mut game: Game, frames: u64) -> Result<ExitCode, String> {
+    let start_cycles = game.nes.cpu.total_cycles;
+    let start = Instant::now();
+
+    let mut run = 0;
+    while run < frames {
+        let running = game.run_frame().map_err(|e| e.to_string())?;
+        run += 1;
+        if !running || game.nes.debugger.is_stopped() {
+            println!("stopped early: {}", game.nes.cpu.state());
+            break;
+        }
+    }
+
+    let elapsed = start.elapsed().as_secs_f64().max(f64::EPSILON);
+    let cycles = game.nes.cpu.total_cycles - start_cycles;
+    let region = game.nes.region();
+    let fps = run as f64 / elapsed;
+    let hz = cycles as f64 / elapsed;
+
+    println!("{} frames in {:.3} s", run, elapsed);
+    println!("{:.1} fps, {:.2}x the {:?} frame rate", fps, fps / region.frame_rate(), region);
+    println!("{:.3} MHz CPU, {:.2}x the {:?} clock", hz / 1e6, hz / region.cpu_clock_hz(), region);
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Runs nestest.nes against its reference log, up to its unofficial
+/// opcodes.
+///
+/// Exits with 0 when every line matches, 2 at the first divergence and 1
+/// on an emulation error.
+pub fn nestest(rom: &Path, log: &Path) -> Result<ExitCode, String> {
+    let data = fs::read(rom).map_err(|e| format!("{}: {}", rom.display(), e))?;
+    let log = fs::read_to_string(log).map_err(|e| format!("{}: {}", log.display(), e))?;
+
+    match nestest::run(&data, &log).map_err(|e| e.to_string())? {
+        Outcome::Passed { lines } => {
+            println!("nestest passed: {} official instructions match", lines);
+            Ok(ExitCode::SUCCESS)
+        },
+        Outcome::Diverged(divergence) => {
+            println!("nestest diverged at line {}", divergence.line);
+            println!("expected: {}", divergence.expected);
+            println!("actual:   {}", divergence.actual);
+            Ok(ExitCode::from(2))
+        },
+        Outcome::Failed { line, error } => {
+            println!("nestest failed at line {}: {}", line, error);
+            Ok(ExitCode::from(1))
+        },
+    }
+}
+
+/// Runs a ROM against a reference trace from another emulator.
+///
+/// Exits with 0 when every line matches, 2 at the first divergence and 1
+/// on an emulation error.
+pub fn lockstep(rom: &Path, trace: &Path) -> Result<ExitCode, String> {
+    let data = fs::read(rom).map_err(|e| format!("{}: {}", rom.display(), e))?;
+    let trace = fs::read_to_string(trace).map_err(|e| format!("{}: {}", trace.display(), e))?;
+
+    match lockstep::run(&data, &trace).map_err(|e| e.to_string())? {
+        lockstep::Outcome::Passed { lines } => {
+            println!("lockstep passed: {} instructions match", lines);
+            Ok(ExitCode::SUCCESS)
+        },
+        lockstep::Outcome::Diverged(divergence) => {
+            println!("diverged at line {}: {}", divergence.line, divergence.reason);
+            for line in &divergence.context {
+                println!("          {}", line);
+            }
+            println!("expected: {}", divergence.expected);
+            println!("actual:   {}", divergence.actual);
+            Ok(ExitCode::from(2))
+        },
+        lockstep::Outcome::Failed { line, error } => {
+            println!("lockstep failed at line {}: {}", line, error);
+            Ok(ExitCode::from(1))
+        },
+    }
+}
+
+/// Runs each blargg test ROM for at most `frames` frames and prints its
+/// verdict and message.
+///
+/// Exits with 0 when every ROM passes and 2 otherwise.
+pub fn blargg(paths: &[PathBuf], emu: &EmuArgs, frames: u64) -> Result<ExitCode, String> {
+    let mut roms = Vec::new();
+    for path in paths {
+        collect_roms(path, &mut roms)?;
+    }
+
+    let mut passed = 0;
+    for rom in &roms {
+        let (mut game, _) = crate::load_game(Some(rom), emu)?;
+        let report = blargg::run(&mut game.nes, frames).map_err(|e| format!("{}: {}", rom.display(), e))?;
+
+        let verdict = match report.verdict {
+            Verdict::Passed => {
+                passed += 1;
+                "passed".to_string()
+            },
+            Verdict::Failed(code) => format!("failed ({})", code),
+            Verdict::TimedOut => "timed out".to_string(),
+        };
+        println!("{}: {} after {} frames", rom.display(), verdict, report.frames);
+        for line in report.message.lines().filter(|line| !line.trim().is_empty()) {
+            println!("    {}", line);
+        }
+    }
+
+    println!("{}/{} passed", passed, roms.len());
+    Ok(if passed == roms.len() { ExitCode::SUCCESS } else { ExitCode::from(2) })
+}
+
+/// A ROM, or every .nes file under a directory in name order.
+pub fn collect_roms(path: &Path, roms: &mut Vec<PathBuf>) -> Result<(), String> {
+    if !path.is_dir() {
+        roms.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let entries = fs::read_dir(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+    entries.sort();
+
+    for entry in entries {
+        let is_rom = entry.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("nes"));
+        if entry.is_dir() || is_rom {
+            collect_roms(&entry, roms)?;
+        }
+    }
+    Ok(())
+}
+
+fn report(game: &Game, frames: u64, reason: &str) {
+    println!("{} after {} frames: {}", reason, frames, game.nes.cpu.state());
+    game.print_watches();
+    crate::print_stats(&game.nes);
+    if let Err(e) = game.write_events() {
+        eprintln!("{}", e);
+    }
+}
@@ -0,0 +1,226 @@
+use nes_core::controller::Button;
+
+use crate::clip;
+use crate::game::Game;
+use crate::record;
+use crate::screenshot;
+use crate::settings::Settings;
+
+/// Emulator functions a host input can trigger besides playing the game.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Hotkey {
+    Quit,
+    Fullscreen,
+    Screenshot,
+    Clip,
+    Record,
+    Faster,
+    Slower,
+    NormalSpeed,
+    /// Runs uncapped while held.
+    FastForward,
+    Pause,
+    /// One frame while paused, or one instruction at a breakpoint; repeats
+    /// while held.
+    Advance,
+    StepBack,
+    StepOver,
+    StepOut,
+    Filter,
+    Stats,
+    InputDisplay,
+    SaveState,
+    LoadState,
+    Reset,
+    PowerCycle,
+}
+
+impl Hotkey {
+    pub const ALL: [Hotkey; 21] = [
+        Hotkey::Quit,
+        Hotkey::Fullscreen,
+        Hotkey::Screenshot,
+        Hotkey::Clip,
+        Hotkey::Record,
+        Hotkey::Faster,
+        Hotkey::Slower,
+        Hotkey::NormalSpeed,
+        Hotkey::FastForward,
+        Hotkey::Pause,
+        Hotkey::Advance,
+        Hotkey::StepBack,
+        Hotkey::StepOver,
+        Hotkey::StepOut,
+        Hotkey::Filter,
+        Hotkey::Stats,
+        Hotkey::InputDisplay,
+        Hotkey::SaveState,
+        Hotkey::LoadState,
+        Hotkey::Reset,
+        Hotkey::PowerCycle,
+    ];
+}
+
+/// What a bound host input drives.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// A button on the first controller, or on a gamepad's own.
+    Button(Button),
+    /// A VS System coin slot, 0 or 1.
+    Coin(usize),
+    Service,
+    Hotkey(Hotkey),
+}
+
+impl Action {
+    /// Parses a button (`A`, `Start`, `Up`, ...), `Coin1`, `Coin2`,
+    /// `Service` or a hotkey (`SaveState`, `FastForward`, ...), ignoring
+    /// case.
+    pub fn parse(name: &str) -> Result<Action, String> {
+        let named = |debug: String| debug.eq_ignore_ascii_case(name);
+
+        if let Some(button) = Button::ALL.into_iter().find(|button| named(format!("{:?}", button))) {
+            return Ok(Action::Button(button));
+        }
+        if let Some(hotkey) = Hotkey::ALL.into_iter().find(|hotkey| named(format!("{:?}", hotkey))) {
+            return Ok(Action::Hotkey(hotkey));
+        }
+
+        match name.to_ascii_lowercase().as_str() {
+            "coin1" => Ok(Action::Coin(0)),
+            "coin2" => Ok(Action::Coin(1)),
+            "service" => Ok(Action::Service),
+            _ => Err(format!("unknown button or hotkey '{}'", name)),
+        }
+    }
+}
+
+/// A host key or gamepad button, by the name the frontend reports for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    Key(String),
+    Pad(String),
+}
+
+impl Input {
+    /// Parses a key name, or `pad:BUTTON` for a gamepad button.
+    pub fn parse(text: &str) -> Input {
+        match text.split_once(':') {
+            Some((kind, button)) if kind.eq_ignore_ascii_case("pad") => Input::Pad(button.to_string()),
+            _ => Input::Key(text.to_string()),
+        }
+    }
+
+    // names compare ignoring case, as they're typed in by hand
+    fn matches(&self, other: &Input) -> bool {
+        match (self, other) {
+            (Input::Key(a), Input::Key(b)) | (Input::Pad(a), Input::Pad(b)) => a.eq_ignore_ascii_case(b),
+            _ => false,
+        }
+    }
+}
+
+/// Which action each host input drives; an input drives at most one.
+#[derive(Clone, Debug, Default)]
+pub struct Bindings {
+    bindings: Vec<(Input, Action)>,
+}
+
+impl Bindings {
+    pub fn new() -> Bindings {
+        Bindings::default()
+    }
+
+    /// Bindings from tables of key and gamepad button names.
+    pub fn from_names(keys: &[(&str, Action)], pad: &[(&str, Action)]) -> Bindings {
+        let keys = keys.iter().map(|&(name, action)| (Input::Key(name.to_string()), action));
+        let pad = pad.iter().map(|&(name, action)| (Input::Pad(name.to_string()), action));
+        Bindings { bindings: keys.chain(pad).collect() }
+    }
+
+    /// Points `input` at `action`, replacing whatever it drove before.
+    pub fn bind(&mut self, input: Input, action: Action) {
+        self.unbind(&input);
+        self.bindings.push((input, action));
+    }
+
+    pub fn unbind(&mut self, input: &Input) {
+        self.bindings.retain(|(bound, _)| !bound.matches(input));
+    }
+
+    pub fn action(&self, input: &Input) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| bound.matches(input))
+            .map(|(_, action)| *action)
+    }
+}
+
+/// Carries out `action` for a host input going down or up; `repeat` marks
+/// the key repeat of one held down, which only frame advance and stepping
+/// back act on.
+///
+/// Returns the hotkeys only the frontend can carry out, quitting and
+/// fullscreen, and an error when emulation can't go on.
+pub fn perform(game: &mut Game, settings: &mut Settings, action: Action, pressed: bool, repeat: bool) -> Result<Option<Hotkey>, String> {
+    let hotkey = match action {
+        _ if repeat && !matches!(action, Action::Hotkey(Hotkey::Advance | Hotkey::StepBack)) => return Ok(None),
+        Action::Button(button) => {
+            game.press(button, pressed);
+            return Ok(None);
+        },
+        Action::Coin(slot) => {
+            game.set_coin(slot, pressed);
+            return Ok(None);
+        },
+        Action::Service => {
+            game.set_service(pressed);
+            return Ok(None);
+        },
+        Action::Hotkey(Hotkey::FastForward) => {
+            game.fast_forward(pressed);
+            return Ok(None);
+        },
+        Action::Hotkey(_) if !pressed => return Ok(None),
+        Action::Hotkey(hotkey) => hotkey,
+    };
+
+    match hotkey {
+        Hotkey::Quit | Hotkey::Fullscreen => return Ok(Some(hotkey)),
+        Hotkey::Screenshot => match screenshot::capture(game, &settings.title, settings.overscan) {
+            Ok(path) => println!("saved {}", path.display()),
+            Err(e) => eprintln!("screenshot failed: {}", e),
+        },
+        Hotkey::Clip => clip::capture(game, &settings.title, settings.clip_format),
+        Hotkey::Record => record::toggle(game, &settings.title),
+        Hotkey::Faster => game.step_speed(true),
+        Hotkey::Slower => game.step_speed(false),
+        Hotkey::NormalSpeed => game.set_speed(1.0),
+        Hotkey::FastForward => {/* handled on press and release above */},
+        Hotkey::Pause => game.toggle_pause(),
+        Hotkey::Advance => {
+            if !game.advance_frame().map_err(|e| e.to_string())? {
+                return Ok(Some(Hotkey::Quit));
+            }
+        },
+        Hotkey::StepBack => game.step_back(),
+        Hotkey::StepOver => game.step_over().map_err(|e| e.to_string())?,
+        Hotkey::StepOut => game.step_out().map_err(|e| e.to_string())?,
+        Hotkey::Filter => {
+            settings.filter = settings.filter.next();
+            println!("filter: {:?}", settings.filter);
+        },
+        Hotkey::Stats => game.toggle_stats(),
+        Hotkey::InputDisplay => game.toggle_input_display(),
+        Hotkey::SaveState => game.save_quick_state(),
+        Hotkey::LoadState => {
+            if let Err(e) = game.load_quick_state() {
+                eprintln!("{}", e);
+            }
+        },
+        Hotkey::Reset => game.reset(),
+        Hotkey::PowerCycle => game.power_cycle(),
+    }
+
+    Ok(None)
+}
@@ -1,3 +0,0 @@
-pub mod constants;
-pub mod cpu;
-pub mod bus;
\ No newline at end of file
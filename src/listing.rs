@@ -0,0 +1,484 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use nes_core::cartridge::Cartridge;
+use nes_core::constants::{AddressingMode, OpCode, OPCODES};
+use nes_core::trace;
+
+// FCEUX code/data log flags, one byte per byte of PRG ROM
+const CDL_CODE: u8 = 0x01;
+const CDL_DATA: u8 = 0x02;
+
+// switchable banks are assumed to be this big once PRG ROM outgrows the
+// 32KB window, with the last one fixed at $C000
+const BANK_SIZE: usize = 0x4000;
+
+// data bytes per `.db` line
+const ROW: usize = 16;
+
+/// A span of PRG ROM and the address it runs at.
+struct Bank<'a> {
+    origin: u16,
+    data: &'a [u8],
+    // the log's flags for `data`; bytes past its end were never run
+    cdl: Option<&'a [u8]>,
+    // where each line starts, and which of those are referenced
+    items: Vec<Item>,
+    labels: BTreeSet<usize>,
+}
+
+#[derive(Copy, Clone)]
+enum Item {
+    Instruction(usize, &'static OpCode),
+    Byte(usize),
+    // NMI, reset and IRQ at $FFFA
+    Vectors(usize),
+}
+
+impl Item {
+    fn offset(&self) -> usize {
+        match *self {
+            Item::Instruction(offset, _) | Item::Byte(offset) | Item::Vectors(offset) => offset,
+        }
+    }
+}
+
+impl Bank<'_> {
+    fn contains(&self, addr: u16) -> bool {
+        (addr as usize).checked_sub(self.origin as usize).is_some_and(|offset| offset < self.data.len())
+    }
+
+    fn word(&self, offset: usize) -> u16 {
+        u16::from_le_bytes([self.data[offset], self.data[offset + 1]])
+    }
+
+    fn flags(&self, offset: usize) -> Option<u8> {
+        self.cdl.map(|cdl| cdl.get(offset).copied().unwrap_or(0))
+    }
+
+    // code wherever the log says it ran, or everywhere that decodes
+    // without one; bytes only logged as data stay data
+    fn is_code(&self, offset: usize) -> bool {
+        match self.flags(offset) {
+            Some(flags) => flags & CDL_CODE != 0,
+            None => true,
+        }
+    }
+
+    fn is_data(&self, offset: usize) -> bool {
+        self.flags(offset).is_some_and(|flags| flags & CDL_DATA != 0 && flags & CDL_CODE == 0)
+    }
+
+    fn split(&mut self) {
+        let vectors = (self.origin as usize + self.data.len() == 0x10000 && self.data.len() >= 6)
+            .then(|| self.data.len() - 6);
+        let end = vectors.unwrap_or(self.data.len());
+
+        let mut offset = 0;
+        while offset < end {
+            let instruction = OPCODES.get(&self.data[offset]).filter(|opcode| {
+                let len = opcode.bytes as usize;
+                offset + len <= end && (offset..offset + len).all(|i| self.is_code(i) && !self.is_data(i))
+            });
+
+            match instruction {
+                Some(opcode) => {
+                    self.items.push(Item::Instruction(offset, opcode));
+                    offset += opcode.bytes as usize;
+                },
+                None => {
+                    self.items.push(Item::Byte(offset));
+                    offset += 1;
+                },
+            }
+        }
+
+        if let Some(vectors) = vectors {
+            self.items.push(Item::Vectors(vectors));
+        }
+    }
+
+    // the line an offset falls on, and how far into it
+    fn line(&self, offset: usize) -> (usize, usize) {
+        let index = self.items.partition_point(|item| item.offset() <= offset) - 1;
+        let start = self.items[index].offset();
+        (start, offset - start)
+    }
+}
+
+/// A listing of the image that assembles back to the same bytes with
+/// asm6, for ROM hacking.
+///
+/// Branch, jump and data references into PRG ROM become labels, and the
+/// interrupt vectors `.dw` entries. PRG ROM is split into code and data by
+/// an FCEUX code/data log when there is one, and by whatever decodes as an
+/// official opcode when not. Instructions an assembler would encode
+/// differently, like absolute addressing of the zero page, are kept as
+/// bytes.
+///
+/// Images larger than 32KB are listed in 16KB banks at `$8000`, the last
+/// fixed at `$C000`, with references resolved within the bank or to the
+/// fixed one.
+pub fn export(name: &str, data: &[u8], cdl: Option<&[u8]>) -> Result<String, String> {
+    let (prefix, prg, suffix, origin) = if Cartridge::is_ines(data) {
+        let cartridge = Cartridge::from_ines(data).map_err(|e| e.to_string())?;
+        let start = 16 + cartridge.trainer.as_ref().map_or(0, |trainer| trainer.len());
+        let end = start + cartridge.prg_rom.len();
+        // a 16KB image sits at $C000, anything larger starts at $8000
+        let origin = 0x10000 - cartridge.prg_rom.len().min(0x8000);
+        (&data[..start], &data[start..end], &data[end..], origin as u16)
+    } else {
+        (&data[..0], data, &data[data.len()..], 0x0600)
+    };
+
+    let bank_size = if prg.len() > 0x8000 { BANK_SIZE } else { prg.len().max(1) };
+    let count = prg.len().div_ceil(bank_size);
+    let mut banks: Vec<Bank> = prg
+        .chunks(bank_size)
+        .enumerate()
+        .map(|(i, data)| Bank {
+            origin: if count == 1 { origin } else if i == count - 1 { 0xC000 } else { 0x8000 },
+            data,
+            cdl: cdl.map(|cdl| cdl.get(i * bank_size..).unwrap_or(&[])),
+            items: Vec::new(),
+            labels: BTreeSet::new(),
+        })
+        .collect();
+
+    for bank in banks.iter_mut() {
+        bank.split();
+    }
+
+    // label every line something refers to
+    let mut referenced = Vec::new();
+    for (index, bank) in banks.iter().enumerate() {
+        for item in &bank.items {
+            for target in targets(bank, *item) {
+                if let Some((target_bank, offset)) = locate(&banks, index, target) {
+                    referenced.push((target_bank, banks[target_bank].line(offset).0));
+                }
+            }
+        }
+    }
+    for (bank, offset) in referenced {
+        banks[bank].labels.insert(offset);
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "; {}, disassembled by nes-emu", name);
+    let _ = writeln!(out, "; assembles back to the original bytes with asm6");
+    bytes(&mut out, prefix);
+
+    for (index, bank) in banks.iter().enumerate() {
+        let _ = writeln!(out);
+        if count > 1 {
+            let _ = writeln!(out, "; bank {}", index);
+        }
+        let _ = writeln!(out, "    .base ${:04X}", bank.origin);
+        list(&mut out, &banks, index);
+    }
+
+    if !suffix.is_empty() {
+        let _ = writeln!(out);
+        bytes(&mut out, suffix);
+    }
+
+    Ok(out)
+}
+
+// addresses an item refers to, whether or not they're in ROM
+fn targets(bank: &Bank, item: Item) -> Vec<u16> {
+    match item {
+        Item::Instruction(offset, opcode) => operand_target(bank, offset, opcode).into_iter().collect(),
+        Item::Vectors(offset) => (0..3).map(|i| bank.word(offset + i * 2)).collect(),
+        Item::Byte(_) => Vec::new(),
+    }
+}
+
+fn operand_target(bank: &Bank, offset: usize, opcode: &OpCode) -> Option<u16> {
+    let pc = bank.origin.wrapping_add(offset as u16);
+    match opcode.addressing_mode {
+        AddressingMode::Relative => {
+            let displacement = bank.data[offset + 1] as i8;
+            Some(pc.wrapping_add(2).wrapping_add(displacement as u16))
+        },
+        AddressingMode::Absolute | AddressingMode::AbsoluteX | AddressingMode::AbsoluteY | AddressingMode::Indirect => {
+            Some(bank.word(offset + 1))
+        },
+        _ => None,
+    }
+}
+
+// the bank and offset an address refers to from `from`, if it's in ROM
+// the bank can see
+fn locate(banks: &[Bank], from: usize, addr: u16) -> Option<(usize, usize)> {
+    let fixed = banks.len() - 1;
+    [from, fixed]
+        .into_iter()
+        .find(|&bank| banks[bank].contains(addr))
+        .map(|bank| (bank, (addr - banks[bank].origin) as usize))
+}
+
+fn label(banks: &[Bank], bank: usize, offset: usize) -> String {
+    let addr = banks[bank].origin as usize + offset;
+    if banks.len() == 1 {
+        format!("L_{:04X}", addr)
+    } else {
+        format!("B{:02}_{:04X}", bank, addr)
+    }
+}
+
+// an address as a label, plus how far past it, when it points into ROM
+fn reference(banks: &[Bank], from: usize, addr: u16) -> String {
+    match locate(banks, from, addr) {
+        Some((bank, offset)) => {
+            let (start, into) = banks[bank].line(offset);
+            match into {
+                0 => label(banks, bank, start),
+                into => format!("{}+{}", label(banks, bank, start), into),
+            }
+        },
+        None => format!("${:04X}", addr),
+    }
+}
+
+fn list(out: &mut String, banks: &[Bank], index: usize) {
+    let bank = &banks[index];
+    let mut row: Vec<u8> = Vec::new();
+
+    for item in &bank.items {
+        let offset = item.offset();
+        if bank.labels.contains(&offset) {
+            bytes(out, &row);
+            row.clear();
+            let _ = writeln!(out, "{}:", label(banks, index, offset));
+        }
+
+        match *item {
+            Item::Byte(offset) => {
+                row.push(bank.data[offset]);
+                if row.len() == ROW {
+                    bytes(out, &row);
+                    row.clear();
+                }
+                continue;
+            },
+            _ => {
+                bytes(out, &row);
+                row.clear();
+            },
+        }
+
+        match *item {
+            Item::Instruction(offset, opcode) => {
+                let _ = writeln!(out, "{}", instruction(banks, index, offset, opcode));
+            },
+            Item::Vectors(offset) => {
+                let vectors: Vec<String> = (0..3).map(|i| reference(banks, index, bank.word(offset + i * 2))).collect();
+                let _ = writeln!(out, "    .dw {} ; NMI, reset, IRQ", vectors.join(", "));
+            },
+            Item::Byte(_) => unreachable!(),
+        }
+    }
+
+    bytes(out, &row);
+}
+
+fn instruction(banks: &[Bank], index: usize, offset: usize, opcode: &OpCode) -> String {
+    let bank = &banks[index];
+    let byte = |i: usize| bank.data[offset + i];
+    let target = || reference(banks, index, bank.word(offset + 1));
+
+    let operand = match opcode.addressing_mode {
+        AddressingMode::Immediate => format!("#${:02X}", byte(1)),
+        AddressingMode::ZeroPage => format!("${:02X}", byte(1)),
+        AddressingMode::ZeroPageX => format!("${:02X},X", byte(1)),
+        AddressingMode::ZeroPageY => format!("${:02X},Y", byte(1)),
+        AddressingMode::IndirectX => format!("(${:02X},X)", byte(1)),
+        AddressingMode::IndirectY => format!("(${:02X}),Y", byte(1)),
+        // the assembler would pick zero page addressing, a byte shorter
+        AddressingMode::Absolute | AddressingMode::AbsoluteX | AddressingMode::AbsoluteY if byte(2) == 0 => {
+            return encoded(bank, offset, opcode);
+        },
+        AddressingMode::Absolute => target(),
+        AddressingMode::AbsoluteX => format!("{},X", target()),
+        AddressingMode::AbsoluteY => format!("{},Y", target()),
+        AddressingMode::Indirect => format!("({})", target()),
+        AddressingMode::Relative => {
+            // a branch can wrap around the address space, but can't be
+            // written that way
+            let to = bank.origin as i32 + offset as i32 + 2 + byte(1) as i8 as i32;
+            if !(0..=0xFFFF).contains(&to) {
+                return encoded(bank, offset, opcode);
+            }
+            reference(banks, index, to as u16)
+        },
+        AddressingMode::Accumulator | AddressingMode::Implicit => String::new(),
+    };
+
+    if operand.is_empty() {
+        format!("    {}", opcode.name)
+    } else {
+        format!("    {} {}", opcode.name, operand)
+    }
+}
+
+// an instruction as its bytes, with what they decode to
+fn encoded(bank: &Bank, offset: usize, opcode: &OpCode) -> String {
+    let bytes = &bank.data[offset..offset + opcode.bytes as usize];
+    let encoded: Vec<String> = bytes.iter().map(|byte| format!("${:02X}", byte)).collect();
+    format!("    .db {} ; {}", encoded.join(", "), trace::disassemble(opcode, bytes))
+}
+
+fn bytes(out: &mut String, data: &[u8]) {
+    for row in data.chunks(ROW) {
+        let row: Vec<String> = row.iter().map(|byte| format!("${:02X}", byte)).collect();
+        let _ = writeln!(out, "    .db {}", row.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LDX #$00; LDA $C020,X; STA $0200,X; INX; BNE $C002; LDA $0010;
+    // JMP $C00E at $C000, four data bytes at $C020, a BPL that would wrap
+    // past $FFFF at $FFF8 and the vectors, in a 16KB NROM image
+    fn nrom() -> Vec<u8> {
+        let mut prg = vec![0xFF; 0x4000];
+        prg[..0x11].copy_from_slice(&[
+            0xA2, 0x00, 0xBD, 0x20, 0xC0, 0x9D, 0x00, 0x02, 0xE8, 0xD0, 0xF7, 0xAD, 0x10, 0x00, 0x4C, 0x0E, 0xC0,
+        ]);
+        prg[0x20..0x24].copy_from_slice(&[0x01, 0x02, 0x03, 0x04]);
+        prg[0x3FF8..].copy_from_slice(&[0x10, 0x7F, 0x00, 0xC0, 0x00, 0xC0, 0x0E, 0xC0]);
+
+        let mut rom = vec![b'N', b'E', b'S', 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        rom.extend(prg);
+        rom
+    }
+
+    // an FCEUX log of the code above having run and the table being read
+    fn cdl() -> Vec<u8> {
+        let mut cdl = vec![0; 0x4000];
+        cdl[..0x11].fill(CDL_CODE);
+        cdl[0x20..0x24].fill(CDL_DATA);
+        cdl[0x3FF8..0x3FFA].fill(CDL_CODE);
+        cdl
+    }
+
+    // a label, a label plus an offset or a hex number
+    fn value(text: &str) -> u16 {
+        let (base, plus) = text.split_once('+').unwrap_or((text, "0"));
+        let hex = base.rsplit(['_', '$']).next().unwrap();
+        u16::from_str_radix(hex, 16).unwrap() + plus.parse::<u16>().unwrap()
+    }
+
+    // the part of asm6 the listings use: returns the bytes a listing
+    // assembles to, checking every label sits at its own address
+    fn assemble(listing: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut pc = 0u16;
+
+        for line in listing.lines() {
+            let line = line.split(';').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(label) = line.strip_suffix(':') {
+                assert_eq!(value(label), pc, "{} is out of place", label);
+                continue;
+            }
+
+            let (name, operand) = line.split_once(' ').unwrap_or((line, ""));
+            let start = out.len();
+            match name {
+                ".base" => pc = value(operand),
+                ".db" => out.extend(operand.split(", ").map(|byte| value(byte) as u8)),
+                ".dw" => out.extend(operand.split(", ").flat_map(|word| value(word).to_le_bytes())),
+                name => {
+                    let zero_page = |text: &str| text.len() == 3 && text.starts_with('$');
+                    let (modes, operand): (&[AddressingMode], &str) = match operand {
+                        "" => (&[AddressingMode::Implicit, AddressingMode::Accumulator], ""),
+                        _ if operand.starts_with('#') => (&[AddressingMode::Immediate], &operand[1..]),
+                        _ if operand.ends_with(",X)") => (&[AddressingMode::IndirectX], &operand[1..operand.len() - 3]),
+                        _ if operand.ends_with("),Y") => (&[AddressingMode::IndirectY], &operand[1..operand.len() - 3]),
+                        _ if operand.starts_with('(') => (&[AddressingMode::Indirect], &operand[1..operand.len() - 1]),
+                        _ if operand.ends_with(",X") => match &operand[..operand.len() - 2] {
+                            base if zero_page(base) => (&[AddressingMode::ZeroPageX], base),
+                            base => (&[AddressingMode::AbsoluteX], base),
+                        },
+                        _ if operand.ends_with(",Y") => match &operand[..operand.len() - 2] {
+                            base if zero_page(base) => (&[AddressingMode::ZeroPageY], base),
+                            base => (&[AddressingMode::AbsoluteY], base),
+                        },
+                        _ if zero_page(operand) => (&[AddressingMode::ZeroPage], operand),
+                        _ => (&[AddressingMode::Relative, AddressingMode::Absolute], operand),
+                    };
+                    let opcode = OPCODES
+                        .iter()
+                        .find(|opcode| opcode.name == name && modes.contains(&opcode.addressing_mode))
+                        .unwrap_or_else(|| panic!("can't assemble '{}'", line));
+
+                    out.push(opcode.opcode);
+                    match opcode.addressing_mode {
+                        AddressingMode::Relative => {
+                            out.push(value(operand).wrapping_sub(pc.wrapping_add(2)) as u8);
+                        },
+                        _ if opcode.bytes == 2 => out.push(value(operand) as u8),
+                        _ if opcode.bytes == 3 => out.extend(value(operand).to_le_bytes()),
+                        _ => {},
+                    }
+                },
+            }
+            pc = pc.wrapping_add((out.len() - start) as u16);
+        }
+
+        out
+    }
+
+    #[test]
+    fn assembles_back_to_the_image() {
+        let rom = nrom();
+        for cdl in [None, Some(cdl())] {
+            let listing = export("test", &rom, cdl.as_deref()).unwrap();
+            assert_eq!(assemble(&listing), rom, "{}", listing);
+        }
+    }
+
+    #[test]
+    fn labels_references_into_rom() {
+        let listing = export("test", &nrom(), None).unwrap();
+        let lines: Vec<&str> = listing.lines().collect();
+
+        assert!(lines.contains(&"    .base $C000"));
+        assert!(lines.contains(&"L_C002:"));
+        assert!(lines.contains(&"    LDA L_C020,X"));
+        assert!(lines.contains(&"    STA $0200,X"));
+        assert!(lines.contains(&"    BNE L_C002"));
+        assert!(lines.contains(&"L_C00E:"));
+        assert!(lines.contains(&"    JMP L_C00E"));
+        assert!(lines.contains(&"    .dw L_C000, L_C000, L_C00E ; NMI, reset, IRQ"));
+    }
+
+    #[test]
+    fn keeps_what_an_assembler_would_change_as_bytes() {
+        let listing = export("test", &nrom(), None).unwrap();
+
+        // asm6 would shorten this to zero page addressing
+        assert!(listing.contains("    .db $AD, $10, $00 ; "));
+        // $FFF8 + 2 + $7F is past $FFFF
+        assert!(listing.contains("    .db $10, $7F ; "));
+    }
+
+    #[test]
+    fn lists_logged_data_as_bytes() {
+        let without = export("test", &nrom(), None).unwrap();
+        assert!(without.contains("    ORA ($02,X)"));
+
+        // only the logged code decodes; the table and the unlogged bytes
+        // after it are data
+        let with = export("test", &nrom(), Some(&cdl())).unwrap();
+        assert!(!with.contains("ORA"));
+        assert!(with.contains("L_C020:\n    .db $01, $02, $03, $04, $FF,"));
+    }
+}
@@ -1,169 +1,476 @@
-pub mod cpu;
-pub mod constants;
-pub mod bus;
-
-use cpu::CPU;
-use rand::Rng;
-use bus::Bus;
-
-use sdl2::event::Event;
-use sdl2::EventPump;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
-use sdl2::pixels::PixelFormatEnum;
-
-fn color(byte: u8) -> Color {
-    match byte {
-        0 => sdl2::pixels::Color::RGB(30, 30, 46),
-        1 => sdl2::pixels::Color::RGB(205, 214, 244),
-        2 | 9 => sdl2::pixels::Color::RGB(108, 112, 134),
-        3 | 10 => sdl2::pixels::Color::RGB(243, 139, 168),
-        4 | 11 => sdl2::pixels::Color::RGB(166, 227, 161),
-        5 | 12 => sdl2::pixels::Color::RGB(137, 180, 250),
-        6 | 13 => sdl2::pixels::Color::RGB(245, 194, 231),
-        7 | 14 => sdl2::pixels::Color::RGB(249, 226, 175),
-        _ => sdl2::pixels::Color::RGB(137, 220, 235),
+// the window-only code is still built for a headless-only build, to keep
+// the command line the same; it just goes unused
+#![cfg_attr(not(any(feature = "sdl", feature = "winit")), allow(dead_code))]
+
+mod cli;
+mod clip;
+mod config;
+mod filter;
+mod game;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod headless;
+mod input;
+mod listing;
+mod overlay;
+mod pacing;
+mod record;
+mod saves;
+mod regress;
+#[cfg(feature = "remote")]
+mod remote;
+mod screenshot;
+#[cfg(feature = "lua")]
+mod script;
+#[cfg(feature = "sdl")]
+mod sdl;
+mod settings;
+mod stats;
+mod suite;
+mod trace_log;
+mod viewport;
+#[cfg(feature = "winit")]
+mod winit_frontend;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use clap::Parser;
+use nes_core::cartridge::Cartridge;
+use nes_core::constants::{Region, OPCODES};
+use nes_core::debug_port::DebugPort;
+use nes_core::hooks::Interrupt;
+use nes_core::mapper;
+use nes_core::movie::Movie;
+use nes_core::nes::{ClockSource, Nes};
+use nes_core::palette::Palette;
+use nes_core::trace::{self, TraceEntry, TraceFilter};
+
+use cli::{BreakOn, Cli, Command, EmuArgs, WindowArgs};
+use config::{GameConfig, RecentRoms};
+use game::Game;
+use input::Bindings;
+#[cfg(feature = "sdl")]
+use pacing::{FrameTimer, Pacing};
+#[cfg(feature = "sdl")]
+use sdl::SdlFrontend;
+use settings::Settings;
+use trace_log::TraceLog;
+
+// routines listed by --profile
+const PROFILE_ROWS: usize = 25;
+
+fn disassemble(program: &[u8], origin: usize) {
+    println!("Address\t\tHexdump\t\tDissassembly");
+    println!("-------------------------------");
+    let mut i: usize = 0;
+
+    while i < program.len() {
+        // data mixed in with the code decodes as unknown opcodes
+        let Some(opc) = OPCODES.get(&program[i]) else {
+            println!("${:04X}\t\t{:02X}          \t.byte ${:02X}", origin + i, program[i], program[i]);
+            i += 1;
+            continue;
+        };
+        let bytes = &program[i..(i + opc.bytes as usize).min(program.len())];
+
+        print!("${:04X}\t\t", origin + i);
+
+        for byte in bytes {
+            print!("{:02X} ", byte);
+        }
+
+        print!("{}", "    ".repeat(3 - bytes.len()));
+
+        println!("\t{}", trace::disassemble(opc, bytes));
+
+        i += opc.bytes as usize;
     }
 }
 
-fn read_screen_state(cpu: &CPU, frame: &mut [u8; 32 * 3 * 32]) -> bool {
-    let mut frame_idx = 0;
-    let mut update = false;
-    for i in 0x0200 as u16..0x600 as u16 {
-        let color_idx = cpu.read(i as u16);
-        let (b1, b2, b3) = color(color_idx).rgb();
-        if frame[frame_idx] != b1 || frame[frame_idx + 1] != b2 || frame[frame_idx + 2] != b3 {
-            frame[frame_idx] = b1;
-            frame[frame_idx + 1] = b2;
-            frame[frame_idx + 2] = b3;
-            update = true;
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Builds a game from an iNES ROM, a raw program loaded at `$0600`, or the
+/// snake demo when no path is given.
+///
+/// Also returns the per-game config for the ROM and adds it to the recent
+/// list.
+fn load_game(rom: Option<&Path>, emu: &EmuArgs) -> Result<(Game, GameConfig), String> {
+    let data = match rom {
+        Some(path) => read(path)?,
+        None => game::SNAKE.to_vec(),
+    };
+
+    let config = GameConfig::load(&config::rom_hash(&data))?;
+    if let Some(path) = rom {
+        if let Err(e) = RecentRoms::add(path) {
+            eprintln!("failed to update recent ROMs: {}", e);
         }
-        frame_idx += 3;
     }
-    update
-}
 
-fn handle_user_input(cpu: &mut CPU, event_pump: &mut EventPump) {
-    for event in event_pump.poll_iter() {
+    let cartridge = if Cartridge::is_ines(&data) {
+        Some(Cartridge::from_ines(&data).map_err(|e| e.to_string())?)
+    } else {
+        None
+    };
+
+    // the command line wins over the per-game config
+    let palette = match emu.palette.as_ref().or(config.palette.as_ref()) {
+        Some(path) => Some(Palette::from_pal(&read(path)?).map_err(|e| e.to_string())?),
+        None => None,
+    };
+
+    // a region from the command line holds for every ROM opened
+    let mut builder = Nes::builder()
+        .region(emu.region.map_or(Region::Ntsc, Region::from))
+        .pin_region(emu.region.is_some())
+        .dip_switches(emu.dip)
+        .ram_pattern(emu.ram_pattern)
+        .overclock_scanlines(emu.overclock)
+        .deterministic(emu.deterministic);
+    if let Some(start) = emu.clock_start {
+        builder = builder.clock_source(ClockSource::Emulated { start });
+    }
+    if let Some(palette) = palette {
+        builder = builder.palette(palette);
+    }
+    let mut nes = builder.build();
+    nes.set_speed(emu.speed);
+    // journaling every instruction costs time and memory, so only when
+    // something can stop in the debugger
+    let debugging =
+        !emu.breakpoints.is_empty() || !emu.break_on.is_empty() || !emu.watchpoints.is_empty();
+    nes.set_history_capacity(emu.history.unwrap_or(if debugging { 10_000 } else { 0 }));
+    nes.profiler.set_enabled(emu.profile);
+    nes.opcode_stats.set_enabled(emu.opcode_stats);
+    for (pc, condition) in &emu.breakpoints {
+        match condition {
+            Some(condition) => nes.debugger.add_conditional_breakpoint(*pc, condition.clone()),
+            None => nes.debugger.add_breakpoint(*pc),
+        }
+    }
+    for event in &emu.break_on {
         match event {
-            Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                std::process::exit(0)
-            },
-            Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
-                cpu.write(0xff, 0x77);
-            },
-            Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
-                cpu.write(0xff, 0x73);
-            },
-            Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
-                cpu.write(0xff, 0x61);
-            },
-            Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
-                cpu.write(0xff, 0x64);
-            }
-            _ => {/* do nothing */}
+            BreakOn::Nmi => nes.debugger.set_break_on_interrupt(Interrupt::Nmi, true),
+            BreakOn::Irq => nes.debugger.set_break_on_interrupt(Interrupt::Irq, true),
+            BreakOn::Brk => nes.debugger.set_break_on_interrupt(Interrupt::Brk, true),
+            BreakOn::Unofficial => nes.debugger.set_break_on_unofficial(true),
+            BreakOn::Vblank => nes.debugger.set_break_on_vblank(true),
+            BreakOn::MapperIrq => nes.debugger.set_break_on_mapper_irq(true),
         }
     }
+    for text in &emu.display {
+        nes.debugger.add_watch(text).map_err(|e| e.to_string())?;
+    }
+    for watchpoint in &emu.watchpoints {
+        nes.cpu.bus.watchpoints.add(watchpoint.range.clone(), watchpoint.access);
+    }
+    if let Some(addr) = emu.debug_port {
+        nes.cpu.bus.debug_port = Some(DebugPort::new(addr));
+    }
+
+    match cartridge {
+        Some(cartridge) => nes.insert_cartridge(cartridge),
+        None => nes.load(&data),
+    }
+    .map_err(|e| e.to_string())?;
+    // after loading, which starts with nothing frozen
+    for &(addr, value) in &emu.freeze {
+        nes.cpu.bus.freeze(addr, value);
+    }
+
+    if let Some(path) = &emu.trace {
+        if path.as_os_str() == "-" {
+            let mut out = io::stdout();
+            nes.cpu.set_trace_sink(Box::new(move |entry: &TraceEntry| {
+                let _ = writeln!(out, "{}", entry);
+            }));
+        } else {
+            nes.cpu.set_trace_sink(Box::new(TraceLog::create(path, emu.trace_split, emu.trace_keep)?));
+        }
+
+        nes.cpu.set_trace_filter(TraceFilter {
+            ranges: emu.trace_ranges.clone(),
+            banks: emu.trace_banks.clone(),
+            mnemonics: emu.trace_ops.clone(),
+        });
+    }
+
+    let mut game = Game::new(nes);
+    game.show_watches_each_frame = emu.display_each_frame;
+    game.set_palette(palette);
+    game.save_flush = emu.save_flush;
+    if emu.deterministic {
+        game.seed_rng(0);
+    }
+    if let Some(path) = rom {
+        game.open_save(path)?;
+    }
+
+    #[cfg(feature = "lua")]
+    if let Some(path) = &emu.script {
+        game.load_script(path)?;
+    }
+
+    if let Some(path) = &emu.events {
+        game.log_events(path);
+    }
+
+    if let Some(path) = &emu.record {
+        game.start_recording(path)?;
+    }
+
+    Ok((game, config))
 }
 
-fn main() {
-    // init sdl2
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem
-        .window("Snake game", (32.0 * 10.0) as u32, (32.0 * 10.0) as u32)
-        .position_centered()
-        .build().unwrap();
-
-    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
-    let mut event_pump = sdl_context.event_pump().unwrap();
-    canvas.set_scale(10.0, 10.0).unwrap();
-
-    let creator = canvas.texture_creator();
-    let mut texture = creator
-        .create_texture_target(PixelFormatEnum::RGB24, 32, 32).unwrap();
-    
-
-    // let game_code = vec![0xA2, 0x0A, 0x8E, 0x00, 0x00, 0xA2, 0x03, 0x8E, 0x01, 0x00, 0xAC, 0x00, 0x00, 0xA9, 0x00, 0x18, 0x6D, 0x01, 0x00, 0x88, 0xD0, 0xFA, 0x8D, 0x02, 0x00, 0xEA, 0xEA, 0xEA];
-    // let game_code = vec![
-    //     0x20, 0x06, 0x06, 0x20, 0x38, 0x06, 0x20, 0x0d, 0x06, 0x20, 0x2a, 0x06, 0x60, 0xa9, 0x02, 0x85, 
-    //     0x02, 0xa9, 0x04, 0x85, 0x03, 0xa9, 0x11, 0x85, 0x10, 0xa9, 0x10, 0x85, 0x12, 0xa9, 0x0f, 0x85, 
-    //     0x14, 0xa9, 0x04, 0x85, 0x11, 0x85, 0x13, 0x85, 0x15, 0x60, 0xa5, 0xfe, 0x85, 0x00, 0xa5, 0xfe, 
-    //     0x29, 0x03, 0x18, 0x69, 0x02, 0x85, 0x01, 0x60, 0x20, 0x4d, 0x06, 0x20, 0x8d, 0x06, 0x20, 0xc3, 
-    //     0x06, 0x20, 0x19, 0x07, 0x20, 0x20, 0x07, 0x20, 0x2d, 0x07, 0x4c, 0x38, 0x06, 0xa5, 0xff, 0xc9, 
-    //     0x77, 0xf0, 0x0d, 0xc9, 0x64, 0xf0, 0x14, 0xc9, 0x73, 0xf0, 0x1b, 0xc9, 0x61, 0xf0, 0x22, 0x60, 
-    //     0xa9, 0x04, 0x24, 0x02, 0xd0, 0x26, 0xa9, 0x01, 0x85, 0x02, 0x60, 0xa9, 0x08, 0x24, 0x02, 0xd0, 
-    //     0x1b, 0xa9, 0x02, 0x85, 0x02, 0x60, 0xa9, 0x01, 0x24, 0x02, 0xd0, 0x10, 0xa9, 0x04, 0x85, 0x02, 
-    //     0x60, 0xa9, 0x02, 0x24, 0x02, 0xd0, 0x05, 0xa9, 0x08, 0x85, 0x02, 0x60, 0x60, 0x20, 0x94, 0x06, 
-    //     0x20, 0xa8, 0x06, 0x60, 0xa5, 0x00, 0xc5, 0x10, 0xd0, 0x0d, 0xa5, 0x01, 0xc5, 0x11, 0xd0, 0x07, 
-    //     0xe6, 0x03, 0xe6, 0x03, 0x20, 0x2a, 0x06, 0x60, 0xa2, 0x02, 0xb5, 0x10, 0xc5, 0x10, 0xd0, 0x06, 
-    //     0xb5, 0x11, 0xc5, 0x11, 0xf0, 0x09, 0xe8, 0xe8, 0xe4, 0x03, 0xf0, 0x06, 0x4c, 0xaa, 0x06, 0x4c, 
-    //     0x35, 0x07, 0x60, 0xa6, 0x03, 0xca, 0x8a, 0xb5, 0x10, 0x95, 0x12, 0xca, 0x10, 0xf9, 0xa5, 0x02, 
-    //     0x4a, 0xb0, 0x09, 0x4a, 0xb0, 0x19, 0x4a, 0xb0, 0x1f, 0x4a, 0xb0, 0x2f, 0xa5, 0x10, 0x38, 0xe9, 
-    //     0x20, 0x85, 0x10, 0x90, 0x01, 0x60, 0xc6, 0x11, 0xa9, 0x01, 0xc5, 0x11, 0xf0, 0x28, 0x60, 0xe6, 
-    //     0x10, 0xa9, 0x1f, 0x24, 0x10, 0xf0, 0x1f, 0x60, 0xa5, 0x10, 0x18, 0x69, 0x20, 0x85, 0x10, 0xb0, 
-    //     0x01, 0x60, 0xe6, 0x11, 0xa9, 0x06, 0xc5, 0x11, 0xf0, 0x0c, 0x60, 0xc6, 0x10, 0xa5, 0x10, 0x29, 
-    //     0x1f, 0xc9, 0x1f, 0xf0, 0x01, 0x60, 0x4c, 0x35, 0x07, 0xa0, 0x00, 0xa5, 0xfe, 0x91, 0x00, 0x60, 
-    //     0xa6, 0x03, 0xa9, 0x00, 0x81, 0x10, 0xa2, 0x00, 0xa9, 0x01, 0x81, 0x10, 0x60, 0xa2, 0x00, 0xea, 
-    //     0xea, 0xca, 0xd0, 0xfb, 0x60 
-    // ];
-    let game_code = vec![
-        0x20, 0x06, 0x06, 0x20, 0x38, 0x06, 0x20, 0x0d, 0x06, 0x20, 0x2a, 0x06, 0x60, 0xa9, 0x02,
-        0x85, 0x02, 0xa9, 0x04, 0x85, 0x03, 0xa9, 0x11, 0x85, 0x10, 0xa9, 0x10, 0x85, 0x12, 0xa9,
-        0x0f, 0x85, 0x14, 0xa9, 0x04, 0x85, 0x11, 0x85, 0x13, 0x85, 0x15, 0x60, 0xa5, 0xfe, 0x85,
-        0x00, 0xa5, 0xfe, 0x29, 0x03, 0x18, 0x69, 0x02, 0x85, 0x01, 0x60, 0x20, 0x4d, 0x06, 0x20,
-        0x8d, 0x06, 0x20, 0xc3, 0x06, 0x20, 0x19, 0x07, 0x20, 0x20, 0x07, 0x20, 0x2d, 0x07, 0x4c,
-        0x38, 0x06, 0xa5, 0xff, 0xc9, 0x77, 0xf0, 0x0d, 0xc9, 0x64, 0xf0, 0x14, 0xc9, 0x73, 0xf0,
-        0x1b, 0xc9, 0x61, 0xf0, 0x22, 0x60, 0xa9, 0x04, 0x24, 0x02, 0xd0, 0x26, 0xa9, 0x01, 0x85,
-        0x02, 0x60, 0xa9, 0x08, 0x24, 0x02, 0xd0, 0x1b, 0xa9, 0x02, 0x85, 0x02, 0x60, 0xa9, 0x01,
-        0x24, 0x02, 0xd0, 0x10, 0xa9, 0x04, 0x85, 0x02, 0x60, 0xa9, 0x02, 0x24, 0x02, 0xd0, 0x05,
-        0xa9, 0x08, 0x85, 0x02, 0x60, 0x60, 0x20, 0x94, 0x06, 0x20, 0xa8, 0x06, 0x60, 0xa5, 0x00,
-        0xc5, 0x10, 0xd0, 0x0d, 0xa5, 0x01, 0xc5, 0x11, 0xd0, 0x07, 0xe6, 0x03, 0xe6, 0x03, 0x20,
-        0x2a, 0x06, 0x60, 0xa2, 0x02, 0xb5, 0x10, 0xc5, 0x10, 0xd0, 0x06, 0xb5, 0x11, 0xc5, 0x11,
-        0xf0, 0x09, 0xe8, 0xe8, 0xe4, 0x03, 0xf0, 0x06, 0x4c, 0xaa, 0x06, 0x4c, 0x35, 0x07, 0x60,
-        0xa6, 0x03, 0xca, 0x8a, 0xb5, 0x10, 0x95, 0x12, 0xca, 0x10, 0xf9, 0xa5, 0x02, 0x4a, 0xb0,
-        0x09, 0x4a, 0xb0, 0x19, 0x4a, 0xb0, 0x1f, 0x4a, 0xb0, 0x2f, 0xa5, 0x10, 0x38, 0xe9, 0x20,
-        0x85, 0x10, 0x90, 0x01, 0x60, 0xc6, 0x11, 0xa9, 0x01, 0xc5, 0x11, 0xf0, 0x28, 0x60, 0xe6,
-        0x10, 0xa9, 0x1f, 0x24, 0x10, 0xf0, 0x1f, 0x60, 0xa5, 0x10, 0x18, 0x69, 0x20, 0x85, 0x10,
-        0xb0, 0x01, 0x60, 0xe6, 0x11, 0xa9, 0x06, 0xc5, 0x11, 0xf0, 0x0c, 0x60, 0xc6, 0x10, 0xa5,
-        0x10, 0x29, 0x1f, 0xc9, 0x1f, 0xf0, 0x01, 0x60, 0x4c, 0x35, 0x07, 0xa0, 0x00, 0xa5, 0xfe,
-        0x91, 0x00, 0x60, 0xa6, 0x03, 0xa9, 0x00, 0x81, 0x10, 0xa2, 0x00, 0xa9, 0x01, 0x81, 0x10,
-        0x60, 0xa6, 0xff, 0xea, 0xea, 0xca, 0xd0, 0xfb, 0x60,
-    ];
-   
-    let bus = Bus::new();
-    let mut cpu = CPU::new(bus);
-    cpu.load(&game_code);
-    cpu.reset();
-
-    let mut screen_state = [0 as u8; 32 * 3 * 32];
-    let mut rng = rand::thread_rng();
-
-    println!("______________ START ______________");
-
-    // CPU::disassemble(&game_code);
-    // run the game cycle
-    loop {
-        handle_user_input(&mut cpu, &mut event_pump);
-
-        cpu.write(0xfe, rng.gen_range(1..16));
-
-        if read_screen_state(&cpu, &mut screen_state) {
-            texture.update(None, &screen_state, 32 * 3).unwrap();
-
-            canvas.copy(&texture, None, None).unwrap();
-
-            canvas.present();
+/// Prints the statistics asked for on the command line: the hottest
+/// routines and the opcodes that ran.
+fn print_stats(nes: &Nes) {
+    if nes.profiler.is_enabled() {
+        print_profile(nes);
+    }
+    if nes.opcode_stats.is_enabled() {
+        print_opcode_stats(nes);
+    }
+}
+
+fn print_opcode_stats(nes: &Nes) {
+    let total = nes.opcode_stats.total().max(1) as f64;
+    println!("{:>12} {:>7}  opcode", "count", "share");
+    for (opcode, count) in nes.opcode_stats.report() {
+        // only official opcodes run, so the lookup can't miss
+        let op = &OPCODES[&opcode];
+        println!(
+            "{:>12} {:>6.2}%  ${:02X} {} {:?}",
+            count,
+            count as f64 * 100.0 / total,
+            opcode,
+            op.name,
+            op.addressing_mode
+        );
+    }
+}
+
+fn print_profile(nes: &Nes) {
+    let total = nes.profiler.total_cycles().max(1) as f64;
+    println!("{:>12} {:>7} {:>7} {:>8}  routine", "self cycles", "self%", "total%", "calls");
+    for (routine, stats) in nes.profiler.report().iter().take(PROFILE_ROWS) {
+        let name = match routine {
+            Some(routine) => routine.to_string(),
+            None => "(top level)".to_string(),
+        };
+        println!(
+            "{:>12} {:>6.2}% {:>6.2}% {:>8}  {}",
+            stats.self_cycles,
+            stats.self_cycles as f64 * 100.0 / total,
+            stats.total_cycles as f64 * 100.0 / total,
+            stats.calls,
+            name
+        );
+    }
+}
+
+fn title(rom: Option<&Path>) -> String {
+    match rom.and_then(|path| path.file_stem()) {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => "Snake game".to_string(),
+    }
+}
+
+fn run(mut game: Game, config: GameConfig, title: &str, window: &WindowArgs) -> Result<ExitCode, String> {
+    let mut bindings = Bindings::new();
+    for (input, action) in window.bindings.iter().cloned() {
+        bindings.bind(input, action);
+    }
+
+    let settings = Settings {
+        title: title.to_string(),
+        scale: window.scale,
+        overscan: window.overscan.or(config.overscan).unwrap_or(0),
+        bindings,
+        game_bindings: config.bindings()?,
+        clip_format: window.clip_format,
+        fullscreen: window.fullscreen,
+        integer_scale: !window.stretch,
+        aspect_correction: window.aspect_correction,
+        filter: window.filter,
+        pacing: window.pacing,
+        #[cfg(feature = "gamepad")]
+        deadzone: window.deadzone,
+        #[cfg(feature = "gamepad")]
+        gamepads: window.gamepads.clone(),
+        #[cfg(feature = "remote")]
+        remote: window.remote,
+    };
+
+    game.show_input = window.input_display;
+    game.show_stats = window.stats;
+    if let Some(path) = &window.record_movie {
+        game.record_movie(path);
+    }
+
+    let frames = window.clip_seconds as f64 * game.nes.region().frame_rate();
+    game.keep_history(frames.ceil() as usize);
+
+    // winit is the only window there is in a build without SDL
+    #[cfg(feature = "winit")]
+    if window.winit || !cfg!(feature = "sdl") {
+        winit_frontend::run(game, settings)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    run_sdl(game, settings)
+}
+
+#[cfg(not(feature = "sdl"))]
+fn run_sdl(_game: Game, _settings: Settings) -> Result<ExitCode, String> {
+    Err("built without a window; enable the sdl or winit feature, or use a headless command".to_string())
+}
+
+#[cfg(feature = "sdl")]
+fn run_sdl(mut game: Game, settings: Settings) -> Result<ExitCode, String> {
+    let pacing = settings.pacing;
+    let mut frontend = SdlFrontend::new(settings)?;
+    let mut timer = FrameTimer::new();
+
+    // run the game cycle, one vsync'd or timed frame at a time
+    while frontend.handle_input(&mut game) {
+        if pacing == Pacing::Timer {
+            timer.wait(game.nes.region().frame_rate());
+        }
+
+        match game.tick() {
+            Ok(true) => {},
+            Ok(false) => break,
+            Err(e) => return Err(e.to_string()),
         }
 
-        ::std::thread::sleep(std::time::Duration::new(0, 1000));
+        // with vsync, presenting every frame is what paces the loop
+        frontend.present(game.screen())?;
+    }
+
+    if let Err(e) = game.flush_saves() {
+        eprintln!("failed to write save: {}", e);
+    }
+    if let Err(e) = game.finish_movie() {
+        eprintln!("{}", e);
+    }
+    if let Err(e) = game.write_events() {
+        eprintln!("{}", e);
+    }
+    print_stats(&game.nes);
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn disasm(path: &Path, output: Option<&Path>, cdl: Option<&Path>) -> Result<ExitCode, String> {
+    let data = read(path)?;
+
+    if let Some(output) = output {
+        let default_cdl = path.with_extension("cdl");
+        let cdl = match cdl {
+            Some(cdl) => Some(read(cdl)?),
+            None if default_cdl.is_file() => Some(read(&default_cdl)?),
+            None => None,
+        };
+
+        let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        let listing = listing::export(&name, &data, cdl.as_deref())?;
+        fs::write(output, listing).map_err(|e| format!("{}: {}", output.display(), e))?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if Cartridge::is_ines(&data) {
+        let cartridge = Cartridge::from_ines(&data).map_err(|e| e.to_string())?;
+        let prg_rom = &cartridge.prg_rom;
+        // a 16KB image sits at $C000, anything larger starts at $8000
+        let origin = 0x10000 - prg_rom.len().min(0x8000);
+        disassemble(prg_rom, origin);
+    } else {
+        disassemble(&data, 0x0600);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn recent() -> Result<ExitCode, String> {
+    for (i, rom) in RecentRoms::load().roms.iter().enumerate() {
+        println!("{:2}. {}", i + 1, rom.display());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn info(path: &Path) -> Result<ExitCode, String> {
+    let cartridge = Cartridge::from_ines(&read(path)?).map_err(|e| e.to_string())?;
 
-        cpu.clock();
+    let name = mapper::name(cartridge.mapper).unwrap_or("unknown");
+    let supported = if mapper::create(cartridge.clone()).is_ok() { "supported" } else { "unsupported" };
 
-        // cpu.cycles = 0;
+    println!("Format:     {}", if cartridge.nes2 { "NES 2.0" } else { "iNES" });
+    println!("Mapper:     {}.{} ({}, {})", cartridge.mapper, cartridge.submapper, name, supported);
+    println!("PRG ROM:    {} KB", cartridge.prg_rom.len() / 1024);
+    println!("CHR ROM:    {} KB", cartridge.chr_rom.len() / 1024);
+    println!("PRG RAM:    {} KB", cartridge.prg_ram_size / 1024);
+    println!("Mirroring:  {:?}", cartridge.mirroring);
+    println!("Battery:    {}", cartridge.battery);
+    println!("Trainer:    {}", cartridge.trainer.is_some());
+    println!("Region:     {:?}", cartridge.region);
+    println!("Console:    {:?}", cartridge.console);
+    if let Some(playchoice) = &cartridge.playchoice {
+        println!("INST ROM:   {} KB", playchoice.inst_rom.len() / 1024);
+        println!("PROM:       {}", playchoice.prom.is_some());
+    }
+    // the key per-game configs are stored under
+    println!("CRC32:      {:08X} (PRG + CHR)", cartridge.crc32());
+
+    Ok(ExitCode::SUCCESS)
+}
 
-        if cpu.complete {
-            break;
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Run { rom, emu, window } => {
+            load_game(rom.as_deref(), &emu)
+                .and_then(|(game, config)| run(game, config, &title(rom.as_deref()), &window))
+        },
+        Command::Recent => recent(),
+        Command::Disasm { rom, output, cdl } => disasm(&rom, output.as_deref(), cdl.as_deref()),
+        Command::Info { rom } => info(&rom),
+        Command::Test { rom, nestest: Some(log), .. } => headless::nestest(&rom, &log),
+        Command::Test { rom, lockstep: Some(trace), .. } => headless::lockstep(&rom, &trace),
+        Command::Test { rom, emu, frames, until, nestest: None, lockstep: None } => {
+            load_game(Some(&rom), &emu).map(|(game, _)| headless::run(game, frames, until))
+        },
+        Command::Bench { rom, emu, frames } => {
+            load_game(Some(&rom), &emu).and_then(|(game, _)| headless::bench(game, frames))
+        },
+        Command::Blargg { roms, emu, frames } => headless::blargg(&roms, &emu, frames),
+        Command::Regress { goldens, emu, update } => regress::run(&goldens, &emu, update),
+        Command::Suite { dir, rules, emu, frames, report } => {
+            suite::run(&dir, rules.as_deref(), &emu, frames, report.as_deref())
+        },
+        Command::PlayMovie { rom, movie, emu, window } => {
+            let text = fs::read_to_string(&movie).map_err(|e| format!("{}: {}", movie.display(), e));
+            text.and_then(|text| Movie::from_fm2(&text).map_err(|e| e.to_string()))
+                .and_then(|movie| {
+                    let (mut game, config) = load_game(Some(&rom), &emu)?;
+                    game.play_movie(movie);
+                    run(game, config, &title(Some(&rom)), &window)
+                })
+        },
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(1)
         }
     }
-}
\ No newline at end of file
+}
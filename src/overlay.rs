@@ -0,0 +1,38 @@
+use nes_core::controller::Button;
+
+use crate::game::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// where each button sits in a controller glyph: a d-pad, then Select,
+// Start, B and A along the middle row
+const LAYOUT: [(Button, u32, u32); 8] = [
+    (Button::Up, 1, 0),
+    (Button::Left, 0, 1),
+    (Button::Right, 2, 1),
+    (Button::Down, 1, 2),
+    (Button::Select, 3, 1),
+    (Button::Start, 4, 1),
+    (Button::B, 5, 1),
+    (Button::A, 6, 1),
+];
+
+const GLYPH_WIDTH: u32 = 7;
+const GLYPH_HEIGHT: u32 = 3;
+
+const PRESSED: [u8; 3] = [255, 255, 255];
+const RELEASED: [u8; 3] = [70, 70, 70];
+
+/// Draws the buttons held on each controller into the bottom corners of an
+/// RGB24 screen, player 1 on the left: lit when pressed, dim otherwise.
+pub fn draw_input(screen: &mut [u8], buttons: [u8; 2]) {
+    let y = SCREEN_HEIGHT - GLYPH_HEIGHT;
+    draw_controller(screen, 0, y, buttons[0]);
+    draw_controller(screen, SCREEN_WIDTH - GLYPH_WIDTH, y, buttons[1]);
+}
+
+fn draw_controller(screen: &mut [u8], x: u32, y: u32, buttons: u8) {
+    for (button, dx, dy) in LAYOUT {
+        let color = if buttons & button.mask() != 0 { PRESSED } else { RELEASED };
+        let offset = (((y + dy) * SCREEN_WIDTH + x + dx) * 3) as usize;
+        screen[offset..offset + 3].copy_from_slice(&color);
+    }
+}
@@ -0,0 +1,46 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+
+/// What decides when the next frame is run and shown.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Pacing {
+    /// The display's refresh, which is smooth as long as it runs close to
+    /// the console's frame rate.
+    Vsync,
+    /// A timer at the console's frame rate, for displays that refresh at
+    /// some other rate; frames may tear or be shown unevenly.
+    Timer,
+}
+
+impl Pacing {
+    pub fn vsync(&self) -> bool {
+        *self == Pacing::Vsync
+    }
+}
+
+/// Free-running clock for [`Pacing::Timer`].
+pub struct FrameTimer {
+    next: Option<Instant>,
+}
+
+impl FrameTimer {
+    pub fn new() -> FrameTimer {
+        FrameTimer { next: None }
+    }
+
+    /// Sleeps until a frame `frame_rate` says is due. After falling more
+    /// than a frame behind, e.g. while a window is dragged, the timer
+    /// starts over from now instead of rushing to catch up.
+    pub fn wait(&mut self, frame_rate: f64) {
+        let period = Duration::from_secs_f64(1.0 / frame_rate);
+        let now = Instant::now();
+        let due = self.next.unwrap_or(now);
+
+        if due > now {
+            thread::sleep(due - now);
+        }
+        self.next = Some(if now > due + period { now + period } else { due + period });
+    }
+}
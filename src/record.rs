@@ -0,0 +1,115 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use crate::game::{Game, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::screenshot;
+
+// recordings are upscaled so players don't blur the 32x32 screen
+const RECORD_SCALE: u32 = 8;
+
+/// Pipes raw RGB24 frames into an `ffmpeg` process that encodes them.
+pub struct Recorder {
+    path: PathBuf,
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+impl Recorder {
+    pub fn start(path: &Path, width: u32, height: u32, frame_rate: f64) -> Result<Recorder, String> {
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-y"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .args(["-s", &format!("{}x{}", width, height)])
+            .args(["-r", &format!("{:.4}", frame_rate)])
+            .args(["-i", "-"])
+            .args(["-vf", &format!("scale=iw*{0}:ih*{0}:flags=neighbor", RECORD_SCALE)])
+            .args(["-pix_fmt", "yuv420p"])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("failed to start ffmpeg: {}", e))?;
+
+        let stdin = child.stdin.take();
+
+        Ok(Recorder {
+            path: path.to_path_buf(),
+            child,
+            stdin,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn push(&mut self, rgb: &[u8]) -> Result<(), String> {
+        match self.stdin.as_mut() {
+            Some(stdin) => stdin.write_all(rgb).map_err(|e| format!("ffmpeg stopped: {}", e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Closes the pipe and waits for ffmpeg to finish writing the file.
+    pub fn finish(mut self) -> Result<PathBuf, String> {
+        self.close()?;
+        Ok(self.path.clone())
+    }
+
+    fn close(&mut self) -> Result<(), String> {
+        // dropping stdin sends EOF
+        if self.stdin.take().is_none() {
+            return Ok(());
+        }
+
+        let status = self.child.wait().map_err(|e| e.to_string())?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("ffmpeg exited with {}", status))
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+impl Game {
+    /// Starts encoding every emulated frame to `path`, so the video keeps
+    /// emulation speed whatever the display does.
+    pub fn start_recording(&mut self, path: &Path) -> Result<(), String> {
+        let frame_rate = self.nes.region().frame_rate();
+        self.recorder = Some(Recorder::start(path, SCREEN_WIDTH, SCREEN_HEIGHT, frame_rate)?);
+        Ok(())
+    }
+
+    /// Stops recording; returns the finished file, if one was being written.
+    pub fn stop_recording(&mut self) -> Option<Result<PathBuf, String>> {
+        self.recorder.take().map(Recorder::finish)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+}
+
+/// Starts a timestamped `.mp4` recording, or stops the current one, reporting what happened on the console.
+pub fn toggle(game: &mut Game, name: &str) {
+    if game.is_recording() {
+        match game.stop_recording() {
+            Some(Ok(path)) => println!("saved {}", path.display()),
+            Some(Err(e)) => eprintln!("recording failed: {}", e),
+            None => {},
+        }
+        return;
+    }
+
+    let path = screenshot::timestamped_path(name, "mp4");
+    match game.start_recording(&path) {
+        Ok(()) => println!("recording to {}", path.display()),
+        Err(e) => eprintln!("recording failed: {}", e),
+    }
+}
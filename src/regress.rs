@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use nes_core::cartridge;
+
+use crate::cli::EmuArgs;
+
+/// One ROM in a goldens file, with the screen hash it should end on.
+struct Golden {
+    line: usize,
+    frames: u64,
+    hash: Option<u32>,
+    rom: String,
+}
+
+/// Runs every ROM listed in `path` headless and compares a CRC32 of the
+/// screen after the given number of frames against the stored one. There
+/// is no APU yet, so there is no audio to hash.
+///
+/// Each line of the goldens file is `FRAMES HASH ROM`, with the ROM
+/// relative to the file and `-` as the hash of a new entry; `#` starts a
+/// comment. With `update`, the file is rewritten with the hashes seen.
+///
+/// Exits with 0 when every hash matches (or was updated) and 2 otherwise.
+pub fn run(path: &Path, emu: &EmuArgs, update: bool) -> Result<ExitCode, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let goldens = parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let mut failed = 0;
+    for golden in &goldens {
+        let hash = screen_hash(&base.join(&golden.rom), emu, golden.frames)?;
+
+        let status = match golden.hash {
+            Some(expected) if expected == hash => "ok".to_string(),
+            _ if update => {
+                lines[golden.line] = format!("{} {:08X} {}", golden.frames, hash, golden.rom);
+                format!("updated to {:08X}", hash)
+            },
+            Some(expected) => {
+                failed += 1;
+                format!("MISMATCH: expected {:08X}, got {:08X}", expected, hash)
+            },
+            None => {
+                failed += 1;
+                format!("no golden yet, got {:08X}", hash)
+            },
+        };
+        println!("{} ({} frames): {}", golden.rom, golden.frames, status);
+    }
+
+    if update {
+        let mut text = lines.join("\n");
+        text.push('\n');
+        fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+
+    println!("{}/{} match", goldens.len() - failed, goldens.len());
+    Ok(if failed == 0 { ExitCode::SUCCESS } else { ExitCode::from(2) })
+}
+
+fn parse(text: &str) -> Result<Vec<Golden>, String> {
+    let mut goldens = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_text = line.split('#').next().unwrap_or("").trim();
+        if line_text.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<_> = line_text.splitn(3, char::is_whitespace).collect();
+        let [frames, hash, rom] = fields[..] else {
+            return Err(format!("line {}: expected FRAMES HASH ROM", i + 1));
+        };
+
+        let frames = frames.parse().map_err(|_| format!("line {}: bad frame count '{}'", i + 1, frames))?;
+        let hash = match hash {
+            "-" => None,
+            hash => Some(u32::from_str_radix(hash, 16).map_err(|_| format!("line {}: bad hash '{}'", i + 1, hash))?),
+        };
+        goldens.push(Golden { line: i, frames, hash, rom: rom.trim().to_string() });
+    }
+
+    Ok(goldens)
+}
+
+// the same seed every run keeps the playground's random byte stable
+fn screen_hash(rom: &Path, emu: &EmuArgs, frames: u64) -> Result<u32, String> {
+    let (mut game, _) = crate::load_game(Some(rom), emu)?;
+    game.seed_rng(0);
+
+    for _ in 0..frames {
+        let running = game.run_frame().map_err(|e| format!("{}: {}", rom.display(), e))?;
+        if !running || game.nes.debugger.is_stopped() {
+            break;
+        }
+    }
+
+    Ok(cartridge::crc32(game.screen()))
+}
@@ -0,0 +1,337 @@
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use nes_core::expr::Expr;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tungstenite::handshake::server::{ErrorResponse, Request as Handshake, Response};
+use tungstenite::http::{header, StatusCode};
+use tungstenite::Message;
+
+use crate::game::Game;
+use crate::input::{self, Action, Hotkey};
+use crate::screenshot;
+use crate::settings::Settings;
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+// the method was understood but the emulator couldn't do it
+const FAILED: i64 = -32000;
+
+/// A message from a client, with where to send the response; `None` for a
+/// notification, which gets none.
+struct Request {
+    text: String,
+    reply: Sender<Option<String>>,
+}
+
+struct Error {
+    code: i64,
+    message: String,
+}
+
+impl Error {
+    fn new(code: i64, message: impl Into<String>) -> Error {
+        Error { code, message: message.into() }
+    }
+}
+
+/// Remote control over WebSocket, one JSON-RPC 2.0 request per text
+/// message, for external tools and bots.
+///
+/// Connections are served on their own threads, but requests are carried
+/// out by [`Remote::poll`] between frames, so they never see the console
+/// halfway through one.
+///
+/// Methods take named parameters:
+///
+/// - `pause`, `resume`, `advance` (one frame or instruction while paused)
+/// - `status` returns `frame`, `paused`, `pc` and the break `stopped` at
+/// - `read_memory {address, length}` returns the bytes, without the side
+///   effects of CPU reads
+/// - `write_memory {address, data}` writes the bytes as the CPU would
+/// - `add_breakpoint {address, condition?}`, `remove_breakpoint {address}`,
+///   `breakpoints`
+/// - `set_buttons {player, buttons}` holds exactly the buttons in the mask
+/// - `screenshot {save?}` returns the screen as `width`, `height` and
+///   `rgb`, or with `save` writes a timestamped PNG to the working
+///   directory, as F12 does, and returns its `path`
+/// - `quit`
+///
+/// Handshakes with an `Origin` header are refused: browsers always send
+/// one, and any web page could otherwise drive the emulator through a
+/// port on localhost.
+pub struct Remote {
+    requests: Receiver<Request>,
+}
+
+impl Remote {
+    pub fn start(addr: SocketAddr) -> Result<Remote, String> {
+        let listener = TcpListener::bind(addr).map_err(|e| format!("remote control on {}: {}", addr, e))?;
+        let (sender, requests) = mpsc::channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                thread::spawn(move || serve(stream, sender));
+            }
+        });
+        println!("remote control listening on ws://{}", addr);
+
+        Ok(Remote { requests })
+    }
+
+    /// Carries out pending requests. Stops at the first hotkey only the
+    /// frontend can carry out and returns it, leaving the rest queued.
+    pub fn poll(&mut self, game: &mut Game, settings: &mut Settings) -> Result<Option<Hotkey>, String> {
+        while let Ok(request) = self.requests.try_recv() {
+            let (response, hotkey) = respond(game, settings, &request.text);
+            // the client may have gone in the meantime
+            let _ = request.reply.send(response);
+
+            if let Some(hotkey) = hotkey? {
+                return Ok(Some(hotkey));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+// relays one client's messages to the frontend until it disconnects
+fn serve(stream: TcpStream, requests: Sender<Request>) {
+    let Ok(mut socket) = tungstenite::accept_hdr(stream, refuse_browsers) else {
+        return;
+    };
+
+    while let Ok(message) = socket.read() {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let (reply, response) = mpsc::channel();
+        if requests.send(Request { text, reply }).is_err() {
+            break;
+        }
+
+        match response.recv() {
+            Ok(Some(response)) => {
+                if socket.send(Message::Text(response)).is_err() {
+                    break;
+                }
+            },
+            Ok(None) => {/* a notification */},
+            Err(_) => break,
+        }
+    }
+}
+
+// the signature tungstenite's handshake callbacks have
+#[allow(clippy::result_large_err)]
+fn refuse_browsers(request: &Handshake, response: Response) -> Result<Response, ErrorResponse> {
+    if !request.headers().contains_key(header::ORIGIN) {
+        return Ok(response);
+    }
+
+    let mut error = ErrorResponse::new(Some("connections from web pages are not allowed".into()));
+    *error.status_mut() = StatusCode::FORBIDDEN;
+    Err(error)
+}
+
+// the response to send, if any, and what the frontend has to do
+fn respond(game: &mut Game, settings: &mut Settings, text: &str) -> (Option<String>, Result<Option<Hotkey>, String>) {
+    let request: Value = match serde_json::from_str(text) {
+        Ok(request) => request,
+        Err(e) => return (Some(error_response(Value::Null, Error::new(PARSE_ERROR, e.to_string()))), Ok(None)),
+    };
+
+    let id = request.get("id").cloned();
+    let method = request.get("method").and_then(Value::as_str);
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let (result, hotkey) = match method {
+        Some(method) => call(game, settings, method, params),
+        None => (Err(Error::new(INVALID_REQUEST, "missing method")), Ok(None)),
+    };
+
+    let response = id.map(|id| match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }).to_string(),
+        Err(error) => error_response(id, error),
+    });
+    (response, hotkey)
+}
+
+fn error_response(id: Value, error: Error) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": error.code, "message": error.message },
+        "id": id,
+    })
+    .to_string()
+}
+
+#[derive(Deserialize)]
+struct Address {
+    address: u16,
+}
+
+#[derive(Deserialize)]
+struct Read {
+    address: u16,
+    length: u16,
+}
+
+#[derive(Deserialize)]
+struct Write {
+    address: u16,
+    data: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct Breakpoint {
+    address: u16,
+    condition: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Buttons {
+    player: usize,
+    buttons: u8,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Capture {
+    save: bool,
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, Error> {
+    serde_json::from_value(params).map_err(|e| Error::new(INVALID_PARAMS, e.to_string()))
+}
+
+// hotkeys are carried out like the player's, so their errors stop
+// emulation the same way
+fn call(game: &mut Game, settings: &mut Settings, method: &str, params: Value) -> (Result<Value, Error>, Result<Option<Hotkey>, String>) {
+    let hotkey = match method {
+        "advance" => Hotkey::Advance,
+        "quit" => Hotkey::Quit,
+        _ => return (call_method(game, settings, method, params), Ok(None)),
+    };
+
+    let result = input::perform(game, settings, Action::Hotkey(hotkey), true, false);
+    let response = match &result {
+        Ok(_) => Ok(Value::Null),
+        Err(e) => Err(Error::new(FAILED, e.clone())),
+    };
+    (response, result)
+}
+
+fn call_method(game: &mut Game, settings: &Settings, method: &str, params: Value) -> Result<Value, Error> {
+    match method {
+        "pause" => {
+            game.set_paused(true);
+            Ok(Value::Null)
+        },
+        "resume" => {
+            game.set_paused(false);
+            Ok(Value::Null)
+        },
+        "status" => {
+            let stopped = game.nes.debugger.stopped().map(|stopped| {
+                json!({ "pc": stopped.pc, "reason": stopped.reason.to_string() })
+            });
+            Ok(json!({
+                "frame": game.frame(),
+                "paused": game.is_paused(),
+                "pc": game.nes.cpu.program_counter,
+                "stopped": stopped,
+            }))
+        },
+        "read_memory" => {
+            let Read { address, length } = parse_params(params)?;
+            let bytes: Vec<u8> = (0..length).map(|i| game.nes.cpu.peek(address.wrapping_add(i))).collect();
+            Ok(json!(bytes))
+        },
+        "write_memory" => {
+            let Write { address, data } = parse_params(params)?;
+            for (i, byte) in data.into_iter().enumerate() {
+                game.nes.cpu.write(address.wrapping_add(i as u16), byte);
+            }
+            Ok(Value::Null)
+        },
+        "add_breakpoint" => {
+            let Breakpoint { address, condition } = parse_params(params)?;
+            match condition {
+                Some(condition) => {
+                    let condition = Expr::parse(&condition).map_err(|e| Error::new(INVALID_PARAMS, e.to_string()))?;
+                    game.nes.debugger.add_conditional_breakpoint(address, condition);
+                },
+                None => game.nes.debugger.add_breakpoint(address),
+            }
+            Ok(Value::Null)
+        },
+        "remove_breakpoint" => {
+            let Address { address } = parse_params(params)?;
+            Ok(json!(game.nes.debugger.remove_breakpoint(address)))
+        },
+        "breakpoints" => Ok(json!(game.nes.debugger.breakpoints().collect::<Vec<u16>>())),
+        "set_buttons" => {
+            let Buttons { player, buttons } = parse_params(params)?;
+            match player {
+                // through the game, so playground programs see the key too
+                0 => game.set_buttons(buttons),
+                1 => game.nes.cpu.bus.controllers[1].set_buttons(buttons),
+                _ => return Err(Error::new(INVALID_PARAMS, format!("no controller {}", player))),
+            }
+            Ok(Value::Null)
+        },
+        "screenshot" => {
+            let Capture { save } = match params {
+                Value::Null => Capture::default(),
+                params => parse_params(params)?,
+            };
+            if save {
+                let path = screenshot::capture(game, &settings.title, settings.overscan)
+                    .map_err(|e| Error::new(FAILED, e))?;
+                return Ok(json!({ "path": path.display().to_string() }));
+            }
+
+            let screenshot = game.screenshot(settings.overscan);
+            Ok(json!({
+                "width": screenshot.width,
+                "height": screenshot.height,
+                "rgb": screenshot.rgb,
+            }))
+        },
+        _ => Err(Error::new(METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the status the handshake is answered with
+    fn handshake(origin: Option<&str>) -> StatusCode {
+        let mut request = Handshake::builder().uri("/");
+        if let Some(origin) = origin {
+            request = request.header(header::ORIGIN, origin);
+        }
+        match refuse_browsers(&request.body(()).unwrap(), Response::default()) {
+            Ok(response) => response.status(),
+            Err(error) => error.status(),
+        }
+    }
+
+    #[test]
+    fn refuses_handshakes_from_web_pages() {
+        assert_eq!(handshake(None), StatusCode::OK);
+        assert_eq!(handshake(Some("https://example.com")), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// When battery saves are written to disk.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SaveFlush {
+    /// Once the save has stopped changing for this long, so a game
+    /// writing it byte by byte doesn't hit the disk each frame.
+    OnChange(Duration),
+    /// At most this often, when something changed.
+    Every(Duration),
+    /// Only when the game is closed or switched.
+    OnExit,
+}
+
+impl Default for SaveFlush {
+    fn default() -> Self {
+        SaveFlush::OnChange(Duration::from_secs(1))
+    }
+}
+
+impl SaveFlush {
+    /// Parses `exit`, `change[:MS]` or `every:SECONDS`.
+    pub fn parse(text: &str) -> Result<SaveFlush, String> {
+        let (kind, value) = match text.split_once(':') {
+            Some((kind, value)) => (kind, Some(value)),
+            None => (text, None),
+        };
+        let number = |value: &str| value.parse::<u64>().map_err(|e| format!("bad number '{}': {}", value, e));
+
+        match (kind, value) {
+            ("exit", None) => Ok(SaveFlush::OnExit),
+            ("change", None) => Ok(SaveFlush::default()),
+            ("change", Some(ms)) => Ok(SaveFlush::OnChange(Duration::from_millis(number(ms)?))),
+            ("every", Some(seconds)) => Ok(SaveFlush::Every(Duration::from_secs(number(seconds)?))),
+            _ => Err(format!("expected exit, change[:MS] or every:SECONDS, got '{}'", text)),
+        }
+    }
+}
+
+/// The `.sav` file kept next to a ROM for its cartridge's battery-backed
+/// memory, and what of it has reached the disk.
+pub struct SaveFile {
+    path: PathBuf,
+    // the contents last written, and last seen in the cartridge
+    written: Vec<u8>,
+    seen: Vec<u8>,
+    changed_at: Instant,
+    flushed_at: Instant,
+}
+
+impl SaveFile {
+    /// The save for `rom`. Returns what's already on disk too, if anything.
+    pub fn open(rom: &Path) -> Result<(SaveFile, Option<Vec<u8>>), String> {
+        let path = rom.with_extension("sav");
+        let data = match fs::read(&path) {
+            Ok(data) => Some(data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+
+        let now = Instant::now();
+        let save = SaveFile {
+            path,
+            written: Vec::new(),
+            seen: Vec::new(),
+            changed_at: now,
+            flushed_at: now,
+        };
+        Ok((save, data))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Takes the cartridge's memory as it starts out, so only later changes
+    /// count as unsaved.
+    pub fn sync(&mut self, data: &[u8]) {
+        self.written = data.to_vec();
+        self.seen = data.to_vec();
+    }
+
+    /// Called once a frame with the cartridge's memory; says whether
+    /// `flush` should write it now.
+    pub fn due(&mut self, data: &[u8], policy: SaveFlush) -> bool {
+        let now = Instant::now();
+        if data != self.seen {
+            self.seen.clear();
+            self.seen.extend_from_slice(data);
+            self.changed_at = now;
+        }
+
+        let unsaved = self.seen != self.written;
+        match policy {
+            SaveFlush::OnChange(delay) => unsaved && now - self.changed_at >= delay,
+            SaveFlush::Every(interval) => unsaved && now - self.flushed_at >= interval,
+            SaveFlush::OnExit => false,
+        }
+    }
+
+    /// Writes `data` if it differs from what's on disk. A failed write
+    /// isn't retried until the data changes again.
+    pub fn flush(&mut self, data: &[u8]) -> Result<(), String> {
+        self.flushed_at = Instant::now();
+        if data == self.written {
+            return Ok(());
+        }
+
+        self.written.clear();
+        self.written.extend_from_slice(data);
+        fs::write(&self.path, data).map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
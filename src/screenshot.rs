@@ -0,0 +1,61 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::{Game, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// A copy of the screen as RGB24.
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+}
+
+impl Screenshot {
+    pub fn write_png(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        writer.write_image_data(&self.rgb).map_err(|e| e.to_string())
+    }
+}
+
+impl Game {
+    /// Copies the current screen, dropping `overscan` rows from the top and
+    /// bottom.
+    pub fn screenshot(&self, overscan: u32) -> Screenshot {
+        let overscan = overscan.min(SCREEN_HEIGHT / 2);
+        let row = SCREEN_WIDTH as usize * 3;
+        let start = overscan as usize * row;
+        let end = (SCREEN_HEIGHT - overscan) as usize * row;
+
+        Screenshot {
+            width: SCREEN_WIDTH,
+            height: SCREEN_HEIGHT - overscan * 2,
+            rgb: self.screen()[start..end].to_vec(),
+        }
+    }
+}
+
+/// `<name>-<unix millis>.<extension>` in the working directory, for files
+/// captured from a hotkey.
+pub fn timestamped_path(name: &str, extension: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+
+    PathBuf::from(format!("{}-{}.{}", name, millis, extension))
+}
+
+/// Writes a timestamped screenshot and returns its path.
+pub fn capture(game: &Game, name: &str, overscan: u32) -> Result<PathBuf, String> {
+    let path = timestamped_path(name, "png");
+    game.screenshot(overscan).write_png(&path)?;
+    Ok(path)
+}
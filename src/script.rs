@@ -0,0 +1,315 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+
+use mlua::{Function, Lua, Table, Thread, ThreadStatus, Value};
+use nes_core::controller::Button;
+
+use crate::game::{Game, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// the names FCEUX uses for buttons in joypad tables
+const BUTTONS: [(&str, Button); 8] = [
+    ("A", Button::A),
+    ("B", Button::B),
+    ("select", Button::Select),
+    ("start", Button::Start),
+    ("up", Button::Up),
+    ("down", Button::Down),
+    ("left", Button::Left),
+    ("right", Button::Right),
+];
+
+type Pixel = (u32, u32, [u8; 3]);
+
+/// A Lua script with an FCEUX-like API: `memory.readbyte`/`readword`/
+/// `writebyte`, `joypad.get`/`set`, `gui.pixel`/`line`/`box`, and
+/// `emu.frameadvance`/`framecount`/`registerbefore`/`registerafter`/
+/// `message`.
+///
+/// The main chunk runs as a coroutine that `emu.frameadvance()` yields
+/// back to the emulator, once per frame.
+pub struct Script {
+    lua: Lua,
+    main: Thread,
+    started: bool,
+    // what the gui functions drew this frame
+    overlay: Vec<Pixel>,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Script, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let lua = Lua::new();
+        let main = setup(&lua, &source, &path.display().to_string()).map_err(|e| e.to_string())?;
+
+        Ok(Script { lua, main, started: false, overlay: Vec::new() })
+    }
+
+    /// Starts the main chunk on the first frame, then calls the
+    /// `registerbefore` function.
+    pub fn before_frame(&mut self, game: &mut Game) -> Result<(), String> {
+        self.overlay.clear();
+
+        let main = (!self.started).then(|| self.main.clone());
+        self.started = true;
+        self.with_api(game, |lua| {
+            if let Some(main) = main {
+                main.resume::<()>(())?;
+            }
+            call_registered(lua, "before")
+        })
+    }
+
+    /// Calls the `registerafter` function, lets the main chunk run up to
+    /// its next `emu.frameadvance()`, and draws the overlay.
+    pub fn after_frame(&mut self, game: &mut Game) -> Result<(), String> {
+        let main = self.main.clone();
+        self.with_api(game, |lua| {
+            call_registered(lua, "after")?;
+            if main.status() == ThreadStatus::Resumable {
+                main.resume::<()>(())?;
+            }
+            Ok(())
+        })?;
+
+        let screen = game.screen_mut();
+        for &(x, y, rgb) in &self.overlay {
+            let i = ((y * SCREEN_WIDTH + x) * 3) as usize;
+            screen[i..i + 3].copy_from_slice(&rgb);
+        }
+
+        Ok(())
+    }
+
+    // the functions that touch the game only exist while `run` does
+    fn with_api(&mut self, game: &mut Game, run: impl FnOnce(&Lua) -> mlua::Result<()>) -> Result<(), String> {
+        let Script { lua, overlay, .. } = self;
+        let game = RefCell::new(game);
+        let overlay = RefCell::new(overlay);
+
+        let result = lua.scope(|scope| {
+            let globals = lua.globals();
+
+            let memory: Table = globals.get("memory")?;
+            memory.set(
+                "readbyte",
+                scope.create_function(|_, addr: u16| Ok(game.borrow().nes.cpu.peek(addr)))?,
+            )?;
+            memory.set(
+                "readword",
+                scope.create_function(|_, addr: u16| {
+                    let cpu = &game.borrow().nes.cpu;
+                    Ok(u16::from_le_bytes([cpu.peek(addr), cpu.peek(addr.wrapping_add(1))]))
+                })?,
+            )?;
+            memory.set(
+                "writebyte",
+                scope.create_function(|_, (addr, value): (u16, u8)| {
+                    game.borrow_mut().nes.cpu.bus.poke(addr, value);
+                    Ok(())
+                })?,
+            )?;
+
+            let joypad: Table = globals.get("joypad")?;
+            joypad.set(
+                "get",
+                scope.create_function(|lua, port: usize| {
+                    let buttons = game.borrow().nes.cpu.bus.controllers[port_index(port)?].buttons();
+                    let table = lua.create_table()?;
+                    for (name, button) in BUTTONS {
+                        table.set(name, buttons & button.mask() != 0)?;
+                    }
+                    Ok(table)
+                })?,
+            )?;
+            joypad.set(
+                "set",
+                scope.create_function(|_, (port, table): (usize, Table)| {
+                    let index = port_index(port)?;
+                    let mut game = game.borrow_mut();
+                    // buttons left out keep the player's input
+                    for (name, button) in BUTTONS {
+                        if let Some(pressed) = table.get::<Option<bool>>(name)? {
+                            match index {
+                                0 => game.press(button, pressed),
+                                _ => game.nes.cpu.bus.controllers[index].set_button(button, pressed),
+                            }
+                        }
+                    }
+                    Ok(())
+                })?,
+            )?;
+
+            let gui: Table = globals.get("gui")?;
+            gui.set(
+                "pixel",
+                scope.create_function(|_, (x, y, color): (i32, i32, Value)| {
+                    if let Some(rgb) = parse_color(&color)? {
+                        plot(&mut overlay.borrow_mut(), x, y, rgb);
+                    }
+                    Ok(())
+                })?,
+            )?;
+            gui.set(
+                "line",
+                scope.create_function(|_, (x1, y1, x2, y2, color): (i32, i32, i32, i32, Value)| {
+                    if let Some(rgb) = parse_color(&color)? {
+                        let overlay = &mut overlay.borrow_mut();
+                        // keeps absurd coordinates from spinning for ages
+                        let [x1, y1, x2, y2] = [x1, y1, x2, y2].map(|v| v.clamp(-4096, 4096));
+                        // Bresenham
+                        let (dx, dy) = ((x2 - x1).abs(), -(y2 - y1).abs());
+                        let (sx, sy) = ((x2 - x1).signum(), (y2 - y1).signum());
+                        let (mut x, mut y, mut error) = (x1, y1, dx + dy);
+                        loop {
+                            plot(overlay, x, y, rgb);
+                            if x == x2 && y == y2 {
+                                break;
+                            }
+                            if 2 * error >= dy {
+                                error += dy;
+                                x += sx;
+                            }
+                            if 2 * error <= dx {
+                                error += dx;
+                                y += sy;
+                            }
+                        }
+                    }
+                    Ok(())
+                })?,
+            )?;
+            gui.set(
+                "box",
+                scope.create_function(|_, (x1, y1, x2, y2, fill, outline): (i32, i32, i32, i32, Value, Value)| {
+                    let fill = parse_color(&fill)?;
+                    let outline = match outline {
+                        Value::Nil => fill,
+                        outline => parse_color(&outline)?,
+                    };
+                    let overlay = &mut overlay.borrow_mut();
+
+                    // clipped to the screen up front so huge boxes stay cheap
+                    let xs = x1.min(x2).max(0)..=x1.max(x2).min(SCREEN_WIDTH as i32 - 1);
+                    let ys = y1.min(y2).max(0)..=y1.max(y2).min(SCREEN_HEIGHT as i32 - 1);
+                    for y in ys {
+                        for x in xs.clone() {
+                            let edge = x == x1 || x == x2 || y == y1 || y == y2;
+                            if let Some(rgb) = if edge { outline } else { fill } {
+                                plot(overlay, x, y, rgb);
+                            }
+                        }
+                    }
+                    Ok(())
+                })?,
+            )?;
+
+            let emu: Table = globals.get("emu")?;
+            emu.set("framecount", scope.create_function(|_, ()| Ok(game.borrow().frame()))?)?;
+
+            run(lua)
+        });
+
+        result.map_err(|e| e.to_string())
+    }
+}
+
+// the parts of the API that don't need the game
+fn setup(lua: &Lua, source: &str, name: &str) -> mlua::Result<Thread> {
+    let globals = lua.globals();
+
+    let emu = lua.create_table()?;
+    let coroutine: Table = globals.get("coroutine")?;
+    emu.set("frameadvance", coroutine.get::<Function>("yield")?)?;
+    emu.set(
+        "registerbefore",
+        lua.create_function(|lua, function: Option<Function>| lua.set_named_registry_value("before", function))?,
+    )?;
+    emu.set(
+        "registerafter",
+        lua.create_function(|lua, function: Option<Function>| lua.set_named_registry_value("after", function))?,
+    )?;
+    emu.set(
+        "message",
+        lua.create_function(|_, text: String| {
+            println!("{}", text);
+            Ok(())
+        })?,
+    )?;
+    globals.set("emu", emu)?;
+
+    for table in ["memory", "joypad", "gui"] {
+        globals.set(table, lua.create_table()?)?;
+    }
+
+    let chunk = lua.load(source).set_name(name).into_function()?;
+    lua.create_thread(chunk)
+}
+
+fn call_registered(lua: &Lua, name: &str) -> mlua::Result<()> {
+    match lua.named_registry_value::<Option<Function>>(name)? {
+        Some(function) => function.call(()),
+        None => Ok(()),
+    }
+}
+
+// ports are numbered from 1, as in FCEUX
+fn port_index(port: usize) -> mlua::Result<usize> {
+    match port {
+        1 | 2 => Ok(port - 1),
+        _ => Err(mlua::Error::RuntimeError(format!("no joypad port {}", port))),
+    }
+}
+
+/// Colors are `0xRRGGBB`, `"#RRGGBB"` or a few names; `"clear"` and
+/// `nil` draw nothing.
+fn parse_color(color: &Value) -> mlua::Result<Option<[u8; 3]>> {
+    let rgb = match color {
+        Value::Nil => return Ok(None),
+        Value::Integer(rgb) => *rgb as u32,
+        Value::String(name) => match name.to_str()?.as_ref() {
+            "clear" => return Ok(None),
+            "white" => 0xFFFFFF,
+            "black" => 0x000000,
+            "red" => 0xFF0000,
+            "green" => 0x00FF00,
+            "blue" => 0x0000FF,
+            "yellow" => 0xFFFF00,
+            "gray" | "grey" => 0x808080,
+            hex => hex
+                .strip_prefix('#')
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| mlua::Error::RuntimeError(format!("bad color '{}'", hex)))?,
+        },
+        other => return Err(mlua::Error::RuntimeError(format!("bad color {:?}", other))),
+    };
+
+    Ok(Some([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]))
+}
+
+fn plot(overlay: &mut Vec<Pixel>, x: i32, y: i32, rgb: [u8; 3]) {
+    if (0..SCREEN_WIDTH as i32).contains(&x) && (0..SCREEN_HEIGHT as i32).contains(&y) {
+        overlay.push((x as u32, y as u32, rgb));
+    }
+}
+
+impl Game {
+    pub fn load_script(&mut self, path: &Path) -> Result<(), String> {
+        self.script = Some(Script::load(path)?);
+        Ok(())
+    }
+
+    /// Runs one phase of the script, dropping it if it fails so the game
+    /// carries on without it.
+    pub(crate) fn run_script(&mut self, phase: fn(&mut Script, &mut Game) -> Result<(), String>) {
+        let Some(mut script) = self.script.take() else {
+            return;
+        };
+
+        match phase(&mut script, self) {
+            Ok(()) => self.script = Some(script),
+            Err(e) => eprintln!("script stopped: {}", e),
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::path::Path;
+
+use nes_core::controller::Button;
+
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::{FullscreenType, Window};
+use sdl2::{EventPump, GameControllerSubsystem};
+
+use crate::config;
+use crate::filter::Filter;
+#[cfg(feature = "gamepad")]
+use crate::gamepad::Gamepads;
+use crate::game::{Game, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::input::{self, Action, Bindings, Hotkey, Input};
+#[cfg(feature = "remote")]
+use crate::remote::Remote;
+use crate::settings::Settings;
+
+/// Window, streaming texture and input devices for the desktop frontend.
+pub struct SdlFrontend {
+    canvas: Canvas<Window>,
+    texture: Texture,
+    event_pump: EventPump,
+    controller_subsystem: GameControllerSubsystem,
+    // kept open so SDL keeps delivering their events
+    controllers: Vec<GameController>,
+    settings: Settings,
+    // what inputs do when no binding in `settings` says otherwise
+    defaults: Bindings,
+    #[cfg(feature = "gamepad")]
+    gamepads: Gamepads,
+    #[cfg(feature = "remote")]
+    remote: Option<Remote>,
+    // the filter output, and the filter the texture is currently sized for
+    filtered: Vec<u8>,
+    texture_filter: Filter,
+}
+
+impl SdlFrontend {
+    pub fn new(settings: Settings) -> Result<SdlFrontend, String> {
+        let sdl_context = sdl2::init()?;
+        let video_subsystem = sdl_context.video()?;
+        let controller_subsystem = sdl_context.game_controller()?;
+
+        let (width, height) = settings.window_size();
+        let mut window = video_subsystem
+            .window(&settings.title, width, height)
+            .position_centered()
+            .resizable()
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        if settings.fullscreen {
+            window.set_fullscreen(FullscreenType::Desktop)?;
+        }
+
+        let mut canvas = window.into_canvas();
+        if settings.pacing.vsync() {
+            canvas = canvas.present_vsync();
+        }
+        let canvas = canvas.build().map_err(|e| e.to_string())?;
+
+        let texture = create_texture(&canvas, settings.filter)?;
+
+        let event_pump = sdl_context.event_pump()?;
+        #[cfg(feature = "gamepad")]
+        let gamepads = Gamepads::new(settings.deadzone, settings.gamepads.clone())?;
+        #[cfg(feature = "remote")]
+        let remote = settings.remote.map(Remote::start).transpose()?;
+
+        Ok(SdlFrontend {
+            canvas,
+            texture,
+            event_pump,
+            controller_subsystem,
+            controllers: Vec::new(),
+            texture_filter: settings.filter,
+            settings,
+            defaults: Bindings::from_names(KEYS, PAD),
+            #[cfg(feature = "gamepad")]
+            gamepads,
+            #[cfg(feature = "remote")]
+            remote,
+            filtered: Vec::new(),
+        })
+    }
+
+    /// Drains pending events into the game's controller and hotkeys.
+    /// Returns false once the user asked to quit.
+    pub fn handle_input(&mut self, game: &mut Game) -> bool {
+        while let Some(event) = self.event_pump.poll_event() {
+            let running = match event {
+                Event::Quit { .. } => false,
+                Event::DropFile { filename, .. } => {
+                    match config::open(game, &mut self.settings, Path::new(&filename)) {
+                        Ok(()) => {
+                            let _ = self.canvas.window_mut().set_title(&self.settings.title);
+                        },
+                        Err(e) => eprintln!("{}", e),
+                    }
+                    true
+                },
+                Event::KeyDown { keycode: Some(keycode), repeat, .. } => {
+                    self.input(game, Input::Key(keycode.name()), true, repeat)
+                },
+                Event::KeyUp { keycode: Some(keycode), .. } => {
+                    self.input(game, Input::Key(keycode.name()), false, false)
+                },
+                // with gilrs handling gamepads, SDL leaves them alone
+                Event::ControllerDeviceAdded { which, .. } if cfg!(not(feature = "gamepad")) => {
+                    if let Ok(controller) = self.controller_subsystem.open(which) {
+                        self.controllers.push(controller);
+                    }
+                    true
+                },
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    self.controllers.retain(|controller| controller.instance_id() != which);
+                    true
+                },
+                Event::ControllerButtonDown { button, .. } => self.input(game, Input::Pad(button.string()), true, false),
+                Event::ControllerButtonUp { button, .. } => self.input(game, Input::Pad(button.string()), false, false),
+                _ => true,
+            };
+
+            if !running {
+                return false;
+            }
+        }
+
+        #[cfg(feature = "gamepad")]
+        {
+            let result = self.gamepads.poll(game, &mut self.settings);
+            if !self.carry_out(result) {
+                return false;
+            }
+        }
+
+        #[cfg(feature = "remote")]
+        if let Some(remote) = self.remote.as_mut() {
+            let result = remote.poll(game, &mut self.settings);
+            if !self.carry_out(result) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // looks up what `input` is bound to and carries it out; false to quit
+    fn input(&mut self, game: &mut Game, input: Input, pressed: bool, repeat: bool) -> bool {
+        let Some(action) = self.settings.action(&input).or_else(|| self.defaults.action(&input)) else {
+            return true;
+        };
+
+        let result = input::perform(game, &mut self.settings, action, pressed, repeat);
+        self.carry_out(result)
+    }
+
+    // does what only the window can for an input; false to quit
+    fn carry_out(&mut self, result: Result<Option<Hotkey>, String>) -> bool {
+        match result {
+            Ok(Some(Hotkey::Fullscreen)) => {
+                if let Err(e) = toggle_fullscreen(self.canvas.window_mut()) {
+                    eprintln!("fullscreen failed: {}", e);
+                }
+                true
+            },
+            Ok(Some(_)) => false,
+            Ok(None) => true,
+            Err(e) => {
+                eprintln!("{}", e);
+                false
+            }
+        }
+    }
+
+    /// Filters an RGB24 frame, streams it into the texture and shows it.
+    pub fn present(&mut self, frame: &[u8]) -> Result<(), String> {
+        let filter = self.settings.filter;
+        if filter != self.texture_filter {
+            self.texture = create_texture(&self.canvas, filter)?;
+            self.texture_filter = filter;
+        }
+
+        filter.apply(frame, SCREEN_WIDTH, SCREEN_HEIGHT, &mut self.filtered);
+        self.texture
+            .update(None, &self.filtered, (SCREEN_WIDTH * filter.factor()) as usize * 3)
+            .map_err(|e| e.to_string())?;
+
+        let (width, height) = self.canvas.output_size()?;
+        let viewport = self.settings.viewport(width, height);
+        let target = Rect::new(viewport.x, viewport.y, viewport.width, viewport.height);
+
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+        self.canvas.copy(&self.texture, None, target)?;
+        self.canvas.present();
+
+        Ok(())
+    }
+}
+
+fn create_texture(canvas: &Canvas<Window>, filter: Filter) -> Result<Texture, String> {
+    canvas
+        .texture_creator()
+        .create_texture_streaming(
+            PixelFormatEnum::RGB24,
+            SCREEN_WIDTH * filter.factor(),
+            SCREEN_HEIGHT * filter.factor(),
+        )
+        .map_err(|e| e.to_string())
+}
+
+fn toggle_fullscreen(window: &mut Window) -> Result<(), String> {
+    let next = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+
+    window.set_fullscreen(next)
+}
+
+// built-in bindings by SDL's key names; VS System coin slots are on 5 and
+// 6 and the service button on 9, as in MAME
+const KEYS: &[(&str, Action)] = &[
+    ("Up", Action::Button(Button::Up)),
+    ("Down", Action::Button(Button::Down)),
+    ("Left", Action::Button(Button::Left)),
+    ("Right", Action::Button(Button::Right)),
+    ("X", Action::Button(Button::A)),
+    ("Z", Action::Button(Button::B)),
+    ("Return", Action::Button(Button::Start)),
+    ("Right Shift", Action::Button(Button::Select)),
+    ("5", Action::Coin(0)),
+    ("6", Action::Coin(1)),
+    ("9", Action::Service),
+    ("Escape", Action::Hotkey(Hotkey::Quit)),
+    ("F11", Action::Hotkey(Hotkey::Fullscreen)),
+    ("F12", Action::Hotkey(Hotkey::Screenshot)),
+    ("F10", Action::Hotkey(Hotkey::Clip)),
+    ("F9", Action::Hotkey(Hotkey::Record)),
+    ("=", Action::Hotkey(Hotkey::Faster)),
+    ("-", Action::Hotkey(Hotkey::Slower)),
+    ("Backspace", Action::Hotkey(Hotkey::NormalSpeed)),
+    ("Tab", Action::Hotkey(Hotkey::FastForward)),
+    ("P", Action::Hotkey(Hotkey::Pause)),
+    ("\\", Action::Hotkey(Hotkey::Advance)),
+    (";", Action::Hotkey(Hotkey::StepBack)),
+    ("]", Action::Hotkey(Hotkey::StepOver)),
+    ("[", Action::Hotkey(Hotkey::StepOut)),
+    ("F7", Action::Hotkey(Hotkey::Filter)),
+    ("F6", Action::Hotkey(Hotkey::Stats)),
+    ("F8", Action::Hotkey(Hotkey::InputDisplay)),
+    ("F2", Action::Hotkey(Hotkey::SaveState)),
+    ("F3", Action::Hotkey(Hotkey::LoadState)),
+    ("F4", Action::Hotkey(Hotkey::Reset)),
+    ("F5", Action::Hotkey(Hotkey::PowerCycle)),
+];
+
+// gamepad buttons by SDL's names for them
+const PAD: &[(&str, Action)] = &[
+    ("dpup", Action::Button(Button::Up)),
+    ("dpdown", Action::Button(Button::Down)),
+    ("dpleft", Action::Button(Button::Left)),
+    ("dpright", Action::Button(Button::Right)),
+    ("a", Action::Button(Button::A)),
+    ("b", Action::Button(Button::B)),
+    ("x", Action::Button(Button::B)),
+    ("start", Action::Button(Button::Start)),
+    ("back", Action::Button(Button::Select)),
+];
@@ -0,0 +1,47 @@
+#[cfg(feature = "remote")]
+use std::net::SocketAddr;
+
+use crate::clip::ClipFormat;
+use crate::filter::Filter;
+use crate::input::{Action, Bindings, Input};
+use crate::pacing::Pacing;
+
+/// Frontend options shared by the SDL and winit windows.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub title: String,
+    /// Window size as a multiple of the screen.
+    pub scale: u32,
+    /// Rows hidden at the top and bottom of screenshots.
+    pub overscan: u32,
+    /// Bindings from the command line. They take priority over the
+    /// frontend's built-in ones.
+    pub bindings: Bindings,
+    /// Bindings from the running game's config, which take priority over
+    /// both.
+    pub game_bindings: Bindings,
+    pub clip_format: ClipFormat,
+    pub fullscreen: bool,
+    /// Scale only by whole multiples, leaving black bars.
+    pub integer_scale: bool,
+    /// Stretch to the 8:7 pixel aspect ratio of a TV.
+    pub aspect_correction: bool,
+    pub filter: Filter,
+    pub pacing: Pacing,
+    /// How far an analog stick has to lean, from 0 to 1, to press a
+    /// direction.
+    #[cfg(feature = "gamepad")]
+    pub deadzone: f32,
+    /// Gamepads to give each player, by part of their name.
+    #[cfg(feature = "gamepad")]
+    pub gamepads: Vec<(usize, String)>,
+    /// Where to serve remote control, if anywhere.
+    #[cfg(feature = "remote")]
+    pub remote: Option<SocketAddr>,
+}
+
+impl Settings {
+    pub fn action(&self, input: &Input) -> Option<Action> {
+        self.game_bindings.action(input).or_else(|| self.bindings.action(input))
+    }
+}
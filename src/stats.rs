@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::game::SCREEN_WIDTH;
+
+/// Frames the figures cover: one per column of the graph.
+const WINDOW: usize = SCREEN_WIDTH as usize;
+
+// rows the graph may fill at the top of the screen; a full bar is a whole
+// frame's time budget
+const GRAPH_HEIGHT: u32 = 4;
+
+const FAST: [u8; 3] = [80, 220, 100];
+const TIGHT: [u8; 3] = [240, 200, 60];
+const LATE: [u8; 3] = [240, 60, 60];
+
+/// Timing of the most recent frames, for diagnosing slowdowns.
+pub struct FrameStats {
+    // time spent emulating each frame, oldest first
+    emulation: VecDeque<Duration>,
+    // when each of those frames finished
+    finished: VecDeque<Instant>,
+}
+
+impl FrameStats {
+    pub fn new() -> FrameStats {
+        FrameStats {
+            emulation: VecDeque::with_capacity(WINDOW),
+            finished: VecDeque::with_capacity(WINDOW),
+        }
+    }
+
+    pub fn record(&mut self, emulation: Duration) {
+        if self.emulation.len() == WINDOW {
+            self.emulation.pop_front();
+            self.finished.pop_front();
+        }
+        self.emulation.push_back(emulation);
+        self.finished.push_back(Instant::now());
+    }
+
+    pub fn clear(&mut self) {
+        self.emulation.clear();
+        self.finished.clear();
+    }
+
+    /// Frames finished per second of wall time; 0 until there are two.
+    pub fn fps(&self) -> f64 {
+        match (self.finished.front(), self.finished.back()) {
+            (Some(first), Some(last)) if last > first => {
+                (self.finished.len() - 1) as f64 / (*last - *first).as_secs_f64()
+            },
+            _ => 0.0,
+        }
+    }
+
+    pub fn average_emulation(&self) -> Duration {
+        let total: Duration = self.emulation.iter().sum();
+        total / self.emulation.len().max(1) as u32
+    }
+
+    pub fn worst_emulation(&self) -> Duration {
+        self.emulation.iter().max().copied().unwrap_or_default()
+    }
+
+    /// Draws the emulation time of each recent frame as a bar along the top
+    /// of an RGB24 screen, newest on the right. A full bar means the frame
+    /// used its whole `budget`; bars turn yellow past half and red past it.
+    pub fn draw(&self, screen: &mut [u8], budget: Duration) {
+        let skip = WINDOW - self.emulation.len();
+        for (i, &time) in self.emulation.iter().enumerate() {
+            let load = time.as_secs_f64() / budget.as_secs_f64();
+            let color = match load {
+                load if load > 1.0 => LATE,
+                load if load > 0.5 => TIGHT,
+                _ => FAST,
+            };
+
+            let height = ((load * GRAPH_HEIGHT as f64).ceil() as u32).clamp(1, GRAPH_HEIGHT);
+            let x = (skip + i) as u32;
+            for y in GRAPH_HEIGHT - height..GRAPH_HEIGHT {
+                let offset = ((y * SCREEN_WIDTH + x) * 3) as usize;
+                screen[offset..offset + 3].copy_from_slice(&color);
+            }
+        }
+    }
+}
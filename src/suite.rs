@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use nes_core::blargg::{self, Verdict};
+use nes_core::cartridge;
+
+use crate::cli::EmuArgs;
+use crate::game::Game;
+use crate::headless::{self, Until};
+
+/// The rules file looked for in the suite's directory.
+const RULES: &str = "suite.txt";
+
+/// How a test ROM says it passed.
+#[derive(Copy, Clone, Debug)]
+enum Rule {
+    /// blargg's status byte at `$6000`.
+    Blargg,
+    /// The screen reaches this CRC32.
+    Hash(u32),
+    /// Memory matches, as for `test --until`.
+    Until(Until),
+}
+
+impl Rule {
+    fn parse(text: &str) -> Result<Rule, String> {
+        match text.split_once(':') {
+            _ if text == "blargg" => Ok(Rule::Blargg),
+            Some(("hash", hash)) => {
+                let hash = u32::from_str_radix(hash, 16).map_err(|_| format!("bad hash '{}'", hash))?;
+                Ok(Rule::Hash(hash))
+            },
+            _ => Ok(Rule::Until(Until::parse(text)?)),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Rule::Blargg => "blargg".to_string(),
+            Rule::Hash(hash) => format!("hash:{:08X}", hash),
+            Rule::Until(Until::Memory(addr, value)) => format!("{:04X}={:02X}", addr, value),
+        }
+    }
+}
+
+enum Outcome {
+    Passed,
+    Failed(String),
+    TimedOut,
+    Error(String),
+}
+
+impl Outcome {
+    fn name(&self) -> &'static str {
+        match self {
+            Outcome::Passed => "passed",
+            Outcome::Failed(_) => "failed",
+            Outcome::TimedOut => "timed out",
+            Outcome::Error(_) => "error",
+        }
+    }
+
+    fn detail(&self) -> &str {
+        match self {
+            Outcome::Failed(detail) | Outcome::Error(detail) => detail,
+            Outcome::Passed | Outcome::TimedOut => "",
+        }
+    }
+}
+
+struct RomResult {
+    rom: String,
+    rule: Rule,
+    outcome: Outcome,
+    frames: u64,
+}
+
+/// Runs every .nes file under `dir` headless and reports which passed, to
+/// track accuracy across a whole test suite.
+///
+/// ROMs pass by blargg's status byte unless the rules file says otherwise.
+/// Each of its lines is `ROM RULE [FRAMES]`, with the ROM relative to the
+/// directory; `#` starts a comment. The rule is `blargg`, `hash:CRC32`
+/// for a screen the ROM shows on success, or `ADDR=VALUE` for memory it
+/// sets, and FRAMES overrides how long the ROM gets before it times out.
+///
+/// With `report`, the results are also written there as CSV.
+///
+/// Exits with 0 when every ROM passes and 2 otherwise.
+pub fn run(dir: &Path, rules: Option<&Path>, emu: &EmuArgs, frames: u64, report: Option<&Path>) -> Result<ExitCode, String> {
+    let default_rules = dir.join(RULES);
+    let rules = match rules {
+        Some(path) => parse(path)?,
+        None if default_rules.is_file() => parse(&default_rules)?,
+        None => BTreeMap::new(),
+    };
+
+    let mut roms = Vec::new();
+    headless::collect_roms(dir, &mut roms)?;
+    for rom in rules.keys() {
+        if !roms.contains(rom) {
+            return Err(format!("{}: not in the suite", rom.display()));
+        }
+    }
+
+    let mut results = Vec::new();
+    for rom in &roms {
+        let (rule, limit) = rules.get(rom).copied().unwrap_or((Rule::Blargg, None));
+        let limit = limit.unwrap_or(frames);
+        let name = rom.strip_prefix(dir).unwrap_or(rom).display().to_string();
+
+        let (outcome, run) = match crate::load_game(Some(rom), emu) {
+            Ok((mut game, _)) => check(&mut game, rule, limit),
+            Err(e) => (Outcome::Error(e), 0),
+        };
+
+        match outcome.detail() {
+            "" => println!("{}: {} after {} frames", name, outcome.name(), run),
+            detail => println!("{}: {} after {} frames: {}", name, outcome.name(), run, detail),
+        }
+        results.push(RomResult { rom: name, rule, outcome, frames: run });
+    }
+
+    let count = |name: &str| results.iter().filter(|result| result.outcome.name() == name).count();
+    let passed = count("passed");
+    println!(
+        "{}/{} passed, {} failed, {} timed out, {} errors",
+        passed,
+        results.len(),
+        count("failed"),
+        count("timed out"),
+        count("error")
+    );
+
+    if let Some(path) = report {
+        fs::write(path, csv(&results)).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+
+    Ok(if passed == results.len() { ExitCode::SUCCESS } else { ExitCode::from(2) })
+}
+
+// each ROM's rule and frame limit, by path
+fn parse(path: &Path) -> Result<BTreeMap<PathBuf, (Rule, Option<u64>)>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let base = path.parent().unwrap_or(Path::new("."));
+    let mut rules = BTreeMap::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let (rom, rule, frames) = match fields[..] {
+            [rom, rule] => (rom, rule, None),
+            [rom, rule, frames] => (rom, rule, Some(frames)),
+            _ => return Err(format!("{}: line {}: expected ROM RULE [FRAMES]", path.display(), i + 1)),
+        };
+
+        let rule = Rule::parse(rule).map_err(|e| format!("{}: line {}: {}", path.display(), i + 1, e))?;
+        let frames = frames
+            .map(|frames| frames.parse().map_err(|_| format!("{}: line {}: bad frame count '{}'", path.display(), i + 1, frames)))
+            .transpose()?;
+        rules.insert(base.join(rom), (rule, frames));
+    }
+
+    Ok(rules)
+}
+
+// runs one ROM until its rule decides, returning the frames it took
+fn check(game: &mut Game, rule: Rule, frames: u64) -> (Outcome, u64) {
+    // the same seed every run, as for regress
+    game.seed_rng(0);
+
+    if let Rule::Blargg = rule {
+        return match blargg::run(&mut game.nes, frames) {
+            Ok(report) => {
+                let outcome = match report.verdict {
+                    Verdict::Passed => Outcome::Passed,
+                    Verdict::Failed(code) => {
+                        let message = report.message.split_whitespace().collect::<Vec<_>>().join(" ");
+                        Outcome::Failed(format!("code {}: {}", code, message))
+                    },
+                    Verdict::TimedOut => Outcome::TimedOut,
+                };
+                (outcome, report.frames)
+            },
+            Err(e) => (Outcome::Error(e.to_string()), 0),
+        };
+    }
+
+    let mut run = 0;
+    while run < frames {
+        let running = match game.run_frame() {
+            Ok(running) => running,
+            Err(e) => return (Outcome::Error(e.to_string()), run),
+        };
+        run += 1;
+
+        let reached = match rule {
+            Rule::Hash(hash) => cartridge::crc32(game.screen()) == hash,
+            Rule::Until(until) => until.reached(game),
+            Rule::Blargg => unreachable!(),
+        };
+        if reached {
+            return (Outcome::Passed, run);
+        }
+        if !running || game.nes.debugger.is_stopped() {
+            return (Outcome::Failed(format!("stopped at ${:04X}", game.nes.cpu.program_counter)), run);
+        }
+    }
+
+    (Outcome::TimedOut, run)
+}
+
+fn csv(results: &[RomResult]) -> String {
+    let quote = |field: &str| format!("\"{}\"", field.replace('"', "\"\""));
+
+    let mut out = String::from("rom,rule,result,frames,detail\n");
+    for result in results {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{}",
+            quote(&result.rom),
+            result.rule.name(),
+            result.outcome.name(),
+            result.frames,
+            quote(result.outcome.detail())
+        );
+    }
+    out
+}
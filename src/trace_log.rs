@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use nes_core::trace::{TraceEntry, TraceSink};
+
+const MEGABYTE: u64 = 1024 * 1024;
+
+/// A CPU trace written to disk, gzipped when the path ends in `.gz`.
+///
+/// With a split size, the trace goes to numbered files next to the path,
+/// `trace.1.log`, `trace.2.log` and so on, starting the next one whenever
+/// one holds that many megabytes of trace text. Only the newest `keep` of
+/// them are left on disk, if set.
+pub struct TraceLog {
+    path: PathBuf,
+    gzip: bool,
+    split: Option<u64>,
+    keep: Option<usize>,
+    out: Option<Box<dyn Write + Send>>,
+    written: u64,
+    // the number of the last split file, and those still on disk
+    number: usize,
+    files: VecDeque<PathBuf>,
+}
+
+impl TraceLog {
+    pub fn create(path: &Path, split_megabytes: Option<u64>, keep: Option<usize>) -> Result<TraceLog, String> {
+        let gzip = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz"));
+        let mut log = TraceLog {
+            path: path.to_path_buf(),
+            gzip,
+            split: split_megabytes.map(|megabytes| megabytes.max(1) * MEGABYTE),
+            keep,
+            out: None,
+            written: 0,
+            number: 0,
+            files: VecDeque::new(),
+        };
+
+        log.next_file()?;
+        Ok(log)
+    }
+
+    // `trace.log.gz` becomes `trace.3.log.gz`
+    fn numbered(&self, number: usize) -> PathBuf {
+        let name = self.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let name = match name.split_once('.') {
+            Some((stem, extensions)) => format!("{}.{}.{}", stem, number, extensions),
+            None => format!("{}.{}", name, number),
+        };
+        self.path.with_file_name(name)
+    }
+
+    fn next_file(&mut self) -> Result<(), String> {
+        // dropping the encoder writes the gzip trailer
+        if let Some(mut out) = self.out.take() {
+            out.flush().map_err(|e| e.to_string())?;
+        }
+
+        self.number += 1;
+        let path = match self.split {
+            Some(_) => self.numbered(self.number),
+            None => self.path.clone(),
+        };
+        let file = File::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let file = BufWriter::new(file);
+
+        self.out = Some(if self.gzip {
+            Box::new(GzEncoder::new(file, Compression::fast()))
+        } else {
+            Box::new(file)
+        });
+        self.written = 0;
+        self.files.push_back(path);
+
+        if let Some(keep) = self.keep {
+            while self.files.len() > keep.max(1) {
+                if let Some(old) = self.files.pop_front() {
+                    let _ = fs::remove_file(old);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TraceSink for TraceLog {
+    fn trace(&mut self, entry: &TraceEntry) {
+        if self.split.is_some_and(|split| self.written >= split) {
+            if let Err(e) = self.next_file() {
+                eprintln!("trace stopped: {}", e);
+                self.out = None;
+            }
+        }
+
+        let Some(out) = self.out.as_mut() else {
+            return;
+        };
+        let line = format!("{}\n", entry);
+        let _ = out.write_all(line.as_bytes());
+        self.written += line.len() as u64;
+    }
+}
@@ -0,0 +1,78 @@
+use crate::game::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::settings::Settings;
+
+// NES pixels are a little wider than tall on a TV
+const PIXEL_ASPECT: f64 = 8.0 / 7.0;
+
+/// Where the screen lands inside a window of the given size.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Settings {
+    /// Width of the screen once the pixel aspect correction is applied, in
+    /// unscaled pixels.
+    pub fn display_width(&self) -> f64 {
+        if self.aspect_correction {
+            SCREEN_WIDTH as f64 * PIXEL_ASPECT
+        } else {
+            SCREEN_WIDTH as f64
+        }
+    }
+
+    /// The initial window size.
+    pub fn window_size(&self) -> (u32, u32) {
+        ((self.display_width() * self.scale as f64).round() as u32, SCREEN_HEIGHT * self.scale)
+    }
+
+    /// Fits the screen into the output, centered with black bars. With
+    /// integer scaling only whole multiples of the screen height are used.
+    pub fn viewport(&self, output_width: u32, output_height: u32) -> Viewport {
+        let display_width = self.display_width();
+        let mut scale = (output_width as f64 / display_width).min(output_height as f64 / SCREEN_HEIGHT as f64);
+
+        if self.integer_scale {
+            scale = scale.floor().max(1.0);
+        }
+
+        let width = (display_width * scale).round() as u32;
+        let height = (SCREEN_HEIGHT as f64 * scale).round() as u32;
+
+        Viewport {
+            x: (output_width as i32 - width as i32) / 2,
+            y: (output_height as i32 - height as i32) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+/// Nearest-neighbor copies an RGB24 image of `src_width` x `src_height`
+/// into the viewport of an RGBA buffer, painting everything around it
+/// black.
+#[cfg(feature = "winit")]
+pub fn blit_rgba(src: &[u8], src_width: u32, src_height: u32, out: &mut [u8], out_width: u32, viewport: Viewport) {
+    let (width, height) = (viewport.width as i32, viewport.height as i32);
+
+    for (y, row) in out.chunks_exact_mut(out_width as usize * 4).enumerate() {
+        let dy = y as i32 - viewport.y;
+
+        for (x, rgba) in row.chunks_exact_mut(4).enumerate() {
+            let dx = x as i32 - viewport.x;
+
+            if (0..width).contains(&dx) && (0..height).contains(&dy) {
+                let src_x = (dx as u32 * src_width / viewport.width) as usize;
+                let src_y = (dy as u32 * src_height / viewport.height) as usize;
+                let i = (src_y * src_width as usize + src_x) * 3;
+                rgba[..3].copy_from_slice(&src[i..i + 3]);
+            } else {
+                rgba[..3].fill(0);
+            }
+            rgba[3] = 0xFF;
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::sync::Arc;
+
+use nes_core::controller::Button;
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::PhysicalKey;
+use winit::window::{Fullscreen, Window, WindowId};
+
+use crate::config;
+#[cfg(feature = "gamepad")]
+use crate::gamepad::Gamepads;
+use crate::game::{Game, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::input::{self, Action, Bindings, Hotkey, Input};
+use crate::pacing::{FrameTimer, Pacing};
+#[cfg(feature = "remote")]
+use crate::remote::Remote;
+use crate::settings::Settings;
+use crate::viewport;
+
+/// Pure-Rust frontend: winit for the window and input, pixels for drawing.
+struct App {
+    game: Game,
+    settings: Settings,
+    // what keys do when no binding in `settings` says otherwise
+    defaults: Bindings,
+    #[cfg(feature = "gamepad")]
+    gamepads: Gamepads,
+    #[cfg(feature = "remote")]
+    remote: Option<Remote>,
+    timer: FrameTimer,
+    filtered: Vec<u8>,
+    window: Option<Arc<Window>>,
+    pixels: Option<Pixels<'static>>,
+    error: Option<String>,
+}
+
+pub fn run(game: Game, settings: Settings) -> Result<(), String> {
+    let event_loop = EventLoop::new().map_err(|e| e.to_string())?;
+    #[cfg(feature = "gamepad")]
+    let gamepads = Gamepads::new(settings.deadzone, settings.gamepads.clone())?;
+    #[cfg(feature = "remote")]
+    let remote = settings.remote.map(Remote::start).transpose()?;
+
+    let mut app = App {
+        game,
+        settings,
+        defaults: Bindings::from_names(KEYS, &[]),
+        #[cfg(feature = "gamepad")]
+        gamepads,
+        #[cfg(feature = "remote")]
+        remote,
+        timer: FrameTimer::new(),
+        filtered: Vec::new(),
+        window: None,
+        pixels: None,
+        error: None,
+    };
+
+    event_loop.run_app(&mut app).map_err(|e| e.to_string())?;
+    if let Err(e) = app.game.flush_saves() {
+        eprintln!("failed to write save: {}", e);
+    }
+    if let Err(e) = app.game.finish_movie() {
+        eprintln!("{}", e);
+    }
+    if let Err(e) = app.game.write_events() {
+        eprintln!("{}", e);
+    }
+    crate::print_stats(&app.game.nes);
+
+    match app.error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+
+        let (width, height) = self.settings.window_size();
+        let fullscreen = self.settings.fullscreen.then_some(Fullscreen::Borderless(None));
+        let attributes = Window::default_attributes()
+            .with_title(self.settings.title.clone())
+            .with_inner_size(LogicalSize::new(width, height))
+            .with_fullscreen(fullscreen);
+
+        let window = match event_loop.create_window(attributes) {
+            Ok(window) => Arc::new(window),
+            Err(e) => return self.fail(event_loop, e.to_string()),
+        };
+
+        // the buffer matches the window so scaling and letterboxing are
+        // done by our own blit
+        let size = window.inner_size();
+        let surface = SurfaceTexture::new(size.width, size.height, window.clone());
+        let pixels = PixelsBuilder::new(size.width.max(1), size.height.max(1), surface)
+            .enable_vsync(self.settings.pacing.vsync())
+            .build();
+        match pixels {
+            Ok(pixels) => self.pixels = Some(pixels),
+            Err(e) => return self.fail(event_loop, e.to_string()),
+        }
+
+        window.request_redraw();
+        self.window = Some(window);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            // bindings use winit's KeyCode names, e.g. "KeyX" or "Space"
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { physical_key: PhysicalKey::Code(code), state, repeat, .. },
+                ..
+            } => {
+                let input = Input::Key(format!("{:?}", code));
+                let Some(action) = self.settings.action(&input).or_else(|| self.defaults.action(&input)) else {
+                    return;
+                };
+
+                let pressed = state == ElementState::Pressed;
+                let result = input::perform(&mut self.game, &mut self.settings, action, pressed, repeat);
+                self.carry_out(event_loop, result);
+            },
+            WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                if let Some(pixels) = self.pixels.as_mut() {
+                    let resized = pixels
+                        .resize_surface(size.width, size.height)
+                        .and_then(|_| pixels.resize_buffer(size.width, size.height));
+
+                    if let Err(e) = resized {
+                        self.error = Some(e.to_string());
+                        event_loop.exit();
+                    }
+                }
+            },
+            WindowEvent::DroppedFile(path) => match config::open(&mut self.game, &mut self.settings, &path) {
+                Ok(()) => {
+                    if let Some(window) = self.window.as_ref() {
+                        window.set_title(&self.settings.title);
+                    }
+                },
+                Err(e) => eprintln!("{}", e),
+            },
+            WindowEvent::RedrawRequested => self.redraw(event_loop),
+            _ => {/* do nothing */}
+        }
+    }
+}
+
+impl App {
+    fn fail(&mut self, event_loop: &ActiveEventLoop, error: String) {
+        self.error = Some(error);
+        event_loop.exit();
+    }
+
+    // does what only the window can for an input
+    fn carry_out(&mut self, event_loop: &ActiveEventLoop, result: Result<Option<Hotkey>, String>) {
+        match result {
+            Ok(Some(Hotkey::Fullscreen)) => self.toggle_fullscreen(),
+            Ok(Some(_)) => event_loop.exit(),
+            Ok(None) => {},
+            Err(e) => self.fail(event_loop, e),
+        }
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        if let Some(window) = self.window.as_ref() {
+            let next = match window.fullscreen() {
+                Some(_) => None,
+                None => Some(Fullscreen::Borderless(None)),
+            };
+            window.set_fullscreen(next);
+        }
+    }
+
+    fn redraw(&mut self, event_loop: &ActiveEventLoop) {
+        if self.settings.pacing == Pacing::Timer {
+            self.timer.wait(self.game.nes.region().frame_rate());
+        }
+
+        #[cfg(feature = "gamepad")]
+        {
+            let result = self.gamepads.poll(&mut self.game, &mut self.settings);
+            self.carry_out(event_loop, result);
+            if event_loop.exiting() {
+                return;
+            }
+        }
+
+        #[cfg(feature = "remote")]
+        if let Some(remote) = self.remote.as_mut() {
+            let result = remote.poll(&mut self.game, &mut self.settings);
+            self.carry_out(event_loop, result);
+            if event_loop.exiting() {
+                return;
+            }
+        }
+
+        match self.game.tick() {
+            Ok(true) => {},
+            Ok(false) => return event_loop.exit(),
+            Err(e) => return self.fail(event_loop, e.to_string()),
+        }
+
+        let Some(pixels) = self.pixels.as_mut() else {
+            return;
+        };
+
+        let size = pixels.texture().size();
+        let viewport = self.settings.viewport(size.width, size.height);
+        let filter = self.settings.filter;
+        filter.apply(self.game.screen(), SCREEN_WIDTH, SCREEN_HEIGHT, &mut self.filtered);
+        viewport::blit_rgba(
+            &self.filtered,
+            SCREEN_WIDTH * filter.factor(),
+            SCREEN_HEIGHT * filter.factor(),
+            pixels.frame_mut(),
+            size.width,
+            viewport,
+        );
+
+        // with vsync, rendering blocks, which paces the emulation
+        if let Err(e) = pixels.render() {
+            return self.fail(event_loop, e.to_string());
+        }
+
+        if let Some(window) = self.window.as_ref() {
+            window.request_redraw();
+        }
+    }
+}
+
+// built-in bindings by winit's KeyCode names; VS System coin slots are on
+// 5 and 6 and the service button on 9, as in MAME
+const KEYS: &[(&str, Action)] = &[
+    ("ArrowUp", Action::Button(Button::Up)),
+    ("ArrowDown", Action::Button(Button::Down)),
+    ("ArrowLeft", Action::Button(Button::Left)),
+    ("ArrowRight", Action::Button(Button::Right)),
+    ("KeyX", Action::Button(Button::A)),
+    ("KeyZ", Action::Button(Button::B)),
+    ("Enter", Action::Button(Button::Start)),
+    ("ShiftRight", Action::Button(Button::Select)),
+    ("Digit5", Action::Coin(0)),
+    ("Digit6", Action::Coin(1)),
+    ("Digit9", Action::Service),
+    ("Escape", Action::Hotkey(Hotkey::Quit)),
+    ("F11", Action::Hotkey(Hotkey::Fullscreen)),
+    ("F12", Action::Hotkey(Hotkey::Screenshot)),
+    ("F10", Action::Hotkey(Hotkey::Clip)),
+    ("F9", Action::Hotkey(Hotkey::Record)),
+    ("Equal", Action::Hotkey(Hotkey::Faster)),
+    ("Minus", Action::Hotkey(Hotkey::Slower)),
+    ("Backspace", Action::Hotkey(Hotkey::NormalSpeed)),
+    ("Tab", Action::Hotkey(Hotkey::FastForward)),
+    ("KeyP", Action::Hotkey(Hotkey::Pause)),
+    ("Backslash", Action::Hotkey(Hotkey::Advance)),
+    ("Semicolon", Action::Hotkey(Hotkey::StepBack)),
+    ("BracketRight", Action::Hotkey(Hotkey::StepOver)),
+    ("BracketLeft", Action::Hotkey(Hotkey::StepOut)),
+    ("F7", Action::Hotkey(Hotkey::Filter)),
+    ("F6", Action::Hotkey(Hotkey::Stats)),
+    ("F8", Action::Hotkey(Hotkey::InputDisplay)),
+    ("F2", Action::Hotkey(Hotkey::SaveState)),
+    ("F3", Action::Hotkey(Hotkey::LoadState)),
+    ("F4", Action::Hotkey(Hotkey::Reset)),
+    ("F5", Action::Hotkey(Hotkey::PowerCycle)),
+];